@@ -0,0 +1,128 @@
+//! Minimal Esplora (mempool.space / blockstream.info) REST client.
+//!
+//! Esplora only exposes the chain tip, so templates built from it are
+//! coinbase-only and can't be submitted; see `miner_core::rpc` for that.
+
+use std::time::Duration;
+use serde_json::Value;
+
+/// Blocking client for the handful of Esplora endpoints the miner needs.
+pub struct EsploraClient {
+    base_url: String,
+    agent: ureq::Agent,
+}
+
+/// A block header as reported by Esplora.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockSummary {
+    /// Block hash in display byte order (hex).
+    pub hash: String,
+    /// Block height.
+    pub height: u32,
+    /// Compact difficulty bits of the block.
+    pub bits: u32,
+    /// Block timestamp.
+    pub timestamp: u32,
+}
+
+impl EsploraClient {
+    /// Create a client for an API base URL such as `https://mempool.space/api`.
+    pub fn new(base_url: &str) -> Self {
+        let agent = ureq::AgentBuilder::new()
+            .timeout(Duration::from_secs(30))
+            .build();
+
+        EsploraClient {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            agent,
+        }
+    }
+
+    /// Hash of the current tip (display order hex).
+    pub fn tip_hash(&self) -> Result<String, String> {
+        Ok(self.get(&format!("{}/blocks/tip/hash", self.base_url))?.trim().to_string())
+    }
+
+    /// Hash of the block at `height` (display order hex).
+    pub fn block_hash(&self, height: u32) -> Result<String, String> {
+        Ok(self.get(&format!("{}/block-height/{}", self.base_url, height))?.trim().to_string())
+    }
+
+    /// Fetch the tip block.
+    pub fn tip(&self) -> Result<BlockSummary, String> {
+        self.block(&self.tip_hash()?)
+    }
+
+    /// Fetch a block's height, bits and timestamp.
+    pub fn block(&self, hash: &str) -> Result<BlockSummary, String> {
+        let block: Value = serde_json::from_str(&self.get(&format!("{}/block/{}", self.base_url, hash))?)
+            .map_err(|e| format!("Invalid block JSON: {}", e))?;
+
+        let height = block["height"].as_u64().ok_or("Block is missing 'height'")? as u32;
+        let bits = block["bits"].as_u64().ok_or("Block is missing 'bits'")? as u32;
+        let timestamp = block["timestamp"].as_u64().ok_or("Block is missing 'timestamp'")? as u32;
+
+        Ok(BlockSummary { hash: hash.to_string(), height, bits, timestamp })
+    }
+
+    fn get(&self, url: &str) -> Result<String, String> {
+        self.agent
+            .get(url)
+            .call()
+            .map_err(|e| format!("GET {} failed: {}", url, e))?
+            .into_string()
+            .map_err(|e| format!("GET {} failed: {}", url, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_tip_from_stub_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let hash = "00000000000000000002a7c4c1e48d76c5a37902165a270156b7a8d72728a054";
+
+        let server = std::thread::spawn(move || {
+            for _ in 0..3 {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                }
+
+                let body = if request_line.contains("/blocks/tip/hash") || request_line.contains("/block-height/874944") {
+                    hash.to_string()
+                } else {
+                    assert!(request_line.contains(&format!("/api/block/{}", hash)));
+                    format!(
+                        r#"{{"id":"{}","height":875000,"bits":386076387,"timestamp":1734100000,"version":536870912}}"#,
+                        hash
+                    )
+                };
+                let mut stream = stream;
+                write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body)
+                    .unwrap();
+            }
+        });
+
+        let client = EsploraClient::new(&format!("http://{}/api/", addr));
+        let tip = client.tip().unwrap();
+        assert_eq!(
+            tip,
+            BlockSummary { hash: hash.to_string(), height: 875000, bits: 0x17030ee3, timestamp: 1734100000 }
+        );
+        assert_eq!(client.block_hash(874944).unwrap(), hash);
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,272 @@
+//! Headless multi-threaded miner built on `miner-core`.
+//!
+//! Work comes from a Bitcoin Core node (`getblocktemplate`), an Esplora
+//! API (coinbase-only templates on the current tip) or a Stratum V1 pool.
+//! The nonce space is split across OS threads that each run `mine_batch`.
+
+mod esplora;
+mod pool;
+mod solo;
+mod stats;
+mod workers;
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::mpsc;
+use std::time::Duration;
+use clap::{Parser, ValueEnum};
+use miner_core::rpc::{RpcAuth, RpcClient};
+use miner_core::coinbase::DEFAULT_COINBASE_TAG;
+use miner_core::simd::Backend;
+use miner_core::{difficulty_to_target, validate_address, Network, Recipient};
+use crate::esplora::EsploraClient;
+use crate::solo::TemplateSource;
+use crate::workers::{WorkerPool, DEFAULT_BATCH_SIZE};
+
+/// Where mining work comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Source {
+    /// A Bitcoin Core node over JSON-RPC (solo mining with fees).
+    Rpc,
+    /// An Esplora API such as mempool.space (coinbase-only, can't submit blocks).
+    Esplora,
+    /// A Stratum V1 pool.
+    Stratum,
+}
+
+#[derive(Debug, Parser)]
+#[command(name = "scratch-off-miner", version, about = "Headless Bitcoin miner")]
+struct Args {
+    /// Address that receives the block reward (and the default pool worker name).
+    #[arg(long)]
+    address: String,
+
+    /// Network to mine on (mainnet, testnet4, regtest or signet).
+    #[arg(long, default_value = "mainnet")]
+    network: String,
+
+    /// Where to get work from.
+    #[arg(long, value_enum, default_value_t = Source::Rpc)]
+    source: Source,
+
+    /// Number of mining threads (defaults to the number of CPUs).
+    #[arg(long)]
+    threads: Option<usize>,
+
+    /// Nonces each thread hashes between checks for new work.
+    #[arg(long, default_value_t = DEFAULT_BATCH_SIZE)]
+    batch_size: u32,
+
+    /// Seconds between status lines.
+    #[arg(long, default_value_t = 10)]
+    stats_interval: u64,
+
+    /// Seconds between checks for a new chain tip when solo mining.
+    #[arg(long, default_value_t = 5)]
+    poll_interval: u64,
+
+    /// Pool difficulty for a hash to count as a share when solo mining.
+    #[arg(long, default_value_t = 1.0)]
+    share_difficulty: f64,
+
+    /// Split the solo block reward: ADDRESS:WEIGHT for a weighted share of
+    /// what is left after fixed amounts, ADDRESS=SATS for a fixed amount.
+    /// Repeat for each recipient; replaces --address as the payee.
+    #[arg(long)]
+    payout: Vec<String>,
+
+    /// Tag written into the coinbase scriptSig when solo mining.
+    #[arg(long, default_value_t = String::from_utf8_lossy(DEFAULT_COINBASE_TAG).into_owned())]
+    coinbase_tag: String,
+
+    /// Extra bytes after the coinbase tag, in hex (e.g. a hash commitment).
+    #[arg(long, default_value = "")]
+    coinbase_data: String,
+
+    /// BIP320 version bits to roll when solo mining, in hex (0 to disable).
+    #[arg(long, default_value = "1fffe000", value_parser = parse_hex_u32)]
+    version_mask: u32,
+
+    /// Bitcoin Core RPC URL (defaults to localhost on the network's RPC port).
+    #[arg(long)]
+    rpc_url: Option<String>,
+
+    /// RPC user name (otherwise the cookie file is used).
+    #[arg(long)]
+    rpc_user: Option<String>,
+
+    /// RPC password.
+    #[arg(long, default_value = "")]
+    rpc_password: String,
+
+    /// Path to the node's .cookie file (defaults to ~/.bitcoin).
+    #[arg(long)]
+    rpc_cookie: Option<PathBuf>,
+
+    /// Esplora API base URL (defaults to mempool.space for the network).
+    #[arg(long)]
+    esplora_url: Option<String>,
+
+    /// Stratum pool address as host:port (a stratum+tcp:// prefix is accepted).
+    #[arg(long)]
+    pool: Option<String>,
+
+    /// Pool worker name (defaults to the reward address).
+    #[arg(long)]
+    worker: Option<String>,
+
+    /// Pool worker password.
+    #[arg(long, default_value = "x")]
+    pool_password: String,
+}
+
+fn main() -> ExitCode {
+    match run(Args::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let network = Network::from_str(&args.network)
+        .ok_or_else(|| format!("Unknown network '{}'", args.network))?;
+    let address = validate_address(&args.address, network)
+        .map_err(|e| format!("Invalid address: {}", e))?;
+    let payouts = args
+        .payout
+        .iter()
+        .map(|payout| parse_payout(payout, network))
+        .collect::<Result<Vec<_>, _>>()?;
+    let coinbase_data =
+        hex::decode(&args.coinbase_data).map_err(|e| format!("Invalid --coinbase-data hex: {}", e))?;
+
+    let threads = args
+        .threads
+        .unwrap_or_else(|| std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1))
+        .max(1);
+    let stats_interval = Duration::from_secs(args.stats_interval.max(1));
+
+    println!(
+        "Mining on {} to {} with {} threads ({} SHA256)",
+        network.display_name(),
+        address.display,
+        threads,
+        Backend::detect().name()
+    );
+
+    let (events_tx, events) = mpsc::channel();
+    let workers = WorkerPool::spawn(threads, args.batch_size.max(1), events_tx);
+
+    match args.source {
+        Source::Stratum => {
+            let pool = args.pool.ok_or("--pool is required with --source stratum")?;
+            let pool = pool.strip_prefix("stratum+tcp://").unwrap_or(&pool).to_string();
+            let worker = args.worker.unwrap_or_else(|| address.display.clone());
+            pool::run(&pool, &worker, &args.pool_password, workers, events, stats_interval)
+        }
+        Source::Rpc | Source::Esplora => {
+            let source = if args.source == Source::Rpc {
+                let url = args
+                    .rpc_url
+                    .unwrap_or_else(|| format!("http://127.0.0.1:{}", network.default_rpc_port()));
+                let auth = match args.rpc_user {
+                    Some(user) => RpcAuth::UserPass(user, args.rpc_password),
+                    None => RpcAuth::CookieFile(args.rpc_cookie.unwrap_or_else(|| default_cookie_path(network))),
+                };
+                TemplateSource::Rpc(RpcClient::new(&url, auth))
+            } else {
+                let url = args.esplora_url.unwrap_or_else(|| network.mempool_api_url().to_string());
+                TemplateSource::Esplora(EsploraClient::new(&url))
+            };
+
+            let settings = solo::Settings {
+                stats_interval,
+                poll_interval: Duration::from_secs(args.poll_interval.max(1)),
+                share_target: difficulty_to_target(args.share_difficulty),
+                version_mask: args.version_mask,
+                payouts,
+                coinbase_tag: args.coinbase_tag.into_bytes(),
+                coinbase_data,
+            };
+            solo::run(source, network, address, workers, events, settings)
+        }
+    }
+}
+
+/// Parse a hex number with an optional 0x prefix.
+fn parse_hex_u32(s: &str) -> Result<u32, String> {
+    let digits = s.strip_prefix("0x").unwrap_or(s);
+    u32::from_str_radix(digits, 16).map_err(|e| format!("invalid hex '{}': {}", s, e))
+}
+
+/// Parse a `--payout` recipient: ADDRESS:WEIGHT or ADDRESS=SATS.
+fn parse_payout(s: &str, network: Network) -> Result<Recipient, String> {
+    let invalid = || format!("Invalid payout '{}': expected ADDRESS:WEIGHT or ADDRESS=SATS", s);
+    // Addresses never contain either separator, so the last one is it
+    let split = s.rfind([':', '=']).ok_or_else(invalid)?;
+    let (address, value, fixed) = (&s[..split], &s[split + 1..], s.as_bytes()[split] == b'=');
+    let value: u64 = value.parse().map_err(|_| invalid())?;
+    let address = validate_address(address, network).map_err(|e| format!("Invalid payout address: {}", e))?;
+    Ok(if fixed { Recipient::amount(address, value) } else { Recipient::weight(address, value) })
+}
+
+/// The cookie file Bitcoin Core writes in its default data directory.
+fn default_cookie_path(network: Network) -> PathBuf {
+    let mut path = std::env::var_os("HOME").map(PathBuf::from).unwrap_or_default();
+    path.push(".bitcoin");
+    match network {
+        Network::Mainnet => {}
+        Network::Testnet4 => path.push("testnet4"),
+        Network::Regtest => path.push("regtest"),
+        Network::Signet => path.push("signet"),
+    }
+    path.push(".cookie");
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+    use miner_core::network::VERSION_ROLLING_MASK;
+    use miner_core::PayoutShare;
+
+    #[test]
+    fn test_args_definition() {
+        Args::command().debug_assert();
+
+        let args = Args::try_parse_from([
+            "scratch-off-miner",
+            "--address",
+            "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq",
+            "--source",
+            "stratum",
+            "--pool",
+            "stratum+tcp://pool.example:3333",
+            "--threads",
+            "4",
+        ])
+        .unwrap();
+        assert_eq!(args.source, Source::Stratum);
+        assert_eq!(args.threads, Some(4));
+        assert_eq!(args.network, "mainnet");
+        assert_eq!(args.version_mask, VERSION_ROLLING_MASK);
+        assert_eq!(parse_hex_u32("0x00006000"), Ok(0x6000));
+        assert!(parse_hex_u32("xyz").is_err());
+
+        let payout = parse_payout("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq:3", Network::Mainnet).unwrap();
+        assert_eq!(payout.share, PayoutShare::Weight(3));
+        let payout = parse_payout("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq=50000", Network::Mainnet).unwrap();
+        assert_eq!(payout.share, PayoutShare::Amount(50_000));
+        assert!(parse_payout("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq", Network::Mainnet).is_err());
+
+        // Both separators: the last one splits, so the address is reported
+        let error = parse_payout("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq:3=50000", Network::Mainnet).unwrap_err();
+        assert!(error.starts_with("Invalid payout address"), "{}", error);
+        let error = parse_payout("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq=50000:x", Network::Mainnet).unwrap_err();
+        assert!(error.starts_with("Invalid payout '"), "{}", error);
+    }
+}
@@ -0,0 +1,171 @@
+//! Pool mining over Stratum V1.
+
+use std::sync::mpsc::Receiver;
+use std::time::{Duration, Instant};
+use miner_core::{bits_to_target, hash_meets_target};
+use miner_core::hash::{hash_to_display_hex, reverse_bytes};
+use miner_core::stratum::v1::{Job as PoolJob, Notification, Share, StratumClient, StratumError};
+use crate::stats::Stats;
+use crate::workers::{Event, Job, WorkerPool};
+
+/// User agent sent in `mining.subscribe`.
+const USER_AGENT: &str = concat!("scratch-off-miner/", env!("CARGO_PKG_VERSION"));
+
+/// How long to wait for pool messages before servicing the mining threads.
+const POLL_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// How long to wait for the pool to answer a share submission.
+const SUBMIT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The job the threads are currently mining, with its coinbase choices.
+struct Active {
+    generation: u64,
+    job: PoolJob,
+    extranonce2: Vec<u8>,
+}
+
+struct Session {
+    client: StratumClient,
+    workers: WorkerPool,
+    stats: Stats,
+    job: Option<PoolJob>,
+    active: Option<Active>,
+    extranonce2_counter: u64,
+    exhausted: usize,
+}
+
+/// Connect to `pool` and mine until the connection fails.
+pub fn run(
+    pool: &str,
+    worker: &str,
+    password: &str,
+    workers: WorkerPool,
+    events: Receiver<Event>,
+    stats_interval: Duration,
+) -> Result<(), String> {
+    let mut client = StratumClient::connect(pool).map_err(|e| e.to_string())?;
+    let subscription = client.subscribe(USER_AGENT).map_err(|e| e.to_string())?;
+    client.authorize(worker, password).map_err(|e| e.to_string())?;
+    client.set_read_timeout(Some(POLL_TIMEOUT)).map_err(|e| e.to_string())?;
+    println!(
+        "Connected to {} as {} (extranonce1 {}, extranonce2 size {})",
+        pool,
+        worker,
+        hex::encode(&subscription.extranonce1),
+        subscription.extranonce2_size
+    );
+
+    let mut session = Session {
+        client,
+        workers,
+        stats: Stats::new(),
+        job: None,
+        active: None,
+        extranonce2_counter: 0,
+        exhausted: 0,
+    };
+    let mut last_stats = Instant::now();
+
+    loop {
+        while let Ok(event) = events.try_recv() {
+            session.handle_event(event)?;
+        }
+
+        match session.client.next_notification() {
+            Ok(Notification::Notify(job)) => {
+                println!("New job {} (clean: {})", job.job_id, job.clean_jobs);
+                session.job = Some(job);
+                session.extranonce2_counter = 0;
+                session.publish()?;
+            }
+            Ok(Notification::SetDifficulty(difficulty)) => {
+                println!("Share difficulty set to {}", difficulty);
+                session.publish()?;
+            }
+            Ok(Notification::SetExtranonce(_)) => session.publish()?,
+            Ok(Notification::Unsupported(_)) => {}
+            Err(StratumError::Timeout) => {}
+            Err(e) => return Err(e.to_string()),
+        }
+
+        if last_stats.elapsed() >= stats_interval {
+            last_stats = Instant::now();
+            println!("{}", session.stats.report(session.workers.hashes()));
+        }
+    }
+}
+
+impl Session {
+    /// Give the current job, with a fresh extranonce2, to the mining threads.
+    fn publish(&mut self) -> Result<(), String> {
+        let Some(job) = &self.job else { return Ok(()) };
+        let subscription = self.client.subscription().map_err(|e| e.to_string())?;
+        let extranonce2 = subscription.extranonce2(self.extranonce2_counter);
+        let header = job.header(&subscription.extranonce1, &extranonce2);
+
+        // Share target from the pool difficulty; blocks are checked separately.
+        // Version rolling is not negotiated, so shares keep the job's version.
+        let generation = self.workers.publish(Job {
+            header: header.serialize_without_nonce(),
+            target: self.client.share_target(),
+            share_target: [0u8; 32],
+            version_mask: 0,
+        });
+
+        self.active = Some(Active { generation, job: job.clone(), extranonce2 });
+        self.exhausted = 0;
+        Ok(())
+    }
+
+    fn handle_event(&mut self, event: Event) -> Result<(), String> {
+        let Some(active) = &self.active else { return Ok(()) };
+
+        match event {
+            Event::Found { generation, nonce, hash, meets_target, .. } if generation == active.generation => {
+                if !meets_target {
+                    return Ok(());
+                }
+                self.stats.shares += 1;
+                self.stats.record_hash(&hash);
+                if hash_meets_target(&reverse_bytes(&hash), &bits_to_target(active.job.bits)) {
+                    println!("Block found: {}", hash_to_display_hex(&hash));
+                }
+
+                let share = Share {
+                    job_id: active.job.job_id.clone(),
+                    extranonce2: active.extranonce2.clone(),
+                    time: active.job.time,
+                    nonce,
+                };
+                self.submit(&share)?;
+            }
+            Event::Exhausted { generation } if generation == active.generation => {
+                self.exhausted += 1;
+                if self.exhausted == self.workers.len() {
+                    self.extranonce2_counter += 1;
+                    self.publish()?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn submit(&mut self, share: &Share) -> Result<(), String> {
+        self.client.set_read_timeout(Some(SUBMIT_TIMEOUT)).map_err(|e| e.to_string())?;
+        let result = self.client.submit(share);
+        self.client.set_read_timeout(Some(POLL_TIMEOUT)).map_err(|e| e.to_string())?;
+
+        match result {
+            Ok(true) => self.stats.accepted += 1,
+            Ok(false) => self.stats.rejected += 1,
+            Err(StratumError::Rpc { code, message }) => {
+                self.stats.rejected += 1;
+                eprintln!("Share rejected ({}): {}", code, message);
+            }
+            Err(StratumError::Timeout) => eprintln!("No answer from the pool for share {:08x}", share.nonce),
+            Err(e) => return Err(e.to_string()),
+        }
+        Ok(())
+    }
+}
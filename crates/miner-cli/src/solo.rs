@@ -0,0 +1,228 @@
+//! Solo mining on templates from a node or an Esplora API.
+
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use miner_core::hash::hash_to_display_hex;
+use miner_core::rpc::RpcClient;
+use miner_core::difficulty::next_work_required;
+use miner_core::{BlockHeader, BlockInfo, BlockTemplate, CoinbaseBuilder, Network, Recipient, ValidatedAddress};
+use crate::esplora::EsploraClient;
+use crate::stats::Stats;
+use crate::workers::{Event, Job, WorkerPool};
+
+/// Where solo templates come from.
+pub enum TemplateSource {
+    /// `getblocktemplate` from Bitcoin Core; found blocks are submitted.
+    Rpc(RpcClient),
+    /// The Esplora chain tip; found blocks are printed for manual submission.
+    Esplora(EsploraClient),
+}
+
+impl TemplateSource {
+    /// Build a template for the next block paying `address` (or the
+    /// settings' payouts).
+    fn template(
+        &self,
+        network: Network,
+        address: &ValidatedAddress,
+        settings: &Settings,
+        extra_nonce: u64,
+    ) -> Result<BlockTemplate, String> {
+        match self {
+            TemplateSource::Rpc(rpc) => {
+                let gbt = rpc.get_block_template().map_err(|e| e.to_string())?;
+                let builder = settings.coinbase(network, gbt.height, address).with_extra_nonce(extra_nonce.to_le_bytes());
+                BlockTemplate::from_getblocktemplate(network, gbt, builder).map_err(|e| e.to_string())
+            }
+            TemplateSource::Esplora(esplora) => {
+                let tip = esplora.tip()?;
+                let start_height = tip.height - tip.height % network.difficulty_adjustment_interval();
+                let start = match start_height == tip.height {
+                    true => tip.clone(),
+                    false => esplora.block(&esplora.block_hash(start_height)?)?,
+                };
+
+                // The tip's bits are wrong after a retarget and under the
+                // testnet4 20-minute rule
+                let mut info = BlockInfo::from_api_data(&tip.hash, tip.height, tip.bits)?;
+                info.bits = next_work_required(
+                    network,
+                    tip.height,
+                    &BlockHeader::new([0u8; 32], [0u8; 32], tip.timestamp, tip.bits),
+                    &BlockHeader::new([0u8; 32], [0u8; 32], start.timestamp, start.bits),
+                    info.timestamp,
+                );
+                let builder = settings.coinbase(network, info.height, address).with_extra_nonce(extra_nonce.to_le_bytes());
+                BlockTemplate::new(
+                    network,
+                    info.height,
+                    info.tip_hash,
+                    info.bits,
+                    info.timestamp,
+                    builder,
+                    network.block_subsidy(info.height),
+                )
+                .map_err(|e| e.to_string())
+            }
+        }
+    }
+
+    /// Hash of the current chain tip (internal byte order).
+    fn tip_hash(&self) -> Result<[u8; 32], String> {
+        match self {
+            TemplateSource::Rpc(rpc) => rpc.get_best_block_hash().map_err(|e| e.to_string()),
+            TemplateSource::Esplora(esplora) => {
+                let hex = esplora.tip_hash()?;
+                let info = BlockInfo::from_api_data(&hex, 0, 0)?;
+                Ok(info.tip_hash)
+            }
+        }
+    }
+
+    /// Hand a solved block to the network.
+    ///
+    /// Blocks that break a consensus rule are refused before they reach the
+    /// node, with every violation listed.
+    fn submit(&self, template: &BlockTemplate) -> Result<(), String> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as u32)
+            .unwrap_or(0);
+        let violations = template.validate(now);
+        if !violations.is_empty() {
+            let reasons: Vec<String> = violations.iter().map(|v| v.to_string()).collect();
+            return Err(format!("block is invalid: {}", reasons.join("; ")));
+        }
+
+        match self {
+            TemplateSource::Rpc(rpc) => rpc
+                .submit_block(&template.serialize_block_hex())
+                .map_err(|e| e.to_string()),
+            TemplateSource::Esplora(_) => Err(format!(
+                "Esplora cannot relay blocks; submit it with `bitcoin-cli submitblock {}`",
+                template.serialize_block_hex()
+            )),
+        }
+    }
+}
+
+/// Timing and share settings for a solo session.
+pub struct Settings {
+    /// Time between status lines.
+    pub stats_interval: Duration,
+    /// Time between checks for a new chain tip.
+    pub poll_interval: Duration,
+    /// Hashes at or below this target (big-endian) count as shares.
+    pub share_target: [u8; 32],
+    /// BIP320 version bits the threads may roll.
+    pub version_mask: u32,
+    /// Recipients splitting the reward (empty to pay it all to the address).
+    pub payouts: Vec<Recipient>,
+    /// Tag and data for the coinbase scriptSig.
+    pub coinbase_tag: Vec<u8>,
+    pub coinbase_data: Vec<u8>,
+}
+
+impl Settings {
+    /// A coinbase builder with the session's payouts and tag.
+    fn coinbase(&self, network: Network, height: u32, address: &ValidatedAddress) -> CoinbaseBuilder {
+        CoinbaseBuilder::new(network, height, address.clone())
+            .with_payouts(self.payouts.clone())
+            .with_tag(self.coinbase_tag.clone())
+            .with_script_data(self.coinbase_data.clone())
+    }
+}
+
+/// Mine until an unrecoverable error occurs.
+pub fn run(
+    source: TemplateSource,
+    network: Network,
+    address: ValidatedAddress,
+    workers: WorkerPool,
+    events: Receiver<Event>,
+    settings: Settings,
+) -> Result<(), String> {
+    let mut stats = Stats::new();
+    let mut extra_nonce = 0u64;
+    let mut template = source.template(network, &address, &settings, extra_nonce)?;
+    println!(
+        "Mining block {} with {} transactions for {} sats",
+        template.height,
+        template.transactions.len(),
+        template.reward
+    );
+    let mut generation = publish(&workers, &template, &settings);
+    let mut exhausted = 0;
+    let mut last_poll = Instant::now();
+    let mut last_stats = Instant::now();
+
+    loop {
+        match events.recv_timeout(Duration::from_millis(250)) {
+            Ok(Event::Found { generation: g, version, nonce, hash, meets_target }) if g == generation => {
+                stats.shares += 1;
+                if stats.record_hash(&hash) {
+                    println!("New best hash: {}", hash_to_display_hex(&hash));
+                }
+
+                if meets_target {
+                    template.header.version = version;
+                    template.header.nonce = nonce;
+                    println!("Block found at height {}: {}", template.height, hash_to_display_hex(&hash));
+                    match source.submit(&template) {
+                        Ok(()) => println!("Block accepted by the node"),
+                        Err(e) => eprintln!("Block not submitted: {}", e),
+                    }
+
+                    extra_nonce = 0;
+                    template = source.template(network, &address, &settings, extra_nonce)?;
+                    generation = publish(&workers, &template, &settings);
+                    exhausted = 0;
+                }
+            }
+            Ok(Event::Exhausted { generation: g }) if g == generation => {
+                exhausted += 1;
+                if exhausted == workers.len() {
+                    // Every thread ran out of nonces and versions: roll the coinbase extranonce
+                    extra_nonce += 1;
+                    template.update_extra_nonce(extra_nonce.to_le_bytes());
+                    generation = publish(&workers, &template, &settings);
+                    exhausted = 0;
+                }
+            }
+            // Results for a job that has since been replaced
+            Ok(_) => {}
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return Err("All mining threads exited".into()),
+        }
+
+        if last_poll.elapsed() >= settings.poll_interval {
+            last_poll = Instant::now();
+            match source.tip_hash() {
+                Ok(tip) if tip != template.header.prev_block_hash => {
+                    extra_nonce = 0;
+                    template = source.template(network, &address, &settings, extra_nonce)?;
+                    generation = publish(&workers, &template, &settings);
+                    exhausted = 0;
+                    println!("New tip, now mining block {}", template.height);
+                }
+                Ok(_) => {}
+                Err(e) => eprintln!("Failed to check the chain tip: {}", e),
+            }
+        }
+
+        if last_stats.elapsed() >= settings.stats_interval {
+            last_stats = Instant::now();
+            println!("{}", stats.report(workers.hashes()));
+        }
+    }
+}
+
+/// Hand the template's header to the mining threads.
+fn publish(workers: &WorkerPool, template: &BlockTemplate, settings: &Settings) -> u64 {
+    workers.publish(Job {
+        header: template.header.serialize_without_nonce(),
+        target: template.target,
+        share_target: settings.share_target,
+        version_mask: settings.version_mask,
+    })
+}
@@ -0,0 +1,126 @@
+//! Hash rate, share and best-hash bookkeeping for the status line.
+
+use std::time::Instant;
+use miner_core::difficulty::{format_difficulty, hash_difficulty};
+use miner_core::hash::{hash_to_display_hex, reverse_bytes};
+
+/// Running statistics for one mining session.
+pub struct Stats {
+    start: Instant,
+    last_report: Instant,
+    last_hashes: u64,
+    /// Shares found (hashes meeting the share criterion).
+    pub shares: u64,
+    /// Shares the pool accepted.
+    pub accepted: u64,
+    /// Shares the pool rejected.
+    pub rejected: u64,
+    /// Best hash so far (internal byte order).
+    best: Option<[u8; 32]>,
+}
+
+impl Stats {
+    /// Start a new session.
+    pub fn new() -> Self {
+        let now = Instant::now();
+        Stats {
+            start: now,
+            last_report: now,
+            last_hashes: 0,
+            shares: 0,
+            accepted: 0,
+            rejected: 0,
+            best: None,
+        }
+    }
+
+    /// Record a found hash. Returns true if it is the best so far.
+    pub fn record_hash(&mut self, hash: &[u8; 32]) -> bool {
+        let better = match &self.best {
+            None => true,
+            Some(best) => reverse_bytes(hash) < reverse_bytes(best),
+        };
+        if better {
+            self.best = Some(*hash);
+        }
+        better
+    }
+
+    /// The best hash so far in display order with its pool difficulty.
+    pub fn best(&self) -> Option<(String, f64)> {
+        self.best.map(|hash| (hash_to_display_hex(&hash), hash_difficulty(&hash)))
+    }
+
+    /// Build a status line and reset the interval hash rate.
+    pub fn report(&mut self, total_hashes: u64) -> String {
+        let now = Instant::now();
+        let interval = now.duration_since(self.last_report).as_secs_f64();
+        let rate = if interval > 0.0 {
+            (total_hashes - self.last_hashes) as f64 / interval
+        } else {
+            0.0
+        };
+        self.last_report = now;
+        self.last_hashes = total_hashes;
+
+        let best = match self.best() {
+            Some((hex, difficulty)) => format!("{} (diff {})", hex, format_difficulty(difficulty)),
+            None => "-".to_string(),
+        };
+
+        let mut line = format!(
+            "[{:>7.0}s] {} | shares {}",
+            now.duration_since(self.start).as_secs_f64(),
+            format_hash_rate(rate),
+            self.shares
+        );
+        if self.accepted + self.rejected > 0 {
+            line.push_str(&format!(" ({} accepted, {} rejected)", self.accepted, self.rejected));
+        }
+        line.push_str(&format!(" | best {}", best));
+        line
+    }
+}
+
+/// Format a hash rate with an appropriate unit.
+pub fn format_hash_rate(hash_rate: f64) -> String {
+    if hash_rate >= 1_000_000_000.0 {
+        format!("{:.2} GH/s", hash_rate / 1_000_000_000.0)
+    } else if hash_rate >= 1_000_000.0 {
+        format!("{:.2} MH/s", hash_rate / 1_000_000.0)
+    } else if hash_rate >= 1_000.0 {
+        format!("{:.2} KH/s", hash_rate / 1_000.0)
+    } else {
+        format!("{:.2} H/s", hash_rate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_hash_rate() {
+        assert_eq!(format_hash_rate(12.0), "12.00 H/s");
+        assert_eq!(format_hash_rate(1_500.0), "1.50 KH/s");
+        assert_eq!(format_hash_rate(25_000_000.0), "25.00 MH/s");
+        assert_eq!(format_hash_rate(3_210_000_000.0), "3.21 GH/s");
+    }
+
+    #[test]
+    fn test_best_hash_uses_display_order() {
+        let mut stats = Stats::new();
+        let mut worse = [0u8; 32];
+        worse[31] = 0x10; // display order starts with 0x10
+        worse[0] = 0x00;
+        let mut better = [0xFF; 32];
+        better[31] = 0x00;
+        better[30] = 0x01;
+
+        assert!(stats.record_hash(&worse));
+        assert!(stats.record_hash(&better));
+        assert!(!stats.record_hash(&worse));
+        assert_eq!(stats.best().unwrap().1, hash_difficulty(&better));
+        assert!(stats.best().unwrap().1 > hash_difficulty(&worse));
+    }
+}
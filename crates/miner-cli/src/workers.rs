@@ -0,0 +1,227 @@
+//! Mining threads that split the nonce space of a shared job.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use miner_core::HeaderSearch;
+
+/// Nonces hashed between checks for a new job.
+pub const DEFAULT_BATCH_SIZE: u32 = 1 << 18;
+
+/// Shares reported per batch before a thread checks for a new job.
+const MAX_SHARES_PER_BATCH: usize = 64;
+
+/// Work handed to every mining thread.
+#[derive(Debug, Clone)]
+pub struct Job {
+    /// The 76-byte header without the nonce.
+    pub header: [u8; 76],
+    /// Hashes at or below this target (big-endian) are reported as found.
+    pub target: [u8; 32],
+    /// Hashes at or below this target (big-endian) count as shares.
+    pub share_target: [u8; 32],
+    /// BIP320 version bits to roll once a thread's nonces run out (0 for none).
+    pub version_mask: u32,
+}
+
+/// Something a mining thread wants the controller to know.
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// A share (or a hash meeting the job target) was found.
+    Found {
+        /// Generation of the job the nonce belongs to.
+        generation: u64,
+        /// The header version, which differs from the job's when rolled.
+        version: i32,
+        nonce: u32,
+        /// The hash in internal byte order.
+        hash: [u8; 32],
+        /// Whether the hash meets the job target.
+        meets_target: bool,
+    },
+    /// The thread searched its whole nonce partition, with every rolled
+    /// version, for this generation.
+    Exhausted { generation: u64 },
+}
+
+/// State shared between the controller and the mining threads.
+struct Shared {
+    /// The current job with its generation, so both are read together.
+    job: Mutex<Option<(u64, Arc<Job>)>>,
+    /// Copy of the current generation, checked between batches.
+    generation: AtomicU64,
+    hashes: AtomicU64,
+    stop: AtomicBool,
+}
+
+/// A pool of mining threads working on one job at a time.
+pub struct WorkerPool {
+    shared: Arc<Shared>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl WorkerPool {
+    /// Spawn `count` threads that report to `events`.
+    pub fn spawn(count: usize, batch_size: u32, events: Sender<Event>) -> Self {
+        let shared = Arc::new(Shared {
+            job: Mutex::new(None),
+            generation: AtomicU64::new(0),
+            hashes: AtomicU64::new(0),
+            stop: AtomicBool::new(false),
+        });
+
+        let threads = (0..count)
+            .map(|index| {
+                let shared = Arc::clone(&shared);
+                let events = events.clone();
+                thread::Builder::new()
+                    .name(format!("miner-{}", index))
+                    .spawn(move || run_worker(index, count, batch_size, &shared, &events))
+                    .expect("failed to spawn mining thread")
+            })
+            .collect();
+
+        WorkerPool { shared, threads }
+    }
+
+    /// Replace the current job. Returns the new job's generation.
+    ///
+    /// Threads abandon the previous job at their next batch boundary.
+    pub fn publish(&self, job: Job) -> u64 {
+        let mut current = self.shared.job.lock().unwrap();
+        let generation = self.shared.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        *current = Some((generation, Arc::new(job)));
+        generation
+    }
+
+    /// Total hashes computed by all threads so far.
+    pub fn hashes(&self) -> u64 {
+        self.shared.hashes.load(Ordering::Relaxed)
+    }
+
+    /// Number of mining threads.
+    pub fn len(&self) -> usize {
+        self.threads.len()
+    }
+}
+
+impl Drop for WorkerPool {
+    /// Stop all threads and wait for them to finish their current batch.
+    fn drop(&mut self) {
+        self.shared.stop.store(true, Ordering::SeqCst);
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// The nonce range `(start, count)` searched by thread `index` of `total`.
+///
+/// The 2^32 nonce space is split into contiguous, non-overlapping ranges;
+/// the last thread also takes the remainder.
+pub fn nonce_partition(index: usize, total: usize) -> (u32, u32) {
+    let span = (1u64 << 32) / total as u64;
+    let start = span * index as u64;
+    let end = if index + 1 == total { 1u64 << 32 } else { start + span };
+    // mine_batch works on u32 counts, so the final nonce of the space is skipped
+    (start as u32, (end - start).min(u32::MAX as u64 - start) as u32)
+}
+
+fn run_worker(index: usize, total: usize, batch_size: u32, shared: &Shared, events: &Sender<Event>) {
+    let (start, count) = nonce_partition(index, total);
+    let mut finished_generation = 0;
+
+    while !shared.stop.load(Ordering::Relaxed) {
+        let current = shared.job.lock().unwrap().clone();
+        let (generation, job) = match current {
+            Some((generation, job)) if generation != finished_generation => (generation, job),
+            _ => {
+                thread::sleep(Duration::from_millis(5));
+                continue;
+            }
+        };
+
+        let mut search = HeaderSearch::new(&job.header)
+            .with_version_mask(job.version_mask)
+            .with_nonce_range(start, count);
+        let mut exhausted = true;
+
+        while !search.is_exhausted() {
+            if shared.stop.load(Ordering::Relaxed) || shared.generation.load(Ordering::Relaxed) != generation {
+                exhausted = false;
+                break;
+            }
+
+            // Report every share, not just the batch's best
+            let mut shares = Vec::new();
+            let result = search.next_batch_shares(
+                &job.target,
+                &job.share_target,
+                batch_size,
+                MAX_SHARES_PER_BATCH,
+                |share| shares.push(share),
+            );
+            shared.hashes.fetch_add(result.hashes_computed, Ordering::Relaxed);
+
+            for share in shares {
+                let _ = events.send(Event::Found {
+                    generation,
+                    version: result.version,
+                    nonce: share.nonce,
+                    hash: share.hash,
+                    meets_target: share.block,
+                });
+            }
+        }
+
+        if exhausted {
+            let _ = events.send(Event::Exhausted { generation });
+        }
+        finished_generation = generation;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_nonce_partition_covers_space() {
+        for total in [1usize, 3, 4, 7, 16] {
+            let mut next = 0u64;
+            for index in 0..total {
+                let (start, count) = nonce_partition(index, total);
+                assert_eq!(start as u64, next);
+                next = start as u64 + count as u64;
+            }
+            assert_eq!(next, u32::MAX as u64);
+        }
+        assert_eq!(nonce_partition(1, 4), (0x4000_0000, 0x4000_0000));
+    }
+
+    #[test]
+    fn test_pool_finds_easy_target() {
+        let (tx, rx) = mpsc::channel();
+        let pool = WorkerPool::spawn(2, 1024, tx);
+
+        // About half of all hashes meet this target
+        let mut target = [0xFF; 32];
+        target[0] = 0x7F;
+        let generation = pool.publish(Job { header: [0x42; 76], target, share_target: [0u8; 32], version_mask: 0 });
+
+        let event = rx.recv_timeout(Duration::from_secs(10)).unwrap();
+        match event {
+            Event::Found { generation: g, version, meets_target, .. } => {
+                assert_eq!(g, generation);
+                assert_eq!(version, 0x4242_4242);
+                assert!(meets_target);
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+        assert!(pool.hashes() > 0);
+        drop(pool);
+    }
+}
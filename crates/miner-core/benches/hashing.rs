@@ -0,0 +1,60 @@
+//! Header hashing throughput: full double SHA256 vs. the precomputed midstate,
+//! and the SIMD backends against each other.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use miner_core::hash::{double_sha256, mine_batch, mine_batch_with, HeaderMidstate};
+use miner_core::simd::Backend;
+
+const BATCH: u32 = 4096;
+
+fn header_hashing(c: &mut Criterion) {
+    let header = [0x5Au8; 80];
+    let prefix: [u8; 76] = header[..76].try_into().unwrap();
+    let midstate = HeaderMidstate::new(&prefix);
+
+    let mut group = c.benchmark_group("header_hash");
+    group.throughput(Throughput::Elements(1));
+    group.bench_function("double_sha256", |b| b.iter(|| double_sha256(black_box(&header))));
+    group.bench_function("midstate", |b| b.iter(|| midstate.hash(black_box(0xDEADBEEF))));
+    group.finish();
+}
+
+fn batch_mining(c: &mut Criterion) {
+    let prefix = [0x5Au8; 76];
+    // Unreachable target so every nonce in the batch is hashed
+    let target = [0u8; 32];
+
+    let mut group = c.benchmark_group("mine_batch");
+    group.throughput(Throughput::Elements(BATCH as u64));
+    group.bench_function("midstate", |b| {
+        b.iter(|| mine_batch(black_box(&prefix), &target, &target, 0, BATCH))
+    });
+    group.bench_function("full_header", |b| {
+        b.iter(|| {
+            let mut header = [0u8; 80];
+            header[..76].copy_from_slice(black_box(&prefix));
+            (0..BATCH).fold(0u8, |acc, nonce| {
+                header[76..].copy_from_slice(&nonce.to_le_bytes());
+                acc ^ double_sha256(&header)[31]
+            })
+        })
+    });
+    group.finish();
+}
+
+fn backends(c: &mut Criterion) {
+    let prefix = [0x5Au8; 76];
+    let target = [0u8; 32];
+
+    let mut group = c.benchmark_group("backend");
+    group.throughput(Throughput::Elements(BATCH as u64));
+    for backend in Backend::available() {
+        group.bench_function(backend.name(), |b| {
+            b.iter(|| mine_batch_with(backend, black_box(&prefix), &target, &target, 0, BATCH))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, header_hashing, batch_mining, backends);
+criterion_main!(benches);
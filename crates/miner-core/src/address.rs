@@ -97,8 +97,9 @@ pub struct ValidatedAddress {
 pub fn validate_address(address: &str, expected_network: Network) -> Result<ValidatedAddress, AddressError> {
     let trimmed = address.trim();
 
-    // Try Bech32/Bech32m first (bc1.../tb1...)
-    if trimmed.to_lowercase().starts_with("bc1") || trimmed.to_lowercase().starts_with("tb1") {
+    // Try Bech32/Bech32m first (bc1.../tb1.../bcrt1...)
+    let lower = trimmed.to_lowercase();
+    if lower.starts_with("bc1") || lower.starts_with("tb1") || lower.starts_with("bcrt1") {
         return validate_bech32_address(trimmed, expected_network);
     }
 
@@ -132,7 +133,8 @@ fn validate_base58_address(address: &str, expected_network: Network) -> Result<V
         return Err(AddressError::InvalidFormat);
     }
 
-    // Determine address type and network
+    // Determine address type and network. Regtest shares testnet's version
+    // bytes, so those addresses are valid on either.
     let (address_type, network) = match version {
         0x00 => (AddressType::P2PKH, Network::Mainnet),
         0x05 => (AddressType::P2SH, Network::Mainnet),
@@ -140,6 +142,11 @@ fn validate_base58_address(address: &str, expected_network: Network) -> Result<V
         0xc4 => (AddressType::P2SH, Network::Testnet4),
         _ => return Err(AddressError::InvalidFormat),
     };
+    let network = if version == expected_network.p2pkh_version() || version == expected_network.p2sh_version() {
+        expected_network
+    } else {
+        network
+    };
 
     // Check network matches
     if network != expected_network {
@@ -187,10 +194,12 @@ fn validate_bech32_address(address: &str, expected_network: Network) -> Result<V
     // Decode Bech32
     let (hrp, data, variant) = bech32_decode(address)?;
 
-    // Check HRP matches network
+    // Check HRP matches network; testnet and signet share "tb"
     let network = match hrp.as_str() {
+        hrp if hrp == expected_network.bech32_hrp() => expected_network,
         "bc" => Network::Mainnet,
         "tb" => Network::Testnet4,
+        "bcrt" => Network::Regtest,
         _ => return Err(AddressError::InvalidBech32(format!("Unknown HRP: {}", hrp))),
     };
 
@@ -482,6 +491,39 @@ mod tests {
         assert_eq!(result.network, Network::Testnet4);
     }
 
+    #[test]
+    fn test_regtest_address() {
+        let address = "bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080";
+        let result = validate_address(address, Network::Regtest).unwrap();
+        assert_eq!(result.address_type, AddressType::P2WPKH);
+        assert_eq!(result.network, Network::Regtest);
+
+        // Base58 addresses are shared with testnet
+        let address = "mrCDrCybB6J1vRfbwM5hemdJz73FwDBC8r";
+        let result = validate_address(address, Network::Regtest).unwrap();
+        assert_eq!(result.address_type, AddressType::P2PKH);
+        assert_eq!(result.network, Network::Regtest);
+        assert_eq!(validate_address(address, Network::Testnet4).unwrap().network, Network::Testnet4);
+
+        // Bech32 addresses are not
+        let result = validate_address("tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx", Network::Regtest);
+        assert!(matches!(result, Err(AddressError::NetworkMismatch { .. })));
+    }
+
+    #[test]
+    fn test_signet_address() {
+        // Signet uses testnet's HRP and version bytes
+        let result = validate_address("tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx", Network::Signet).unwrap();
+        assert_eq!(result.address_type, AddressType::P2WPKH);
+        assert_eq!(result.network, Network::Signet);
+
+        let result = validate_address("mrCDrCybB6J1vRfbwM5hemdJz73FwDBC8r", Network::Signet).unwrap();
+        assert_eq!(result.network, Network::Signet);
+
+        let result = validate_address("bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080", Network::Signet);
+        assert!(matches!(result, Err(AddressError::NetworkMismatch { .. })));
+    }
+
     #[test]
     fn test_network_mismatch() {
         // Try to validate a mainnet address with testnet expected
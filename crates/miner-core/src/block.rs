@@ -1,11 +1,15 @@
 //! Bitcoin block header construction and serialization.
 
 use alloc::vec::Vec;
-use crate::coinbase::{CoinbaseBuilder, CoinbaseTransaction};
+use crate::coinbase::{CoinbaseBuilder, CoinbaseError, CoinbaseTransaction};
+use crate::coinbase::encode_varint;
+use crate::decode::decode_block;
 use crate::difficulty::bits_to_target;
+use crate::gbt::{GetBlockTemplate, TemplateError, TemplateTransaction};
 use crate::hash::double_sha256;
-use crate::merkle::compute_merkle_root;
+use crate::merkle::{compute_merkle_root, compute_witness_merkle_root};
 use crate::network::{Network, BLOCK_VERSION};
+use crate::signet::{SignetError, SignetSolution, SignetTxs};
 
 /// A Bitcoin block header (80 bytes).
 #[derive(Debug, Clone)]
@@ -107,6 +111,16 @@ pub struct BlockTemplate {
     pub height: u32,
     /// The total reward (subsidy + fees).
     pub reward: u64,
+    /// Transactions following the coinbase (empty for coinbase-only blocks).
+    pub transactions: Vec<TemplateTransaction>,
+    /// The earliest timestamp the block may use (0 if unknown).
+    pub min_time: u32,
+    /// Witness commitment script from the node, if it sent one; otherwise
+    /// the coinbase commits to the witness merkle root of the transactions.
+    pub default_witness_commitment: Option<Vec<u8>>,
+    /// The builder the coinbase came from, kept to rebuild it with a new
+    /// extra nonce.
+    pub coinbase_builder: CoinbaseBuilder,
 }
 
 impl BlockTemplate {
@@ -120,6 +134,8 @@ impl BlockTemplate {
     /// * `timestamp` - Block timestamp
     /// * `coinbase_builder` - Builder for the coinbase transaction
     /// * `reward` - Total block reward (subsidy + fees)
+    ///
+    /// Fails if the coinbase cannot pay out `reward` as the builder asks.
     pub fn new(
         network: Network,
         height: u32,
@@ -128,9 +144,9 @@ impl BlockTemplate {
         timestamp: u32,
         coinbase_builder: CoinbaseBuilder,
         reward: u64,
-    ) -> Self {
+    ) -> Result<Self, CoinbaseError> {
         // Build the coinbase transaction
-        let coinbase = coinbase_builder.build(reward);
+        let coinbase = coinbase_builder.build(reward)?;
 
         // Compute merkle root (for coinbase-only, it's just the txid)
         let merkle_root = compute_merkle_root(&[coinbase.txid]);
@@ -141,44 +157,153 @@ impl BlockTemplate {
         // Get the target
         let target = bits_to_target(bits);
 
-        BlockTemplate {
+        Ok(BlockTemplate {
             header,
             coinbase,
             target,
             network,
             height,
             reward,
+            transactions: Vec::new(),
+            min_time: 0,
+            default_witness_commitment: None,
+            coinbase_builder,
+        })
+    }
+
+    /// Create a block template from a parsed `getblocktemplate` response.
+    ///
+    /// The header takes the template's version, previous hash, bits and
+    /// current time; the coinbase pays out `coinbasevalue` and carries the
+    /// node's witness commitment, or one computed from the transactions'
+    /// wtxids if the node sent none. The builder must be for the template's
+    /// height.
+    pub fn from_getblocktemplate(
+        network: Network,
+        gbt: GetBlockTemplate,
+        coinbase_builder: CoinbaseBuilder,
+    ) -> Result<Self, TemplateError> {
+        if coinbase_builder.block_height() != gbt.height {
+            return Err(TemplateError::HeightMismatch {
+                template: gbt.height,
+                coinbase: coinbase_builder.block_height(),
+            });
         }
+
+        let coinbase_builder = match &gbt.default_witness_commitment {
+            Some(script) => coinbase_builder.with_witness_commitment_script(script.clone()),
+            None => {
+                let wtxids: Vec<[u8; 32]> = gbt.transactions.iter().map(|tx| tx.wtxid).collect();
+                coinbase_builder.with_witness_merkle_root(compute_witness_merkle_root(&wtxids))
+            }
+        };
+        let coinbase_builder = match &gbt.signet_challenge {
+            Some(challenge) => coinbase_builder.with_signet_challenge(challenge.clone()),
+            None => coinbase_builder,
+        };
+        let coinbase = coinbase_builder.build(gbt.coinbase_value).map_err(TemplateError::Coinbase)?;
+
+        let mut header = BlockHeader::new(gbt.prev_block_hash, [0u8; 32], gbt.cur_time, gbt.bits);
+        header.version = gbt.version;
+
+        let mut template = BlockTemplate {
+            header,
+            coinbase,
+            target: bits_to_target(gbt.bits),
+            network,
+            height: gbt.height,
+            reward: gbt.coinbase_value,
+            transactions: gbt.transactions,
+            min_time: gbt.min_time,
+            default_witness_commitment: gbt.default_witness_commitment,
+            coinbase_builder,
+        };
+        template.header.merkle_root = template.compute_merkle_root();
+
+        Ok(template)
+    }
+
+    /// Compute the merkle root over the coinbase and all template transactions.
+    fn compute_merkle_root(&self) -> [u8; 32] {
+        let mut txids = Vec::with_capacity(1 + self.transactions.len());
+        txids.push(self.coinbase.txid);
+        txids.extend(self.transactions.iter().map(|tx| tx.txid));
+        compute_merkle_root(&txids)
     }
 
     /// Update the extra nonce and rebuild the coinbase/merkle root.
     ///
     /// This is used when we've exhausted all nonce values and need to
     /// change the merkle root to continue mining.
-    pub fn update_extra_nonce(&mut self, extra_nonce: [u8; 8], coinbase_builder: CoinbaseBuilder) {
-        // Rebuild coinbase with new extra nonce
-        self.coinbase = coinbase_builder.with_extra_nonce(extra_nonce).build(self.reward);
+    ///
+    /// # Panics
+    ///
+    /// If `coinbase_builder` was replaced by one that cannot build a
+    /// coinbase for `reward`.
+    pub fn update_extra_nonce(&mut self, extra_nonce: [u8; 8]) {
+        // Rebuild coinbase with new extra nonce; everything else (including
+        // the node's witness commitment) stays in the stored builder
+        self.coinbase_builder = self.coinbase_builder.clone().with_extra_nonce(extra_nonce);
+        self.coinbase = self
+            .coinbase_builder
+            .build(self.reward)
+            .expect("the builder already built this reward, and the extra nonce has a fixed size");
 
         // Update merkle root
-        self.header.merkle_root = compute_merkle_root(&[self.coinbase.txid]);
+        self.header.merkle_root = self.compute_merkle_root();
 
         // Reset nonce
         self.header.nonce = 0;
     }
 
+    /// The transactions the signet solution must sign.
+    ///
+    /// Sign after the final extra nonce, version and timestamp are set; only
+    /// the nonce may change afterwards.
+    pub fn signet_txs(&self) -> Result<SignetTxs, SignetError> {
+        let challenge = self.coinbase_builder.signet_challenge().ok_or(SignetError::MissingChallenge)?;
+        let block = decode_block(&self.serialize_block()).map_err(SignetError::MalformedBlock)?;
+        SignetTxs::new(&block, challenge)
+    }
+
+    /// Put a signet solution into the coinbase and rebuild the merkle root.
+    ///
+    /// The solution is not part of what it signs, so `signet_txs` stays the
+    /// same.
+    ///
+    /// # Panics
+    ///
+    /// If `coinbase_builder` was replaced by one that cannot build a
+    /// coinbase for `reward`.
+    pub fn set_signet_solution(&mut self, solution: SignetSolution) {
+        self.coinbase_builder = self.coinbase_builder.clone().with_signet_solution(solution);
+        self.coinbase = self
+            .coinbase_builder
+            .build(self.reward)
+            .expect("the builder already built this reward, and the solution is not size-checked");
+        self.header.merkle_root = self.compute_merkle_root();
+        self.header.nonce = 0;
+    }
+
     /// Serialize the complete block for submission.
     pub fn serialize_block(&self) -> Vec<u8> {
-        let mut block = Vec::with_capacity(200);
+        let tx_bytes: usize = self.transactions.iter().map(|tx| tx.data.len()).sum();
+        let mut block = Vec::with_capacity(200 + tx_bytes);
 
         // Block header (80 bytes)
         block.extend_from_slice(&self.header.serialize());
 
-        // Transaction count (varint) - just the coinbase
-        block.push(0x01);
+        // Transaction count (varint) - coinbase plus template transactions
+        encode_varint(1 + self.transactions.len() as u64, &mut block);
 
         // Coinbase transaction (with witness)
         block.extend_from_slice(&self.coinbase.raw_tx_with_witness);
 
+        // Template transactions, in the order the node selected them
+        for tx in &self.transactions {
+            block.extend_from_slice(&tx.data);
+        }
+
         block
     }
 
@@ -259,6 +384,7 @@ fn current_timestamp() -> u32 {
 mod tests {
     use super::*;
     use crate::address::validate_address;
+    use crate::merkle::compute_witness_commitment;
 
     #[test]
     fn test_block_header_serialization() {
@@ -319,7 +445,8 @@ mod tests {
             timestamp,
             coinbase_builder,
             reward,
-        );
+        )
+        .unwrap();
 
         // Verify basic properties
         assert_eq!(template.height, height);
@@ -334,4 +461,94 @@ mod tests {
         let block_hex = template.serialize_block_hex();
         assert!(!block_hex.is_empty());
     }
+
+    #[test]
+    fn test_block_template_from_getblocktemplate() {
+        let network = Network::Testnet4;
+        let gbt = GetBlockTemplate::parse(crate::gbt::tests::TEMPLATE_JSON).unwrap();
+        let address = validate_address("tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx", network).unwrap();
+        let builder = CoinbaseBuilder::new(network, gbt.height, address);
+
+        let template = BlockTemplate::from_getblocktemplate(network, gbt.clone(), builder).unwrap();
+
+        assert_eq!(template.height, 101);
+        assert_eq!(template.reward, 5_000_004_000);
+        assert_eq!(template.min_time, 1_296_688_603);
+        assert_eq!(template.header.version, 0x2000_0000);
+        assert_eq!(template.header.timestamp, 1_700_000_000);
+        assert_eq!(template.header.bits, 0x207fffff);
+        assert_eq!(
+            template.header.merkle_root,
+            compute_merkle_root(&[template.coinbase.txid, [0x11; 32], [0x33; 32]])
+        );
+
+        // The coinbase carries the node's witness commitment
+        let commitment = gbt.default_witness_commitment.as_ref().unwrap();
+        let raw = &template.coinbase.raw_tx;
+        assert!(raw.windows(commitment.len()).any(|w| w == &commitment[..]));
+
+        // Header, tx count, coinbase, then each transaction verbatim
+        let block = template.serialize_block();
+        assert_eq!(block[80], 3);
+        let coinbase_end = 81 + template.coinbase.raw_tx_with_witness.len();
+        assert_eq!(&block[81..coinbase_end], &template.coinbase.raw_tx_with_witness[..]);
+        let tx0_end = coinbase_end + gbt.transactions[0].data.len();
+        assert_eq!(&block[coinbase_end..tx0_end], &gbt.transactions[0].data[..]);
+        assert_eq!(&block[tx0_end..], &gbt.transactions[1].data[..]);
+    }
+
+    #[test]
+    fn test_block_template_computes_missing_witness_commitment() {
+        let network = Network::Mainnet;
+        let mut gbt = GetBlockTemplate::parse(crate::gbt::tests::TEMPLATE_JSON).unwrap();
+        gbt.default_witness_commitment = None;
+        let address = validate_address("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq", network).unwrap();
+        let builder = CoinbaseBuilder::new(network, gbt.height, address);
+
+        // The coinbase commits to the wtxids the node listed
+        let wtxids: Vec<[u8; 32]> = gbt.transactions.iter().map(|tx| tx.wtxid).collect();
+        let template = BlockTemplate::from_getblocktemplate(network, gbt, builder).unwrap();
+        let commitment = compute_witness_commitment(&compute_witness_merkle_root(&wtxids), &[0u8; 32]);
+        assert!(template.coinbase.raw_tx.windows(32).any(|w| w == commitment));
+    }
+
+    #[test]
+    fn test_block_template_rejects_height_mismatch() {
+        let network = Network::Testnet4;
+        let gbt = GetBlockTemplate::parse(crate::gbt::tests::TEMPLATE_JSON).unwrap();
+        let address = validate_address("tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx", network).unwrap();
+        let builder = CoinbaseBuilder::new(network, gbt.height + 1, address);
+
+        assert_eq!(
+            BlockTemplate::from_getblocktemplate(network, gbt, builder).unwrap_err(),
+            TemplateError::HeightMismatch { template: 101, coinbase: 102 }
+        );
+    }
+
+    #[test]
+    fn test_update_extra_nonce_keeps_template() {
+        let network = Network::Testnet4;
+        let gbt = GetBlockTemplate::parse(crate::gbt::tests::TEMPLATE_JSON).unwrap();
+        let address = validate_address("tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx", network).unwrap();
+        let builder = CoinbaseBuilder::new(network, gbt.height, address);
+        let mut template = BlockTemplate::from_getblocktemplate(network, gbt.clone(), builder).unwrap();
+        let old_root = template.header.merkle_root;
+        template.header.nonce = 1234;
+
+        template.update_extra_nonce(7u64.to_le_bytes());
+
+        assert_ne!(template.header.merkle_root, old_root);
+        assert_eq!(
+            template.header.merkle_root,
+            compute_merkle_root(&[template.coinbase.txid, [0x11; 32], [0x33; 32]])
+        );
+        assert_eq!(template.header.nonce, 0);
+        assert_eq!(template.reward, 5_000_004_000);
+
+        // The node's commitment and the new extra nonce are both in the coinbase
+        let raw = &template.coinbase.raw_tx;
+        let commitment = gbt.default_witness_commitment.as_ref().unwrap();
+        assert!(raw.windows(commitment.len()).any(|w| w == &commitment[..]));
+        assert!(raw.windows(8).any(|w| w == 7u64.to_le_bytes()));
+    }
 }
@@ -0,0 +1,379 @@
+//! Chainwork and best-chain selection over block headers.
+//!
+//! A header's work is the expected number of hashes it took to find,
+//! `2^256 / (target + 1)`. Nodes follow the chain with the most cumulative
+//! work (chainwork), not the most blocks. [`HeaderChain`] keeps a tree of
+//! headers from a trusted starting point, follows the tip with the most work
+//! and reports when a new header moves that tip onto another branch, so a
+//! watcher can tell a new block from a reorg instead of trusting whatever
+//! tip hash its block source returns.
+//!
+//! Only each header's own proof of work is checked; whether its `bits` are
+//! the ones the difficulty rules require is left to the block source.
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::fmt;
+use crate::block::BlockHeader;
+use crate::difficulty::hash_meets_target;
+use crate::hash::reverse_bytes;
+use crate::uint::U256;
+
+/// Work proven by a header with compact target `bits`.
+///
+/// Computed as Bitcoin Core does, as `~target / (target + 1) + 1`, which
+/// equals `2^256 / (target + 1)` without needing 257 bits. Invalid or zero
+/// targets prove no work.
+pub fn header_work(bits: u32) -> U256 {
+    match U256::from_compact(bits) {
+        Some(target) if !target.is_zero() => !target / (target + U256::ONE) + U256::ONE,
+        _ => U256::ZERO,
+    }
+}
+
+/// Total work of a sequence of headers.
+pub fn chain_work(headers: &[BlockHeader]) -> U256 {
+    headers
+        .iter()
+        .fold(U256::ZERO, |total, header| total.saturating_add(header.work()))
+}
+
+impl BlockHeader {
+    /// Work proven by this header.
+    pub fn work(&self) -> U256 {
+        header_work(self.bits)
+    }
+}
+
+/// Errors that can occur while adding a header to a [`HeaderChain`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChainError {
+    /// The header's parent is not in the chain.
+    UnknownParent,
+    /// The header's `bits` are negative, zero or overflow.
+    InvalidBits,
+    /// The header's hash is above its target.
+    HighHash,
+}
+
+impl fmt::Display for ChainError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChainError::UnknownParent => write!(f, "Header does not connect to a known block"),
+            ChainError::InvalidBits => write!(f, "Invalid difficulty bits"),
+            ChainError::HighHash => write!(f, "Header hash does not meet its target"),
+        }
+    }
+}
+
+/// How adding a header changed the best tip.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TipUpdate {
+    /// The best tip stayed the same: the header was already known or is on
+    /// a branch with no more work.
+    Unchanged,
+    /// The header extends the previous best tip.
+    Extended,
+    /// The header's branch overtook the previous best chain.
+    Reorg(Reorg),
+}
+
+/// A switch of the best tip from one branch to another.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reorg {
+    /// Hash of the last block both branches share.
+    pub fork_hash: [u8; 32],
+    /// Height of the fork block.
+    pub fork_height: u32,
+    /// Blocks that left the best chain, from the old tip back.
+    pub disconnected: Vec<[u8; 32]>,
+    /// Blocks that joined the best chain, from the fork up to the new tip.
+    pub connected: Vec<[u8; 32]>,
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    header: BlockHeader,
+    height: u32,
+    chain_work: U256,
+}
+
+/// A tree of headers that follows the branch with the most work.
+///
+/// Hashes are in internal byte order. On equal work the tip seen first is
+/// kept, as nodes do.
+#[derive(Debug, Clone)]
+pub struct HeaderChain {
+    entries: BTreeMap<[u8; 32], Entry>,
+    tip: [u8; 32],
+}
+
+impl HeaderChain {
+    /// Start a chain at `root`, a header at `height` that is trusted as-is.
+    ///
+    /// Chainwork counts from the root's own work unless set with
+    /// [`HeaderChain::with_chain_work`]; either way it compares branches
+    /// correctly.
+    pub fn new(root: BlockHeader, height: u32) -> Self {
+        let hash = root.hash();
+        let chain_work = root.work();
+        let mut entries = BTreeMap::new();
+        entries.insert(hash, Entry { header: root, height, chain_work });
+        HeaderChain { entries, tip: hash }
+    }
+
+    /// Set the root's chainwork, e.g. the `chainwork` a node reports for it,
+    /// so the chain's totals match the network's.
+    pub fn with_chain_work(mut self, chain_work: U256) -> Self {
+        if let Some(root) = self.entries.get_mut(&self.tip) {
+            root.chain_work = chain_work;
+        }
+        self
+    }
+
+    /// Add a header whose parent is already in the chain.
+    pub fn insert(&mut self, header: BlockHeader) -> Result<TipUpdate, ChainError> {
+        let hash = header.hash();
+        if self.entries.contains_key(&hash) {
+            return Ok(TipUpdate::Unchanged);
+        }
+
+        let parent = self
+            .entries
+            .get(&header.prev_block_hash)
+            .ok_or(ChainError::UnknownParent)?;
+
+        let target = U256::from_compact(header.bits)
+            .filter(|target| !target.is_zero())
+            .ok_or(ChainError::InvalidBits)?;
+        if !hash_meets_target(&reverse_bytes(&hash), &target.to_be_bytes()) {
+            return Err(ChainError::HighHash);
+        }
+
+        let height = parent.height + 1;
+        let chain_work = parent.chain_work.saturating_add(header.work());
+        let extends_tip = header.prev_block_hash == self.tip;
+        self.entries.insert(hash, Entry { header, height, chain_work });
+
+        if chain_work <= self.tip_chain_work() {
+            return Ok(TipUpdate::Unchanged);
+        }
+
+        let old_tip = self.tip;
+        self.tip = hash;
+        if extends_tip {
+            Ok(TipUpdate::Extended)
+        } else {
+            Ok(TipUpdate::Reorg(self.reorg(old_tip, hash)))
+        }
+    }
+
+    /// Hash of the tip with the most work.
+    pub fn tip_hash(&self) -> [u8; 32] {
+        self.tip
+    }
+
+    /// Header of the tip with the most work.
+    pub fn tip(&self) -> &BlockHeader {
+        &self.entries[&self.tip].header
+    }
+
+    /// Height of the tip with the most work.
+    pub fn tip_height(&self) -> u32 {
+        self.entries[&self.tip].height
+    }
+
+    /// Chainwork of the best tip.
+    pub fn tip_chain_work(&self) -> U256 {
+        self.entries[&self.tip].chain_work
+    }
+
+    /// A known header by hash.
+    pub fn header(&self, hash: &[u8; 32]) -> Option<&BlockHeader> {
+        self.entries.get(hash).map(|entry| &entry.header)
+    }
+
+    /// Chainwork up to and including a known header.
+    pub fn chain_work_at(&self, hash: &[u8; 32]) -> Option<U256> {
+        self.entries.get(hash).map(|entry| entry.chain_work)
+    }
+
+    /// Whether a known header is on the best chain.
+    pub fn is_on_best_chain(&self, hash: &[u8; 32]) -> bool {
+        let Some(entry) = self.entries.get(hash) else { return false };
+        let mut cursor = self.tip;
+        while self.entries[&cursor].height > entry.height {
+            cursor = self.entries[&cursor].header.prev_block_hash;
+        }
+        cursor == *hash
+    }
+
+    /// Number of known headers, on every branch.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Always false: the root is always present.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Walk both tips back to their common ancestor.
+    fn reorg(&self, old_tip: [u8; 32], new_tip: [u8; 32]) -> Reorg {
+        let mut disconnected = Vec::new();
+        let mut connected = Vec::new();
+        let (mut old, mut new) = (old_tip, new_tip);
+
+        while old != new {
+            let old_height = self.entries[&old].height;
+            let new_height = self.entries[&new].height;
+            if old_height >= new_height {
+                disconnected.push(old);
+                old = self.entries[&old].header.prev_block_hash;
+            }
+            if new_height >= old_height {
+                connected.push(new);
+                new = self.entries[&new].header.prev_block_hash;
+            }
+        }
+
+        connected.reverse();
+        Reorg { fork_hash: old, fork_height: self.entries[&old].height, disconnected, connected }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::decode_block_hex;
+    use crate::decode::tests::{BLOCK_170_HEX, GENESIS_BLOCK_HEX};
+    use crate::difficulty::bits_to_target;
+
+    const REGTEST_BITS: u32 = 0x207fffff;
+
+    /// A solved regtest header on `parent`; `tag` tells siblings apart.
+    fn child(parent: &BlockHeader, tag: u8) -> BlockHeader {
+        child_with_bits(parent, tag, REGTEST_BITS)
+    }
+
+    fn child_with_bits(parent: &BlockHeader, tag: u8, bits: u32) -> BlockHeader {
+        let mut header = BlockHeader::new(parent.hash(), [tag; 32], parent.timestamp + 600, bits);
+        let target = bits_to_target(bits);
+        while !hash_meets_target(&reverse_bytes(&header.hash()), &target) {
+            header.nonce += 1;
+        }
+        header
+    }
+
+    fn root() -> BlockHeader {
+        child(&BlockHeader::new([0u8; 32], [0u8; 32], 1_700_000_000, REGTEST_BITS), 0)
+    }
+
+    #[test]
+    fn test_header_work() {
+        // Bitcoin Core reports chainwork 0x100010001 for the genesis block
+        let genesis = decode_block_hex(GENESIS_BLOCK_HEX).unwrap().header;
+        assert_eq!(genesis.work(), U256::from_u64(0x1_0001_0001));
+        let block = decode_block_hex(BLOCK_170_HEX).unwrap().header;
+        assert_eq!(chain_work(&[genesis, block]), U256::from_u64(0x2_0002_0002));
+
+        // Regtest's target is about 2^255
+        assert_eq!(header_work(REGTEST_BITS), U256::from_u64(2));
+        assert_eq!(header_work(0x04923456), U256::ZERO);
+        assert_eq!(header_work(0), U256::ZERO);
+
+        // Roughly 86 trillion times 2^32 hashes at bits 0x17034219
+        assert_eq!(header_work(0x17034219), (U256::from_u64(0x4e92) << 64) + U256::from_u64(0x35f0_4363_4662_e0cb));
+    }
+
+    #[test]
+    fn test_extend_and_side_branch() {
+        let root = root();
+        let mut chain = HeaderChain::new(root.clone(), 100).with_chain_work(U256::from_u64(1000));
+        let a1 = child(&root, 1);
+        let a2 = child(&a1, 1);
+        assert_eq!(chain.insert(a1.clone()), Ok(TipUpdate::Extended));
+        assert_eq!(chain.insert(a2.clone()), Ok(TipUpdate::Extended));
+        assert_eq!(chain.tip_hash(), a2.hash());
+        assert_eq!(chain.tip_height(), 102);
+        assert_eq!(chain.tip_chain_work(), U256::from_u64(1004));
+
+        // A competing block at the same height does not take over
+        let b1 = child(&root, 2);
+        let b2 = child(&b1, 2);
+        assert_eq!(chain.insert(b1.clone()), Ok(TipUpdate::Unchanged));
+        assert_eq!(chain.insert(b2.clone()), Ok(TipUpdate::Unchanged));
+        assert_eq!(chain.tip_hash(), a2.hash());
+        assert!(chain.is_on_best_chain(&a1.hash()));
+        assert!(!chain.is_on_best_chain(&b1.hash()));
+        assert_eq!(chain.chain_work_at(&b2.hash()), Some(U256::from_u64(1004)));
+
+        // Known headers are ignored
+        assert_eq!(chain.insert(a2), Ok(TipUpdate::Unchanged));
+        assert_eq!(chain.len(), 5);
+    }
+
+    #[test]
+    fn test_reorg() {
+        let root = root();
+        let mut chain = HeaderChain::new(root.clone(), 0);
+        let a1 = child(&root, 1);
+        let a2 = child(&a1, 1);
+        let b1 = child(&root, 2);
+        let b2 = child(&b1, 2);
+        let b3 = child(&b2, 2);
+        for header in [&a1, &a2, &b1, &b2] {
+            chain.insert(header.clone()).unwrap();
+        }
+
+        assert_eq!(
+            chain.insert(b3.clone()),
+            Ok(TipUpdate::Reorg(Reorg {
+                fork_hash: root.hash(),
+                fork_height: 0,
+                disconnected: alloc::vec![a2.hash(), a1.hash()],
+                connected: alloc::vec![b1.hash(), b2.hash(), b3.hash()],
+            }))
+        );
+        assert_eq!(chain.tip_hash(), b3.hash());
+        assert!(!chain.is_on_best_chain(&a1.hash()));
+    }
+
+    #[test]
+    fn test_most_work_beats_most_blocks() {
+        let root = root();
+        let mut chain = HeaderChain::new(root.clone(), 0);
+        let a1 = child(&root, 1);
+        let a2 = child(&a1, 1);
+        let a3 = child(&a2, 1);
+        for header in [&a1, &a2, &a3] {
+            chain.insert(header.clone()).unwrap();
+        }
+
+        // One block at a quarter of the target outweighs three at the limit
+        let heavy = child_with_bits(&root, 2, 0x201fffff);
+        assert_eq!(heavy.work(), U256::from_u64(8));
+        let update = chain.insert(heavy.clone()).unwrap();
+        let TipUpdate::Reorg(reorg) = update else { panic!("expected a reorg, got {:?}", update) };
+        assert_eq!(reorg.disconnected.len(), 3);
+        assert_eq!(reorg.connected, [heavy.hash()]);
+        assert_eq!(chain.tip_height(), 1);
+    }
+
+    #[test]
+    fn test_rejected_headers() {
+        let root = root();
+        let mut chain = HeaderChain::new(root.clone(), 0);
+
+        let orphan = child(&child(&root, 1), 1);
+        assert_eq!(chain.insert(orphan), Err(ChainError::UnknownParent));
+
+        let mut unsolved = BlockHeader::new(root.hash(), [3; 32], root.timestamp + 600, 0x1d00ffff);
+        unsolved.nonce = 1;
+        assert_eq!(chain.insert(unsolved), Err(ChainError::HighHash));
+
+        let negative = BlockHeader::new(root.hash(), [3; 32], root.timestamp + 600, 0x04923456);
+        assert_eq!(chain.insert(negative), Err(ChainError::InvalidBits));
+        assert_eq!(chain.len(), 1);
+    }
+}
@@ -5,28 +5,92 @@
 
 use alloc::vec;
 use alloc::vec::Vec;
+use core::fmt;
 use crate::address::ValidatedAddress;
 use crate::hash::double_sha256;
 use crate::merkle::{compute_witness_commitment, witness_commitment_script};
-use crate::network::Network;
+use crate::network::{Network, MAX_COINBASE_SCRIPTSIG_SIZE};
+use crate::payout::{split_reward, PayoutError, Recipient};
+use crate::signet::{append_solution, push_data, SignetSolution, DEFAULT_SIGNET_CHALLENGE};
+
+/// The tag written after the extra nonce unless the builder is given one.
+pub const DEFAULT_COINBASE_TAG: &[u8] = b"/ScratchOffMiner/";
+
+/// Tag and data bytes that fit in the scriptSig at any height, next to the
+/// height push (up to 5 bytes) and the 8-byte extra nonce.
+pub const MAX_COINBASE_TAG_SIZE: usize = MAX_COINBASE_SCRIPTSIG_SIZE - 5 - 8;
+
+/// Largest OP_RETURN output script this crate puts in a coinbase: OP_RETURN
+/// and a push of up to 80 bytes, which is plenty for a commitment.
+pub const MAX_OP_RETURN_SCRIPT_SIZE: usize = 83;
+
+/// Why a coinbase transaction cannot be built.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CoinbaseError {
+    /// The reward cannot be split between the payout recipients.
+    Payout(PayoutError),
+    /// The scriptSig would be over the consensus size limit.
+    ScriptSigTooLarge { size: usize, max: usize },
+    /// An OP_RETURN output script is over the standard size.
+    OpReturnTooLarge { size: usize, max: usize },
+}
+
+impl fmt::Display for CoinbaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CoinbaseError::Payout(e) => write!(f, "Invalid coinbase payout: {}", e),
+            CoinbaseError::ScriptSigTooLarge { size, max } => write!(
+                f,
+                "Coinbase scriptSig would be {} bytes, over the {}-byte limit; shorten the tag or data",
+                size, max
+            ),
+            CoinbaseError::OpReturnTooLarge { size, max } => write!(
+                f,
+                "OP_RETURN output script would be {} bytes, over the {}-byte limit",
+                size, max
+            ),
+        }
+    }
+}
 
 /// Builder for constructing coinbase transactions.
+#[derive(Debug, Clone)]
 pub struct CoinbaseBuilder {
-    /// The network (mainnet or testnet4).
+    /// The network (mainnet, testnet4, regtest or signet).
     #[allow(dead_code)]
     network: Network,
     /// The block height (required by BIP34).
     block_height: u32,
     /// The address to receive the block reward.
     reward_address: ValidatedAddress,
+    /// Recipients sharing the reward instead of `reward_address`, if any.
+    payouts: Vec<Recipient>,
+    /// Payloads of zero-value OP_RETURN outputs, in order.
+    op_returns: Vec<Vec<u8>>,
     /// Extra nonce data for merkle root variation (8 bytes).
     extra_nonce: [u8; 8],
+    /// Miner tag written after the extra nonce.
+    tag: Vec<u8>,
+    /// Arbitrary bytes written after the tag.
+    script_data: Vec<u8>,
     /// Witness reserved value (32 bytes, typically all zeros).
     witness_reserved: [u8; 32],
+    /// Witness merkle root the commitment is made to.
+    witness_merkle_root: [u8; 32],
+    /// Witness commitment output script supplied by the node, if any.
+    witness_commitment_script: Option<Vec<u8>>,
+    /// Signet challenge; when set, the witness commitment carries the
+    /// signet solution.
+    signet_challenge: Option<Vec<u8>>,
+    /// Solution to the signet challenge (empty until signed).
+    signet_solution: SignetSolution,
 }
 
 impl CoinbaseBuilder {
     /// Create a new coinbase builder.
+    ///
+    /// On signet the default signet's challenge is assumed; custom signets
+    /// set theirs with [`with_signet_challenge`](Self::with_signet_challenge).
     pub fn new(
         network: Network,
         block_height: u32,
@@ -36,8 +100,16 @@ impl CoinbaseBuilder {
             network,
             block_height,
             reward_address,
+            payouts: Vec::new(),
+            op_returns: Vec::new(),
             extra_nonce: [0u8; 8],
+            tag: DEFAULT_COINBASE_TAG.to_vec(),
+            script_data: Vec::new(),
             witness_reserved: [0u8; 32],
+            witness_merkle_root: [0u8; 32],
+            witness_commitment_script: None,
+            signet_challenge: (network == Network::Signet).then(|| DEFAULT_SIGNET_CHALLENGE.to_vec()),
+            signet_solution: SignetSolution::default(),
         }
     }
 
@@ -47,21 +119,100 @@ impl CoinbaseBuilder {
         self
     }
 
+    /// Set the tag that identifies the miner in the scriptSig (empty for
+    /// none).
+    pub fn with_tag(mut self, tag: Vec<u8>) -> Self {
+        self.tag = tag;
+        self
+    }
+
+    /// Write arbitrary bytes after the tag, e.g. a message or a hash
+    /// commitment.
+    ///
+    /// Tag and data together may use [`MAX_COINBASE_TAG_SIZE`] bytes; at low
+    /// heights a few more fit.
+    pub fn with_script_data(mut self, data: Vec<u8>) -> Self {
+        self.script_data = data;
+        self
+    }
+
+    /// Split the reward between `recipients` instead of paying it all to the
+    /// reward address.
+    ///
+    /// See [`payout`](crate::payout) for how the reward is divided.
+    pub fn with_payouts(mut self, recipients: Vec<Recipient>) -> Self {
+        self.payouts = recipients;
+        self
+    }
+
+    /// Add a zero-value OP_RETURN output carrying `data`, e.g. a
+    /// merge-mining or timestamp commitment.
+    ///
+    /// The outputs follow the payouts in the order they were added; the
+    /// witness commitment stays last, where nodes look for it. Each script
+    /// may use [`MAX_OP_RETURN_SCRIPT_SIZE`] bytes, so up to 80 bytes of data.
+    pub fn with_op_return(mut self, data: Vec<u8>) -> Self {
+        self.op_returns.push(data);
+        self
+    }
+
     /// Set the witness reserved value.
     pub fn with_witness_reserved(mut self, witness_reserved: [u8; 32]) -> Self {
         self.witness_reserved = witness_reserved;
         self
     }
 
+    /// Commit to the witness merkle root of a block with transactions.
+    ///
+    /// Blocks with transactions must commit to their wtxids; compute the
+    /// root with [`compute_witness_merkle_root`](crate::merkle::compute_witness_merkle_root).
+    /// The default, all zeros, is the root of a coinbase-only block.
+    pub fn with_witness_merkle_root(mut self, root: [u8; 32]) -> Self {
+        self.witness_merkle_root = root;
+        self
+    }
+
+    /// Use a precomputed witness commitment script instead of computing it
+    /// from the witness merkle root.
+    ///
+    /// The `default_witness_commitment` from `getblocktemplate` goes here.
+    pub fn with_witness_commitment_script(mut self, script: Vec<u8>) -> Self {
+        self.witness_commitment_script = Some(script);
+        self
+    }
+
+    /// Set the challenge of a custom signet.
+    pub fn with_signet_challenge(mut self, challenge: Vec<u8>) -> Self {
+        self.signet_challenge = Some(challenge);
+        self
+    }
+
+    /// Set the solution to the signet challenge.
+    pub fn with_signet_solution(mut self, solution: SignetSolution) -> Self {
+        self.signet_solution = solution;
+        self
+    }
+
+    /// The block height committed to in the scriptSig.
+    pub fn block_height(&self) -> u32 {
+        self.block_height
+    }
+
+    /// The signet challenge blocks from this builder must satisfy, if any.
+    pub fn signet_challenge(&self) -> Option<&[u8]> {
+        self.signet_challenge.as_deref()
+    }
+
     /// Build the coinbase transaction.
     ///
-    /// Returns the serialized transaction and its txid.
-    pub fn build(&self, total_reward: u64) -> CoinbaseTransaction {
-        // Build scriptSig: [height_push] [height_bytes] [extra_nonce]
-        let script_sig = self.build_script_sig();
+    /// Returns the serialized transaction and its txid, or an error if the
+    /// reward cannot be paid out as configured.
+    pub fn build(&self, total_reward: u64) -> Result<CoinbaseTransaction, CoinbaseError> {
+        // Build scriptSig: [height_push] [height_bytes] [extra_nonce] [tag] [data]
+        let script_sig = self.build_script_sig()?;
 
         // Build outputs
-        let outputs = self.build_outputs(total_reward);
+        let outputs = self.build_outputs(total_reward)?;
 
         // Serialize the transaction
         let (raw_tx, raw_tx_with_witness) = self.serialize_transaction(&script_sig, &outputs);
@@ -72,52 +223,84 @@ impl CoinbaseBuilder {
         // For coinbase, wtxid is defined as all zeros
         let wtxid = [0u8; 32];
 
-        CoinbaseTransaction {
+        Ok(CoinbaseTransaction {
             raw_tx,
             raw_tx_with_witness,
             txid,
             wtxid,
-        }
+        })
     }
 
-    /// Build the scriptSig with BIP34 height encoding and extra nonce.
-    fn build_script_sig(&self) -> Vec<u8> {
+    /// Build the scriptSig with BIP34 height encoding, extra nonce, tag and
+    /// data.
+    fn build_script_sig(&self) -> Result<Vec<u8>, CoinbaseError> {
         let mut script_sig = Vec::with_capacity(32);
 
         // BIP34: Block height must be in scriptSig
-        let height_bytes = encode_block_height(self.block_height);
-        script_sig.push(height_bytes.len() as u8); // Push opcode
-        script_sig.extend_from_slice(&height_bytes);
+        script_sig.extend_from_slice(&encode_height_push(self.block_height));
 
         // Add extra nonce
         script_sig.extend_from_slice(&self.extra_nonce);
 
-        // Add miner tag (optional, for fun)
-        let tag = b"/ScratchOffMiner/";
-        script_sig.extend_from_slice(tag);
+        // Add miner tag and data (not executed, so no push opcodes needed)
+        script_sig.extend_from_slice(&self.tag);
+        script_sig.extend_from_slice(&self.script_data);
 
-        script_sig
+        if script_sig.len() > MAX_COINBASE_SCRIPTSIG_SIZE {
+            return Err(CoinbaseError::ScriptSigTooLarge {
+                size: script_sig.len(),
+                max: MAX_COINBASE_SCRIPTSIG_SIZE,
+            });
+        }
+        Ok(script_sig)
     }
 
     /// Build the transaction outputs.
-    fn build_outputs(&self, total_reward: u64) -> Vec<TxOutput> {
-        let mut outputs = Vec::with_capacity(2);
+    fn build_outputs(&self, total_reward: u64) -> Result<Vec<TxOutput>, CoinbaseError> {
+        let mut outputs = Vec::with_capacity(2 + self.payouts.len());
+
+        // Block reward to the miner's address, or split between the recipients
+        if self.payouts.is_empty() {
+            outputs.push(TxOutput {
+                value: total_reward,
+                script_pubkey: self.reward_address.script_pubkey.clone(),
+            });
+        } else {
+            let amounts = split_reward(&self.payouts, total_reward).map_err(CoinbaseError::Payout)?;
+            for (recipient, value) in self.payouts.iter().zip(amounts) {
+                if value > 0 {
+                    outputs.push(TxOutput { value, script_pubkey: recipient.address.script_pubkey.clone() });
+                }
+            }
+        }
 
-        // Output 0: Block reward to miner's address
-        outputs.push(TxOutput {
-            value: total_reward,
-            script_pubkey: self.reward_address.script_pubkey.clone(),
-        });
+        // Commitments from the caller
+        for data in &self.op_returns {
+            let script_pubkey = op_return_script(data);
+            if script_pubkey.len() > MAX_OP_RETURN_SCRIPT_SIZE {
+                return Err(CoinbaseError::OpReturnTooLarge {
+                    size: script_pubkey.len(),
+                    max: MAX_OP_RETURN_SCRIPT_SIZE,
+                });
+            }
+            outputs.push(TxOutput { value: 0, script_pubkey });
+        }
 
-        // Output 1: Witness commitment (required for SegWit blocks)
-        let witness_commitment = compute_witness_commitment(&self.witness_reserved);
-        let commitment_script = witness_commitment_script(&witness_commitment);
+        // Last output: Witness commitment (required for SegWit blocks)
+        let commitment_script = match &self.witness_commitment_script {
+            Some(script) => script.clone(),
+            None => witness_commitment_script(&compute_witness_commitment(&self.witness_merkle_root, &self.witness_reserved)),
+        };
+        let commitment_script = match &self.signet_challenge {
+            Some(_) => append_solution(&commitment_script, &self.signet_solution),
+            None => commitment_script,
+        };
         outputs.push(TxOutput {
             value: 0, // Witness commitment has no value
             script_pubkey: commitment_script,
         });
 
-        outputs
+        Ok(outputs)
     }
 
     /// Serialize the transaction (both with and without witness).
@@ -221,6 +404,14 @@ struct TxOutput {
     script_pubkey: Vec<u8>,
 }
 
+/// An OP_RETURN script pushing `data`.
+fn op_return_script(data: &[u8]) -> Vec<u8> {
+    let mut script = Vec::with_capacity(3 + data.len());
+    script.push(0x6a);
+    push_data(data, &mut script);
+    script
+}
+
 /// Encode a block height according to BIP34.
 ///
 /// The height is minimally encoded as a little-endian integer with proper handling
@@ -251,8 +442,27 @@ fn encode_block_height(height: u32) -> Vec<u8> {
     bytes
 }
 
+/// The script push of a block height that BIP34 requires at the start of
+/// the coinbase scriptSig.
+///
+/// Bitcoin Core pushes heights 1 to 16 as OP_1 to OP_16 and compares the
+/// scriptSig prefix byte for byte, so those heights cannot use a data push.
+pub(crate) fn encode_height_push(height: u32) -> Vec<u8> {
+    match height {
+        // OP_1 through OP_16
+        1..=16 => vec![0x50 + height as u8],
+        _ => {
+            let bytes = encode_block_height(height);
+            let mut push = Vec::with_capacity(bytes.len() + 1);
+            push.push(bytes.len() as u8); // Push opcode (OP_0 for height 0)
+            push.extend_from_slice(&bytes);
+            push
+        }
+    }
+}
+
 /// Encode a variable-length integer (Bitcoin varint).
-fn encode_varint(value: u64, output: &mut Vec<u8>) {
+pub(crate) fn encode_varint(value: u64, output: &mut Vec<u8>) {
     if value < 0xfd {
         output.push(value as u8);
     } else if value <= 0xffff {
@@ -270,12 +480,13 @@ fn encode_varint(value: u64, output: &mut Vec<u8>) {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::address::{validate_address, AddressType};
+    use crate::address::validate_address;
+    use crate::decode::decode_transaction;
 
     #[test]
     fn test_encode_block_height() {
         // Height 0
-        assert_eq!(encode_block_height(0), vec![]);
+        assert_eq!(encode_block_height(0), Vec::<u8>::new());
 
         // Height 1
         assert_eq!(encode_block_height(1), vec![0x01]);
@@ -294,6 +505,16 @@ mod tests {
         assert_eq!(encode_block_height(500000), vec![0x20, 0xA1, 0x07]);
     }
 
+    #[test]
+    fn test_encode_height_push() {
+        assert_eq!(encode_height_push(0), vec![0x00]);
+        assert_eq!(encode_height_push(1), vec![0x51]);
+        assert_eq!(encode_height_push(16), vec![0x60]);
+        assert_eq!(encode_height_push(17), vec![0x01, 0x11]);
+        assert_eq!(encode_height_push(128), vec![0x02, 0x80, 0x00]);
+        assert_eq!(encode_height_push(227_931), vec![0x03, 0x5b, 0x7a, 0x03]);
+    }
+
     #[test]
     fn test_encode_varint() {
         let mut output = Vec::new();
@@ -317,7 +538,7 @@ mod tests {
             .with_extra_nonce([0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08]);
 
         let reward = 312_500_000; // 3.125 BTC in satoshis
-        let coinbase = builder.build(reward);
+        let coinbase = builder.build(reward).unwrap();
 
         // Verify txid is 32 bytes
         assert_eq!(coinbase.txid.len(), 32);
@@ -331,4 +552,99 @@ mod tests {
         // Verify witness version is longer (has marker, flag, and witness)
         assert!(coinbase.raw_tx_with_witness.len() > coinbase.raw_tx.len());
     }
+
+    #[test]
+    fn test_witness_commitment_script_override() {
+        let network = Network::Mainnet;
+        let address = validate_address("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq", network).unwrap();
+        let mut script = vec![0x6a, 0x24, 0xaa, 0x21, 0xa9, 0xed];
+        script.extend_from_slice(&[0x5A; 32]);
+
+        let coinbase = CoinbaseBuilder::new(network, 875000, address)
+            .with_witness_commitment_script(script.clone())
+            .build(312_500_000)
+            .unwrap();
+
+        // The commitment is the last output, right before the locktime
+        let end = coinbase.raw_tx.len() - 4;
+        assert_eq!(&coinbase.raw_tx[end - script.len()..end], &script[..]);
+    }
+
+    #[test]
+    fn test_multi_output_payouts() {
+        let network = Network::Mainnet;
+        let address = validate_address("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq", network).unwrap();
+        let other = validate_address("1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2", network).unwrap();
+        let builder = CoinbaseBuilder::new(network, 875000, address.clone()).with_payouts(vec![
+            Recipient::weight(address.clone(), 3),
+            Recipient::weight(other.clone(), 1),
+        ]);
+
+        let coinbase = builder.build(312_500_001).unwrap();
+        let tx = decode_transaction(&coinbase.raw_tx_with_witness).unwrap();
+        assert_eq!(tx.outputs.len(), 3);
+        assert_eq!(tx.outputs[0].value, 234_375_001);
+        assert_eq!(tx.outputs[0].script_pubkey, address.script_pubkey);
+        assert_eq!(tx.outputs[1].value, 78_125_000);
+        assert_eq!(tx.outputs[1].script_pubkey, other.script_pubkey);
+        assert_eq!(tx.outputs[2].value, 0);
+
+        let builder = builder.with_payouts(vec![Recipient::amount(other, 1_000_000)]);
+        assert_eq!(
+            builder.build(312_500_000).unwrap_err(),
+            CoinbaseError::Payout(PayoutError::Unallocated(311_500_000))
+        );
+    }
+
+    #[test]
+    fn test_script_sig_tag_and_data() {
+        let network = Network::Mainnet;
+        let address = validate_address("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq", network).unwrap();
+        let builder = CoinbaseBuilder::new(network, 875000, address)
+            .with_extra_nonce([0x01; 8])
+            .with_tag(b"/alice/".to_vec())
+            .with_script_data(vec![0xAB; 4]);
+
+        let coinbase = builder.build(312_500_000).unwrap();
+        let tx = decode_transaction(&coinbase.raw_tx).unwrap();
+        let mut expected = encode_height_push(875000);
+        expected.extend_from_slice(&[0x01; 8]);
+        expected.extend_from_slice(b"/alice/");
+        expected.extend_from_slice(&[0xAB; 4]);
+        assert_eq!(tx.inputs[0].script_sig, expected);
+
+        // The 4-byte height push leaves room for one more byte than any height
+        let builder = builder.with_tag(Vec::new()).with_script_data(vec![0; MAX_COINBASE_TAG_SIZE]);
+        assert!(builder.build(312_500_000).is_ok());
+        let builder = builder.with_script_data(vec![0; MAX_COINBASE_TAG_SIZE + 2]);
+        assert_eq!(
+            builder.build(312_500_000).unwrap_err(),
+            CoinbaseError::ScriptSigTooLarge { size: 101, max: 100 }
+        );
+    }
+
+    #[test]
+    fn test_op_return_outputs() {
+        let network = Network::Mainnet;
+        let address = validate_address("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq", network).unwrap();
+        let plain = CoinbaseBuilder::new(network, 875000, address.clone()).build(312_500_000).unwrap();
+        let builder = CoinbaseBuilder::new(network, 875000, address)
+            .with_op_return(b"timestamp".to_vec())
+            .with_op_return(vec![0x5A; 80]);
+
+        let coinbase = builder.build(312_500_000).unwrap();
+        assert_ne!(coinbase.txid, plain.txid);
+        let tx = decode_transaction(&coinbase.raw_tx).unwrap();
+        assert_eq!(tx.outputs.len(), 4);
+        assert_eq!(tx.outputs[1].value, 0);
+        assert_eq!(tx.outputs[1].script_pubkey, b"\x6a\x09timestamp");
+        assert_eq!(&tx.outputs[2].script_pubkey[..3], &[0x6a, 0x4c, 80]);
+        assert_eq!(tx.outputs[2].script_pubkey.len(), MAX_OP_RETURN_SCRIPT_SIZE);
+        assert_eq!(tx.outputs[3].script_pubkey[..6], crate::merkle::WITNESS_COMMITMENT_PREFIX);
+
+        assert_eq!(
+            builder.with_op_return(vec![0; 81]).build(312_500_000).unwrap_err(),
+            CoinbaseError::OpReturnTooLarge { size: 84, max: 83 }
+        );
+    }
 }
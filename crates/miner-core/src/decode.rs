@@ -0,0 +1,574 @@
+//! Parsing of serialized block headers, transactions and blocks.
+//!
+//! This is the inverse of the serialization in `block` and `coinbase`:
+//! headers, transactions in both the legacy and the BIP144 (witness)
+//! format, and whole blocks as found in `submitblock`/`getblock` hex.
+
+use alloc::vec::Vec;
+use core::fmt;
+use crate::block::BlockHeader;
+use crate::coinbase::encode_varint;
+use crate::hash::double_sha256;
+use crate::merkle::WITNESS_COMMITMENT_PREFIX;
+use crate::network::BLOCK_HEADER_SIZE;
+
+/// Errors that can occur while decoding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The input ended in the middle of a field.
+    UnexpectedEnd,
+    /// A varint used more bytes than needed for its value.
+    NonCanonicalVarint,
+    /// The witness flag byte after the marker is not 1.
+    InvalidWitnessFlag(u8),
+    /// The witness format was used but every witness is empty.
+    SuperfluousWitness,
+    /// Bytes remain after the decoded object.
+    TrailingBytes(usize),
+    /// The input is not valid hex.
+    InvalidHex,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::UnexpectedEnd => write!(f, "Unexpected end of data"),
+            DecodeError::NonCanonicalVarint => write!(f, "Non-canonical varint"),
+            DecodeError::InvalidWitnessFlag(flag) => write!(f, "Invalid witness flag {:#04x}", flag),
+            DecodeError::SuperfluousWitness => write!(f, "Witness flag set but all witnesses are empty"),
+            DecodeError::TrailingBytes(n) => write!(f, "{} unexpected trailing bytes", n),
+            DecodeError::InvalidHex => write!(f, "Invalid hex"),
+        }
+    }
+}
+
+/// A transaction input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxIn {
+    /// Txid of the spent output (internal byte order).
+    pub prev_txid: [u8; 32],
+    /// Index of the spent output.
+    pub prev_vout: u32,
+    /// The unlocking script.
+    pub script_sig: Vec<u8>,
+    /// Sequence number.
+    pub sequence: u32,
+    /// Witness stack items (empty for legacy inputs).
+    pub witness: Vec<Vec<u8>>,
+}
+
+/// A transaction output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxOut {
+    /// Value in satoshis.
+    pub value: u64,
+    /// The locking script.
+    pub script_pubkey: Vec<u8>,
+}
+
+/// A decoded transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transaction {
+    /// Transaction version.
+    pub version: i32,
+    /// Inputs, each with its witness.
+    pub inputs: Vec<TxIn>,
+    /// Outputs.
+    pub outputs: Vec<TxOut>,
+    /// Lock time.
+    pub lock_time: u32,
+}
+
+impl Transaction {
+    /// Whether any input carries witness data.
+    pub fn has_witness(&self) -> bool {
+        self.inputs.iter().any(|input| !input.witness.is_empty())
+    }
+
+    /// Whether this is a coinbase: a single input spending the null outpoint.
+    pub fn is_coinbase(&self) -> bool {
+        self.inputs.len() == 1
+            && self.inputs[0].prev_txid == [0u8; 32]
+            && self.inputs[0].prev_vout == 0xFFFF_FFFF
+    }
+
+    /// Serialize without witness data (the format the txid commits to).
+    pub fn serialize_without_witness(&self) -> Vec<u8> {
+        self.serialize_inner(false)
+    }
+
+    /// Serialize with witness data when there is any, as relayed on the network.
+    pub fn serialize(&self) -> Vec<u8> {
+        self.serialize_inner(self.has_witness())
+    }
+
+    /// Transaction ID (internal byte order).
+    pub fn txid(&self) -> [u8; 32] {
+        double_sha256(&self.serialize_without_witness())
+    }
+
+    /// Witness transaction ID (internal byte order).
+    ///
+    /// Equal to the txid for transactions without witness data.
+    pub fn wtxid(&self) -> [u8; 32] {
+        double_sha256(&self.serialize())
+    }
+
+    fn serialize_inner(&self, with_witness: bool) -> Vec<u8> {
+        let mut out = Vec::with_capacity(128);
+        out.extend_from_slice(&self.version.to_le_bytes());
+
+        if with_witness {
+            // Marker and flag (BIP144)
+            out.push(0x00);
+            out.push(0x01);
+        }
+
+        encode_varint(self.inputs.len() as u64, &mut out);
+        for input in &self.inputs {
+            out.extend_from_slice(&input.prev_txid);
+            out.extend_from_slice(&input.prev_vout.to_le_bytes());
+            encode_varint(input.script_sig.len() as u64, &mut out);
+            out.extend_from_slice(&input.script_sig);
+            out.extend_from_slice(&input.sequence.to_le_bytes());
+        }
+
+        encode_varint(self.outputs.len() as u64, &mut out);
+        for output in &self.outputs {
+            out.extend_from_slice(&output.value.to_le_bytes());
+            encode_varint(output.script_pubkey.len() as u64, &mut out);
+            out.extend_from_slice(&output.script_pubkey);
+        }
+
+        if with_witness {
+            for input in &self.inputs {
+                encode_varint(input.witness.len() as u64, &mut out);
+                for item in &input.witness {
+                    encode_varint(item.len() as u64, &mut out);
+                    out.extend_from_slice(item);
+                }
+            }
+        }
+
+        out.extend_from_slice(&self.lock_time.to_le_bytes());
+        out
+    }
+}
+
+/// A decoded block.
+#[derive(Debug, Clone)]
+pub struct Block {
+    /// The block header.
+    pub header: BlockHeader,
+    /// All transactions, starting with the coinbase.
+    pub transactions: Vec<Transaction>,
+}
+
+impl Block {
+    /// Serialize the block with witness data.
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(BLOCK_HEADER_SIZE + 256 * self.transactions.len());
+        out.extend_from_slice(&self.header.serialize());
+        encode_varint(self.transactions.len() as u64, &mut out);
+        for tx in &self.transactions {
+            out.extend_from_slice(&tx.serialize());
+        }
+        out
+    }
+
+    /// The block hash (internal byte order).
+    pub fn hash(&self) -> [u8; 32] {
+        self.header.hash()
+    }
+
+    /// Index of the coinbase output holding the witness commitment.
+    ///
+    /// BIP141: the last output of at least 38 bytes that starts with the
+    /// commitment header counts; later matches override earlier ones.
+    pub fn witness_commitment_index(&self) -> Option<usize> {
+        let coinbase = self.transactions.first()?;
+        coinbase.outputs.iter().rposition(|output| {
+            output.script_pubkey.len() >= 38 && output.script_pubkey.starts_with(&WITNESS_COMMITMENT_PREFIX)
+        })
+    }
+}
+
+/// Decode an 80-byte block header.
+pub fn decode_header(bytes: &[u8]) -> Result<BlockHeader, DecodeError> {
+    let mut reader = Reader::new(bytes);
+    let header = reader.read_header()?;
+    reader.finish()?;
+    Ok(header)
+}
+
+/// Decode a transaction in either the legacy or the witness format.
+pub fn decode_transaction(bytes: &[u8]) -> Result<Transaction, DecodeError> {
+    let mut reader = Reader::new(bytes);
+    let tx = reader.read_transaction()?;
+    reader.finish()?;
+    Ok(tx)
+}
+
+/// Decode a block: the header followed by its transactions.
+pub fn decode_block(bytes: &[u8]) -> Result<Block, DecodeError> {
+    let mut reader = Reader::new(bytes);
+    let header = reader.read_header()?;
+
+    let count = reader.read_varint()?;
+    let mut transactions = Vec::with_capacity(reader.capacity_hint(count));
+    for _ in 0..count {
+        transactions.push(reader.read_transaction()?);
+    }
+
+    reader.finish()?;
+    Ok(Block { header, transactions })
+}
+
+/// Decode a block from hex, as returned by `getblock <hash> 0`.
+pub fn decode_block_hex(hex_str: &str) -> Result<Block, DecodeError> {
+    let bytes = hex::decode(hex_str.trim()).map_err(|_| DecodeError::InvalidHex)?;
+    decode_block(&bytes)
+}
+
+/// Decode a transaction from hex.
+pub fn decode_transaction_hex(hex_str: &str) -> Result<Transaction, DecodeError> {
+    let bytes = hex::decode(hex_str.trim()).map_err(|_| DecodeError::InvalidHex)?;
+    decode_transaction(&bytes)
+}
+
+/// Cursor over the input bytes.
+pub(crate) struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Reader { data, pos: 0 }
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        let end = self.pos.checked_add(len).ok_or(DecodeError::UnexpectedEnd)?;
+        let bytes = self.data.get(self.pos..end).ok_or(DecodeError::UnexpectedEnd)?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        let mut array = [0u8; N];
+        array.copy_from_slice(self.read_bytes(N)?);
+        Ok(array)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.read_array::<1>()?[0])
+    }
+
+    fn read_u32(&mut self) -> Result<u32, DecodeError> {
+        Ok(u32::from_le_bytes(self.read_array()?))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, DecodeError> {
+        Ok(u64::from_le_bytes(self.read_array()?))
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.data.get(self.pos).copied()
+    }
+
+    /// Bitcoin varint, rejecting non-minimal encodings like Bitcoin Core.
+    pub(crate) fn read_varint(&mut self) -> Result<u64, DecodeError> {
+        let (value, min) = match self.read_u8()? {
+            0xfd => (u16::from_le_bytes(self.read_array()?) as u64, 0xfd),
+            0xfe => (self.read_u32()? as u64, 0x1_0000),
+            0xff => (self.read_u64()?, 0x1_0000_0000),
+            byte => return Ok(byte as u64),
+        };
+        if value < min {
+            return Err(DecodeError::NonCanonicalVarint);
+        }
+        Ok(value)
+    }
+
+    /// A varint length followed by that many bytes.
+    pub(crate) fn read_var_bytes(&mut self) -> Result<Vec<u8>, DecodeError> {
+        let len = self.read_varint()?;
+        let len = usize::try_from(len).map_err(|_| DecodeError::UnexpectedEnd)?;
+        Ok(self.read_bytes(len)?.to_vec())
+    }
+
+    /// Capacity for `count` items, bounded by the remaining input so a
+    /// bogus count can't trigger a huge allocation.
+    pub(crate) fn capacity_hint(&self, count: u64) -> usize {
+        count.min((self.data.len() - self.pos) as u64) as usize
+    }
+
+    fn read_header(&mut self) -> Result<BlockHeader, DecodeError> {
+        let version = self.read_u32()? as i32;
+        let prev_block_hash = self.read_array()?;
+        let merkle_root = self.read_array()?;
+        let timestamp = self.read_u32()?;
+        let bits = self.read_u32()?;
+        let nonce = self.read_u32()?;
+
+        let mut header = BlockHeader::new(prev_block_hash, merkle_root, timestamp, bits);
+        header.version = version;
+        header.nonce = nonce;
+        Ok(header)
+    }
+
+    fn read_transaction(&mut self) -> Result<Transaction, DecodeError> {
+        let version = self.read_u32()? as i32;
+
+        // BIP144: a zero where the input count goes is the witness marker
+        let with_witness = self.peek() == Some(0x00);
+        if with_witness {
+            self.read_u8()?;
+            let flag = self.read_u8()?;
+            if flag != 0x01 {
+                return Err(DecodeError::InvalidWitnessFlag(flag));
+            }
+        }
+
+        let count = self.read_varint()?;
+        let mut inputs = Vec::with_capacity(self.capacity_hint(count));
+        for _ in 0..count {
+            inputs.push(TxIn {
+                prev_txid: self.read_array()?,
+                prev_vout: self.read_u32()?,
+                script_sig: self.read_var_bytes()?,
+                sequence: self.read_u32()?,
+                witness: Vec::new(),
+            });
+        }
+
+        let count = self.read_varint()?;
+        let mut outputs = Vec::with_capacity(self.capacity_hint(count));
+        for _ in 0..count {
+            outputs.push(TxOut {
+                value: self.read_u64()?,
+                script_pubkey: self.read_var_bytes()?,
+            });
+        }
+
+        if with_witness {
+            for input in inputs.iter_mut() {
+                let items = self.read_varint()?;
+                let mut witness = Vec::with_capacity(self.capacity_hint(items));
+                for _ in 0..items {
+                    witness.push(self.read_var_bytes()?);
+                }
+                input.witness = witness;
+            }
+            if inputs.iter().all(|input| input.witness.is_empty()) {
+                return Err(DecodeError::SuperfluousWitness);
+            }
+        }
+
+        let lock_time = self.read_u32()?;
+        Ok(Transaction { version, inputs, outputs, lock_time })
+    }
+
+    pub(crate) fn finish(&self) -> Result<(), DecodeError> {
+        match self.data.len() - self.pos {
+            0 => Ok(()),
+            n => Err(DecodeError::TrailingBytes(n)),
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::address::validate_address;
+    use crate::block::BlockTemplate;
+    use crate::coinbase::CoinbaseBuilder;
+    use crate::gbt::GetBlockTemplate;
+    use crate::hash::hash_to_display_hex;
+    use crate::merkle::compute_merkle_root;
+    use crate::network::Network;
+
+    /// Mainnet block 0.
+    pub(crate) const GENESIS_BLOCK_HEX: &str = concat!(
+        "0100000000000000000000000000000000000000000000000000000000000000",
+        "000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa",
+        "4b1e5e4a29ab5f49ffff001d1dac2b7c01010000000100000000000000000000",
+        "00000000000000000000000000000000000000000000ffffffff4d04ffff001d",
+        "0104455468652054696d65732030332f4a616e2f32303039204368616e63656c",
+        "6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f75742066",
+        "6f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe554827",
+        "1967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4",
+        "f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000",
+    );
+
+    /// Mainnet block 170, with the first transaction between two people.
+    pub(crate) const BLOCK_170_HEX: &str = concat!(
+        "0100000055bd840a78798ad0da853f68974f3d183e2bd1db6a842c1feecf222a",
+        "00000000ff104ccb05421ab93e63f8c3ce5c2c2e9dbb37de2764b3a3175c8166",
+        "562cac7d51b96a49ffff001d283e9e7002010000000100000000000000000000",
+        "00000000000000000000000000000000000000000000ffffffff0704ffff001d",
+        "0102ffffffff0100f2052a01000000434104d46c4968bde02899d2aa0963367c",
+        "7a6ce34eec332b32e42e5f3407e052d64ac625da6f0718e7b302140434bd7257",
+        "06957c092db53805b821a85b23a7ac61725bac000000000100000001c997a5e5",
+        "6e104102fa209c6a852dd90660a20b2d9c352423edce25857fcd370400000000",
+        "4847304402204e45e16932b8af514961a1d3a1a25fdf3f4f7732e9d624c6c615",
+        "48ab5fb8cd410220181522ec8eca07de4860a4acdd12909d831cc56cbbac4622",
+        "082221a8768d1d0901ffffffff0200ca9a3b00000000434104ae1a62fe09c5f5",
+        "1b13905f07f06b99a2f7159b2225f374cd378d71302fa28414e7aab37397f554",
+        "a7df5f142c21c1b7303b8a0626f1baded5c72a704f7e6cd84cac00286bee0000",
+        "000043410411db93e1dcdb8a016b49840f8c53bc1eb68a382e97b1482ecad7b1",
+        "48a6909a5cb2e0eaddfb84ccf9744464f82e160bfa9b8b64f9d4c03f999b8643",
+        "f656b412a3ac00000000",
+    );
+
+    /// Signed P2WPKH example transaction from BIP143 (one legacy and one
+    /// witness input).
+    const BIP143_P2WPKH_TX_HEX: &str = concat!(
+        "01000000000102fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433",
+        "541db4e4ad969f00000000494830450221008b9d1dc26ba6a9cb62127b02742f",
+        "a9d754cd3bebf337f7a55d114c8e5cdd30be022040529b194ba3f9281a99f2b1",
+        "c0a19c0489bc22ede944ccf4ecbab4cc618ef3ed01eeffffffef51e1b804cc89",
+        "d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffff",
+        "ffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac",
+        "7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0",
+        "167faa815988ac000247304402203609e17b84f6a7d30c80bfa610b5b4542f32",
+        "a8a0d5447a12fb1366d7f01cc44a0220573a954c4518331561406f90300e8f33",
+        "58f51928d43c212a8caed02de67eebee0121025476c2e83188368da1ff3e292e",
+        "7acafcdb3566bb0ad253f62fc70f07aeee635711000000",
+    );
+
+    #[test]
+    fn test_decode_genesis_block() {
+        let block = decode_block_hex(GENESIS_BLOCK_HEX).unwrap();
+
+        assert_eq!(
+            hash_to_display_hex(&block.hash()),
+            "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"
+        );
+        assert_eq!(block.header.version, 1);
+        assert_eq!(block.header.prev_block_hash, [0u8; 32]);
+        assert_eq!(block.header.timestamp, 1231006505);
+        assert_eq!(block.header.bits, 0x1d00ffff);
+        assert_eq!(block.header.nonce, 2083236893);
+
+        assert_eq!(block.transactions.len(), 1);
+        let coinbase = &block.transactions[0];
+        assert!(coinbase.is_coinbase());
+        assert!(!coinbase.has_witness());
+        assert_eq!(coinbase.outputs[0].value, 5_000_000_000);
+        assert!(coinbase.inputs[0].script_sig.ends_with(b"second bailout for banks"));
+        assert_eq!(coinbase.txid(), block.header.merkle_root);
+
+        assert_eq!(hex::encode(block.serialize()), GENESIS_BLOCK_HEX);
+    }
+
+    #[test]
+    fn test_decode_block_170() {
+        let block = decode_block_hex(BLOCK_170_HEX).unwrap();
+
+        assert_eq!(
+            hash_to_display_hex(&block.hash()),
+            "00000000d1145790a8694403d4063f323d499e655c83426834d4ce2f8dd4a2ee"
+        );
+        assert_eq!(block.transactions.len(), 2);
+        let spend = &block.transactions[1];
+        assert!(!spend.is_coinbase());
+        assert_eq!(
+            hash_to_display_hex(&spend.txid()),
+            "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16"
+        );
+        assert_eq!(spend.outputs.len(), 2);
+        assert_eq!(spend.outputs[0].value, 1_000_000_000);
+        assert_eq!(spend.outputs[1].value, 4_000_000_000);
+        assert_eq!(spend.inputs[0].prev_vout, 0);
+
+        let txids: Vec<[u8; 32]> = block.transactions.iter().map(|tx| tx.txid()).collect();
+        assert_eq!(compute_merkle_root(&txids), block.header.merkle_root);
+        assert_eq!(hex::encode(block.serialize()), BLOCK_170_HEX);
+    }
+
+    #[test]
+    fn test_decode_witness_transaction() {
+        let tx = decode_transaction_hex(BIP143_P2WPKH_TX_HEX).unwrap();
+
+        assert!(tx.has_witness());
+        assert_eq!(tx.inputs.len(), 2);
+        assert!(tx.inputs[0].witness.is_empty());
+        assert_eq!(tx.inputs[1].witness.len(), 2);
+        assert_eq!(tx.inputs[1].witness[1].len(), 33);
+        assert_eq!(tx.lock_time, 0x11);
+        assert_eq!(hex::encode(tx.serialize()), BIP143_P2WPKH_TX_HEX);
+
+        // Stripping the witness changes the wtxid but not the txid
+        let stripped = decode_transaction(&tx.serialize_without_witness()).unwrap();
+        assert!(!stripped.has_witness());
+        assert_eq!(stripped.txid(), tx.txid());
+        assert_ne!(tx.wtxid(), tx.txid());
+        assert_eq!(stripped.wtxid(), stripped.txid());
+    }
+
+    #[test]
+    fn test_round_trip_serialize_block() {
+        let network = Network::Testnet4;
+        let gbt = GetBlockTemplate::parse(crate::gbt::tests::TEMPLATE_JSON).unwrap();
+        let address = validate_address("tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx", network).unwrap();
+        let builder = CoinbaseBuilder::new(network, gbt.height, address);
+        let mut template = BlockTemplate::from_getblocktemplate(network, gbt, builder).unwrap();
+        template.header.nonce = 0xDEADBEEF;
+
+        let bytes = template.serialize_block();
+        let block = decode_block(&bytes).unwrap();
+
+        assert_eq!(block.header.serialize(), template.header.serialize());
+        assert_eq!(block.transactions.len(), 1 + template.transactions.len());
+        let coinbase = &block.transactions[0];
+        assert!(coinbase.is_coinbase());
+        assert_eq!(coinbase.inputs[0].witness, [[0u8; 32].to_vec()]);
+        assert_eq!(coinbase.txid(), template.coinbase.txid);
+        assert_eq!(coinbase.serialize(), template.coinbase.raw_tx_with_witness);
+        assert_eq!(block.serialize(), bytes);
+    }
+
+    #[test]
+    fn test_decode_header() {
+        let bytes = hex::decode(&GENESIS_BLOCK_HEX[..160]).unwrap();
+        let header = decode_header(&bytes).unwrap();
+        assert_eq!(header.serialize().as_slice(), bytes.as_slice());
+
+        assert_eq!(decode_header(&bytes[..79]).unwrap_err(), DecodeError::UnexpectedEnd);
+        let mut long = bytes.clone();
+        long.push(0);
+        assert_eq!(decode_header(&long).unwrap_err(), DecodeError::TrailingBytes(1));
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        let tx = hex::decode(BIP143_P2WPKH_TX_HEX).unwrap();
+
+        // Truncated anywhere
+        for len in [0, 4, 6, 50, tx.len() - 1] {
+            assert_eq!(decode_transaction(&tx[..len]).unwrap_err(), DecodeError::UnexpectedEnd);
+        }
+
+        // Unknown witness flag
+        let mut bad_flag = tx.clone();
+        bad_flag[5] = 0x02;
+        assert_eq!(decode_transaction(&bad_flag).unwrap_err(), DecodeError::InvalidWitnessFlag(2));
+
+        // Witness format with no witness data
+        let legacy = decode_transaction(&tx).unwrap().serialize_without_witness();
+        let mut empty_witness = legacy[..4].to_vec();
+        empty_witness.extend_from_slice(&[0x00, 0x01]);
+        empty_witness.extend_from_slice(&legacy[4..legacy.len() - 4]);
+        empty_witness.extend_from_slice(&[0x00, 0x00]);
+        empty_witness.extend_from_slice(&legacy[legacy.len() - 4..]);
+        assert_eq!(decode_transaction(&empty_witness).unwrap_err(), DecodeError::SuperfluousWitness);
+
+        // Varint 0xfd0100 should have been the single byte 0x01
+        let mut block = hex::decode(GENESIS_BLOCK_HEX).unwrap();
+        block.splice(80..81, [0xfd, 0x01, 0x00]);
+        assert_eq!(decode_block(&block).unwrap_err(), DecodeError::NonCanonicalVarint);
+
+        assert_eq!(decode_block_hex("zz").unwrap_err(), DecodeError::InvalidHex);
+    }
+}
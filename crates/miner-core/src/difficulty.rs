@@ -1,167 +1,192 @@
 //! Bitcoin difficulty target conversion and utilities.
 
+use crate::block::BlockHeader;
+use crate::network::{Network, MAX_TIMEWARP};
+use crate::uint::U256;
+
 /// Convert compact "bits" representation to a 256-bit target.
 ///
 /// The bits format is: [exponent (1 byte)][mantissa (3 bytes)]
 /// Target = mantissa * 256^(exponent - 3)
 ///
 /// The result is a 32-byte big-endian representation of the target.
+/// Negative and overflowing bits give a zero target, which no hash meets.
 pub fn bits_to_target(bits: u32) -> [u8; 32] {
-    let exponent = ((bits >> 24) & 0xFF) as usize;
-    let mantissa = bits & 0x007FFFFF;
-
-    // Handle negative flag (bit 23 of mantissa)
-    // In practice, this shouldn't be set for valid difficulty targets
-    let is_negative = (bits & 0x00800000) != 0;
-    if is_negative {
-        return [0u8; 32]; // Invalid negative target
-    }
-
-    let mut target = [0u8; 32];
-
-    if exponent == 0 {
-        // Special case: exponent 0 means target is 0
-        return target;
-    }
-
-    if exponent <= 3 {
-        // Mantissa fits in fewer bytes than specified
-        let shift = 8 * (3 - exponent);
-        let value = mantissa >> shift;
-
-        // Place at the end (least significant position)
-        if exponent >= 1 { target[31] = (value & 0xFF) as u8; }
-        if exponent >= 2 { target[30] = ((value >> 8) & 0xFF) as u8; }
-        if exponent >= 3 { target[29] = ((value >> 16) & 0xFF) as u8; }
-    } else {
-        // Normal case: mantissa goes at position (32 - exponent)
-        let pos = 32 - exponent;
-
-        // Place the 3 mantissa bytes
-        if pos < 32 { target[pos] = ((mantissa >> 16) & 0xFF) as u8; }
-        if pos + 1 < 32 { target[pos + 1] = ((mantissa >> 8) & 0xFF) as u8; }
-        if pos + 2 < 32 { target[pos + 2] = (mantissa & 0xFF) as u8; }
-    }
-
-    target
+    U256::from_compact(bits).unwrap_or_default().to_be_bytes()
 }
 
 /// Convert a 256-bit target back to compact "bits" representation.
 ///
 /// This is the inverse of `bits_to_target`.
 pub fn target_to_bits(target: &[u8; 32]) -> u32 {
-    // Find the first non-zero byte
-    let mut first_nonzero = 0;
-    while first_nonzero < 32 && target[first_nonzero] == 0 {
-        first_nonzero += 1;
-    }
-
-    if first_nonzero == 32 {
-        // All zeros - return 0
-        return 0;
-    }
-
-    // Calculate exponent (number of bytes from the right)
-    let exponent = (32 - first_nonzero) as u32;
-
-    // Extract mantissa (up to 3 bytes starting at first non-zero)
-    let mut mantissa: u32 = 0;
-
-    if first_nonzero < 32 {
-        mantissa |= (target[first_nonzero] as u32) << 16;
-    }
-    if first_nonzero + 1 < 32 {
-        mantissa |= (target[first_nonzero + 1] as u32) << 8;
-    }
-    if first_nonzero + 2 < 32 {
-        mantissa |= target[first_nonzero + 2] as u32;
-    }
-
-    // If the high bit of mantissa is set, we need to shift right
-    // to avoid the negative flag
-    let (exp_adj, mant_adj) = if mantissa & 0x00800000 != 0 {
-        (exponent + 1, mantissa >> 8)
-    } else {
-        (exponent, mantissa)
-    };
-
-    (exp_adj << 24) | (mant_adj & 0x007FFFFF)
+    U256::from_be_bytes(*target).to_compact()
 }
 
 /// Check if a hash meets the difficulty target.
 ///
-/// Returns true if hash < target (valid proof of work).
+/// Returns true if hash <= target (valid proof of work). Both are 32-byte
+/// big-endian numbers.
 #[inline]
 pub fn hash_meets_target(hash: &[u8; 32], target: &[u8; 32]) -> bool {
-    // Both are 32-byte big-endian numbers
-    // Compare byte by byte from most significant
-    for i in 0..32 {
-        if hash[i] < target[i] {
-            return true;
-        }
-        if hash[i] > target[i] {
-            return false;
-        }
-    }
-    // Exactly equal - technically meets target but extremely rare
-    true
+    U256::from_be_bytes(*hash) <= U256::from_be_bytes(*target)
 }
 
-/// Calculate approximate difficulty from bits.
+/// The "difficulty 1" target: the genesis block target (bits 0x1d00ffff),
+/// `0x00000000ffff0000...`. Pools measure share difficulty against it.
+pub const DIFF1_TARGET: [u8; 32] = [
+    0x00, 0x00, 0x00, 0x00, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+/// Calculate difficulty from bits.
 ///
 /// Difficulty = max_target / current_target
 /// Where max_target is the genesis block target (bits = 0x1d00ffff)
 pub fn bits_to_difficulty(bits: u32) -> f64 {
-    // Genesis block bits: 0x1d00ffff
-    // This represents the "difficulty 1" target
-    const GENESIS_BITS: u32 = 0x1d00ffff;
+    difficulty_of(U256::from_compact(bits).unwrap_or_default())
+}
 
-    let current_target = bits_to_target(bits);
-    let genesis_target = bits_to_target(GENESIS_BITS);
+/// Difficulty of a 256-bit big-endian target.
+pub fn target_to_difficulty(target: &[u8; 32]) -> f64 {
+    difficulty_of(U256::from_be_bytes(*target))
+}
 
-    // Convert to f64 for division (approximate)
-    let current_f64 = target_to_f64(&current_target);
-    let genesis_f64 = target_to_f64(&genesis_target);
+/// Exact difficulty of a hash (internal byte order), as pools report it.
+///
+/// The hash would meet any share target of this difficulty or lower.
+pub fn hash_difficulty(hash: &[u8; 32]) -> f64 {
+    difficulty_of(U256::from_le_bytes(*hash))
+}
 
-    if current_f64 == 0.0 {
+/// `DIFF1_TARGET / value`, infinite for zero.
+fn difficulty_of(value: U256) -> f64 {
+    if value.is_zero() {
         return f64::INFINITY;
     }
 
-    genesis_f64 / current_f64
+    // Exact integer part plus the fraction left over
+    let (quotient, remainder) = U256::from_be_bytes(DIFF1_TARGET).div_rem(value);
+    quotient.to_f64() + remainder.to_f64() / value.to_f64()
 }
 
-/// Convert a 256-bit target to an approximate f64 value.
-fn target_to_f64(target: &[u8; 32]) -> f64 {
-    // Find first non-zero byte
-    let mut first_nonzero = 0;
-    while first_nonzero < 32 && target[first_nonzero] == 0 {
-        first_nonzero += 1;
+/// Convert a pool share difficulty to a 256-bit target.
+///
+/// Pool difficulty 1 corresponds to the genesis target (bits 0x1d00ffff),
+/// so the target is `0xffff * 2^208 / difficulty`. Difficulties at or below
+/// zero map to the maximum target.
+pub fn difficulty_to_target(difficulty: f64) -> [u8; 32] {
+    let mut value = 65535.0 / difficulty;
+    if difficulty <= 0.0 || !value.is_finite() {
+        return [0xff; 32];
+    }
+
+    // Normalize into a 53-bit integer mantissa with a binary exponent
+    let mut shift: i32 = 208;
+    while value >= 9_007_199_254_740_992.0 {
+        value /= 2.0;
+        shift += 1;
+    }
+    while value < 4_503_599_627_370_496.0 {
+        value *= 2.0;
+        shift -= 1;
     }
+    let mantissa = value as u64;
 
-    if first_nonzero == 32 {
-        return 0.0;
+    if shift + 53 > 256 {
+        return [0xff; 32];
     }
 
-    // Take up to 8 bytes for precision
-    let mut value: u64 = 0;
-    for i in 0..8 {
-        if first_nonzero + i < 32 {
-            value = (value << 8) | (target[first_nonzero + i] as u64);
+    let target = if shift >= 0 {
+        U256::from_u64(mantissa) << shift as u32
+    } else {
+        U256::from_u64(mantissa) >> (-shift) as u32
+    };
+    target.to_be_bytes()
+}
+
+/// Compute the bits the block after `tip` must use.
+///
+/// `period_start` is the first block of the tip's difficulty period, at
+/// height `tip_height - tip_height % 2016` (the tip itself if it starts
+/// one). `timestamp` is the new block's timestamp, which decides whether
+/// the testnet 20-minute rule applies.
+pub fn next_work_required(
+    network: Network,
+    tip_height: u32,
+    tip: &BlockHeader,
+    period_start: &BlockHeader,
+    timestamp: u32,
+) -> u32 {
+    if !(tip_height + 1).is_multiple_of(network.difficulty_adjustment_interval()) {
+        if !network.allows_min_difficulty_blocks() {
+            return tip.bits;
         }
+
+        // No block for more than 20 minutes: the minimum difficulty is allowed
+        if timestamp > tip.timestamp.saturating_add(2 * network.pow_target_spacing()) {
+            return network.pow_limit_bits();
+        }
+
+        // Otherwise the period's real difficulty. Bitcoin Core walks back
+        // past minimum difficulty blocks to find it; every other block in
+        // the period carries the bits of the first one.
+        return period_start.bits;
+    }
+
+    retarget(network, period_start, tip)
+}
+
+/// Compute the bits of the first block of a new difficulty period.
+///
+/// `first` and `last` are the first and last blocks of the period that just
+/// ended. The new target is the old one scaled by how long the period took
+/// relative to two weeks, with the timespan clamped to between a quarter
+/// and four times that (so difficulty moves by at most a factor of four)
+/// and the target capped at the network's pow limit.
+///
+/// Like Bitcoin Core, the timespan is measured over 2015 block intervals,
+/// from the first block's timestamp to the last one's.
+pub fn retarget(network: Network, first: &BlockHeader, last: &BlockHeader) -> u32 {
+    if network.pow_no_retargeting() {
+        return last.bits;
     }
 
-    // Scale by position using libm for no_std compatibility
-    let shift = (31 - first_nonzero) * 8;
-    let exponent = (shift as i32) - 56;
-    (value as f64) * pow2_f64(exponent)
+    // BIP94: retarget from the first block's bits, which cannot use the
+    // 20-minute rule, rather than a possibly minimum difficulty last block
+    let bits = if network.enforces_bip94() { first.bits } else { last.bits };
+    calculate_next_work_required(network, bits, first.timestamp, last.timestamp)
 }
 
-/// Compute 2^exp for f64, no_std compatible.
-fn pow2_f64(exp: i32) -> f64 {
-    if exp >= 0 {
-        (1u64 << exp.min(63)) as f64
+/// Earliest timestamp BIP94 allows for the block after `tip`.
+///
+/// The first block of a difficulty period may be at most [`MAX_TIMEWARP`]
+/// seconds older than its parent, which closes the time warp attack.
+/// Returns 0 where the rule does not apply.
+pub fn timewarp_min_time(network: Network, tip_height: u32, tip: &BlockHeader) -> u32 {
+    let starts_period = (tip_height + 1).is_multiple_of(network.difficulty_adjustment_interval());
+    if network.enforces_bip94() && starts_period {
+        tip.timestamp.saturating_sub(MAX_TIMEWARP)
     } else {
-        1.0 / (1u64 << (-exp).min(63)) as f64
+        0
+    }
+}
+
+/// Scale the target of `bits` by the time the period actually took.
+///
+/// The timespan is clamped to a quarter and four times the target timespan,
+/// and the result to the network's pow limit.
+fn calculate_next_work_required(network: Network, bits: u32, first_time: u32, last_time: u32) -> u32 {
+    let target_timespan = network.pow_target_timespan() as i64;
+    let actual_timespan = (last_time as i64 - first_time as i64).clamp(target_timespan / 4, target_timespan * 4);
+
+    let pow_limit = U256::from_be_bytes(network.pow_limit());
+    let target = U256::from_compact(bits)
+        .unwrap_or_default()
+        .checked_mul(U256::from_u64(actual_timespan as u64))
+        .map(|target| target / U256::from_u64(target_timespan as u64));
+    match target {
+        Some(target) if target <= pow_limit => target.to_compact(),
+        _ => pow_limit.to_compact(),
     }
 }
 
@@ -207,8 +232,8 @@ mod tests {
         assert_eq!(target[5], 0xff);
 
         // Rest should be zeros
-        for i in 6..32 {
-            assert_eq!(target[i], 0x00, "byte {} should be 0", i);
+        for (i, byte) in target.iter().enumerate().skip(6) {
+            assert_eq!(*byte, 0x00, "byte {} should be 0", i);
         }
     }
 
@@ -221,8 +246,8 @@ mod tests {
 
         // Exponent = 0x17 = 23, so target starts at byte 32-23 = 9
         // First 9 bytes should be zero
-        for i in 0..9 {
-            assert_eq!(target[i], 0x00, "byte {} should be 0", i);
+        for (i, byte) in target.iter().enumerate().take(9) {
+            assert_eq!(*byte, 0x00, "byte {} should be 0", i);
         }
 
         // Mantissa 0x034219 should be at bytes 9, 10, 11
@@ -269,10 +294,159 @@ mod tests {
         assert!(!hash_meets_target(&bad_hash, &target));
     }
 
+    #[test]
+    fn test_difficulty_to_target() {
+        // Difficulty 1 is the genesis target
+        assert_eq!(difficulty_to_target(1.0), bits_to_target(0x1d00ffff));
+
+        // Doubling the difficulty halves the target
+        let half = difficulty_to_target(2.0);
+        assert_eq!(&half[..6], &[0x00, 0x00, 0x00, 0x00, 0x7f, 0xff]);
+        assert_eq!(half[6], 0x80);
+
+        // Fractional difficulties give larger targets
+        let easy = difficulty_to_target(1.0 / 256.0);
+        assert_eq!(&easy[..5], &[0x00, 0x00, 0x00, 0xff, 0xff]);
+
+        // Nonsensical difficulties saturate to the maximum target
+        assert_eq!(difficulty_to_target(0.0), [0xff; 32]);
+        assert_eq!(difficulty_to_target(1e-80), [0xff; 32]);
+    }
+
+    fn header(bits: u32, timestamp: u32) -> BlockHeader {
+        BlockHeader::new([0u8; 32], [0u8; 32], timestamp, bits)
+    }
+
+    #[test]
+    fn test_testnet4_min_difficulty_rule() {
+        let network = Network::Testnet4;
+        let limit = network.pow_limit_bits();
+        let start = header(0x1d0fffff, 1_700_000_000);
+        let tip = header(0x1d0fffff, 1_700_060_000);
+
+        // Within 20 minutes the period's difficulty applies
+        assert_eq!(next_work_required(network, 10_000, &tip, &start, 1_700_061_200), 0x1d0fffff);
+        // After 20 minutes the minimum difficulty is allowed
+        assert_eq!(next_work_required(network, 10_000, &tip, &start, 1_700_061_201), limit);
+
+        // A minimum difficulty tip does not lower the next block's difficulty
+        let min_tip = header(limit, 1_700_061_300);
+        assert_eq!(next_work_required(network, 10_000, &min_tip, &start, 1_700_061_400), 0x1d0fffff);
+
+        // Mainnet has no such rule
+        assert_eq!(next_work_required(Network::Mainnet, 10_000, &tip, &start, 1_800_000_000), 0x1d0fffff);
+    }
+
+    #[test]
+    fn test_bip94_retarget() {
+        let network = Network::Testnet4;
+        let timespan = network.pow_target_timespan();
+        let start = header(0x1c7fffff, 1_700_000_000);
+        // The period took exactly two weeks but ended on a minimum difficulty block
+        let tip = header(network.pow_limit_bits(), 1_700_000_000 + timespan);
+
+        // BIP94 keeps the real difficulty; without it the retarget would
+        // start from the tip's minimum difficulty
+        assert_eq!(next_work_required(network, 4031, &tip, &start, tip.timestamp + 600), 0x1c7fffff);
+        assert_eq!(
+            calculate_next_work_required(network, tip.bits, start.timestamp, tip.timestamp),
+            network.pow_limit_bits()
+        );
+
+        // Twice as slow halves the difficulty, clamped at four times
+        let slow = header(0x1c7fffff, 1_700_000_000 + 2 * timespan);
+        assert_eq!(next_work_required(network, 4031, &slow, &start, slow.timestamp), 0x1d00ffff);
+        let stalled = header(0x1b7fffff, 1_700_000_000 + 10 * timespan);
+        let start = header(0x1b7fffff, 1_700_000_000);
+        assert_eq!(next_work_required(network, 4031, &stalled, &start, stalled.timestamp), 0x1c01ffff);
+
+        // The first block of a period may not warp back more than 10 minutes
+        assert_eq!(timewarp_min_time(network, 4031, &tip), tip.timestamp - MAX_TIMEWARP);
+        assert_eq!(timewarp_min_time(network, 4030, &tip), 0);
+        assert_eq!(timewarp_min_time(Network::Mainnet, 4031, &tip), 0);
+    }
+
+    #[test]
+    fn test_retarget_mainnet_history() {
+        // Vectors from Bitcoin Core's pow_tests
+        let network = Network::Mainnet;
+        let cases = [
+            // Blocks 30240..=32255: a normal adjustment
+            (1261130161, 1262152739, 0x1d00ffff, 0x1d00d86a),
+            // Blocks 0..=2015: slower than two weeks, but capped at the pow limit
+            (1231006505, 1233061996, 0x1d00ffff, 0x1d00ffff),
+            // Blocks 66528..=68543: too fast, clamped to a quarter of the timespan
+            (1279008237, 1279297671, 0x1c05a3f4, 0x1c0168fd),
+            // Blocks 46368..=48383: too slow, clamped to four times the timespan
+            (1263163443, 1269211443, 0x1c387f6f, 0x1d00e1fd),
+        ];
+
+        for (first_time, last_time, bits, expected) in cases {
+            let first = header(bits, first_time);
+            let last = header(bits, last_time);
+            assert_eq!(retarget(network, &first, &last), expected, "retarget from {:08x}", bits);
+        }
+    }
+
+    #[test]
+    fn test_retarget_network_params() {
+        let first = header(0x1c05a3f4, 1279008237);
+        let last = header(0x1c05a3f4, 1279297671);
+
+        // Regtest never retargets
+        assert_eq!(retarget(Network::Regtest, &first, &last), 0x1c05a3f4);
+        assert_eq!(retarget(Network::Signet, &first, &last), 0x1c0168fd);
+
+        // Only at the end of a period does next_work_required retarget
+        assert_eq!(next_work_required(Network::Mainnet, 68543, &last, &first, last.timestamp + 600), 0x1c0168fd);
+        assert_eq!(next_work_required(Network::Mainnet, 68542, &last, &first, last.timestamp + 600), 0x1c05a3f4);
+
+        // Signet's pow limit caps an easing retarget
+        let first = header(0x1e0377ae, 1_600_000_000);
+        let last = header(0x1e0377ae, 1_600_000_000 + 4 * Network::Signet.pow_target_timespan());
+        assert_eq!(retarget(Network::Signet, &first, &last), 0x1e0377ae);
+        assert_eq!(Network::Mainnet.difficulty_adjustment_interval(), 2016);
+    }
+
+    #[test]
+    fn test_hash_difficulty() {
+        assert_eq!(DIFF1_TARGET, bits_to_target(0x1d00ffff));
+        assert_eq!(target_to_difficulty(&DIFF1_TARGET), 1.0);
+        assert_eq!(target_to_difficulty(&difficulty_to_target(1024.0)), 1024.0);
+
+        // The genesis block's hash, in internal byte order
+        let mut genesis = [0u8; 32];
+        hex::decode_to_slice("000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f", &mut genesis)
+            .unwrap();
+        genesis.reverse();
+        assert_eq!(hash_difficulty(&genesis), 2536.4262984453103);
+        assert_eq!(hash_difficulty(&[0u8; 32]), f64::INFINITY);
+
+        // A hash is a share at exactly the difficulties up to its own
+        let target = difficulty_to_target(2536.0);
+        assert!(hash_meets_target(&crate::hash::reverse_bytes(&genesis), &target));
+        let target = difficulty_to_target(2537.0);
+        assert!(!hash_meets_target(&crate::hash::reverse_bytes(&genesis), &target));
+    }
+
     #[test]
     fn test_difficulty_calculation() {
         // Genesis block should have difficulty 1
         let genesis_diff = bits_to_difficulty(0x1d00ffff);
         assert!((genesis_diff - 1.0).abs() < 0.01);
     }
+
+    #[test]
+    fn test_difficulty_is_exact() {
+        assert_eq!(bits_to_difficulty(0x1d00ffff), 1.0);
+        assert_eq!(bits_to_difficulty(0x17034219), 86388558925171.02);
+        assert_eq!(bits_to_difficulty(0x1b0404cb), 16307.420938523983);
+        assert_eq!(bits_to_difficulty(0x207fffff), 4.6565423739069247e-10);
+
+        // Negative and overflowing bits are unreachable targets
+        assert_eq!(bits_to_target(0x04923456), [0u8; 32]);
+        assert_eq!(bits_to_target(0xff123456), [0u8; 32]);
+        assert_eq!(bits_to_difficulty(0xff123456), f64::INFINITY);
+        assert!(hash_meets_target(&bits_to_target(0x1d00ffff), &bits_to_target(0x1d00ffff)));
+    }
 }
@@ -0,0 +1,339 @@
+//! Parsing of bitcoind `getblocktemplate` responses (BIP22/BIP23).
+//!
+//! A template carries the mempool transactions the node selected for the
+//! next block, so mining on it collects their fees in addition to the subsidy.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use serde_json::Value;
+use crate::coinbase::CoinbaseError;
+
+/// Errors that can occur while parsing a block template or building on it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateError {
+    /// The input is not valid JSON.
+    InvalidJson(String),
+    /// The RPC response carried an error instead of a template.
+    Rpc(String),
+    /// A required field is missing.
+    MissingField(&'static str),
+    /// A field is present but malformed.
+    InvalidField(&'static str),
+    /// The coinbase builder is for a different height than the template.
+    HeightMismatch { template: u32, coinbase: u32 },
+    /// The coinbase cannot pay out the template's `coinbasevalue`.
+    Coinbase(CoinbaseError),
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateError::InvalidJson(e) => write!(f, "Invalid template JSON: {}", e),
+            TemplateError::Rpc(e) => write!(f, "getblocktemplate failed: {}", e),
+            TemplateError::MissingField(name) => write!(f, "Template is missing '{}'", name),
+            TemplateError::InvalidField(name) => write!(f, "Template field '{}' is malformed", name),
+            TemplateError::HeightMismatch { template, coinbase } => write!(
+                f,
+                "Template is for height {} but the coinbase commits to height {}",
+                template, coinbase
+            ),
+            TemplateError::Coinbase(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// A transaction selected by the node for inclusion in the block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateTransaction {
+    /// Raw transaction bytes, as they appear in the block (with witness).
+    pub data: Vec<u8>,
+    /// Transaction ID (internal byte order).
+    pub txid: [u8; 32],
+    /// Witness transaction ID (internal byte order).
+    pub wtxid: [u8; 32],
+    /// Fee paid by the transaction in satoshis.
+    pub fee: u64,
+    /// Transaction weight in weight units.
+    pub weight: u64,
+}
+
+/// The fields of a `getblocktemplate` response needed to build a block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GetBlockTemplate {
+    /// Block version, including any versionbits the node signals.
+    pub version: i32,
+    /// Hash of the previous block (internal byte order).
+    pub prev_block_hash: [u8; 32],
+    /// Transactions to include after the coinbase, in order.
+    pub transactions: Vec<TemplateTransaction>,
+    /// Total value available to the coinbase (subsidy + fees).
+    pub coinbase_value: u64,
+    /// Complete scriptPubKey of the witness commitment output, if provided.
+    pub default_witness_commitment: Option<Vec<u8>>,
+    /// The challenge blocks must satisfy, on signet.
+    pub signet_challenge: Option<Vec<u8>>,
+    /// The node's current time for the block.
+    pub cur_time: u32,
+    /// The earliest timestamp the block may use.
+    pub min_time: u32,
+    /// Difficulty target in compact format.
+    pub bits: u32,
+    /// Height of the block being built.
+    pub height: u32,
+}
+
+impl GetBlockTemplate {
+    /// Parse a template from JSON.
+    ///
+    /// Accepts either the bare template object or a full JSON-RPC response
+    /// with the template under `result`.
+    pub fn parse(json: &str) -> Result<Self, TemplateError> {
+        let value: Value = serde_json::from_str(json)
+            .map_err(|e| TemplateError::InvalidJson(e.to_string()))?;
+        Self::from_value(&value)
+    }
+
+    /// Parse a template from an already-decoded JSON value.
+    pub fn from_value(value: &Value) -> Result<Self, TemplateError> {
+        let template = match value.get("result") {
+            Some(result) => {
+                if let Some(error) = value.get("error").filter(|e| !e.is_null()) {
+                    return Err(TemplateError::Rpc(error.to_string()));
+                }
+                result
+            }
+            None => value,
+        };
+
+        let version = get_i64(template, "version")?;
+        let version = i32::try_from(version)
+            .or_else(|_| u32::try_from(version).map(|v| v as i32))
+            .map_err(|_| TemplateError::InvalidField("version"))?;
+
+        let bits_hex = get_str(template, "bits")?;
+        let bits = u32::from_str_radix(bits_hex, 16)
+            .map_err(|_| TemplateError::InvalidField("bits"))?;
+
+        let transactions = template
+            .get("transactions")
+            .and_then(Value::as_array)
+            .ok_or(TemplateError::MissingField("transactions"))?
+            .iter()
+            .map(parse_transaction)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(GetBlockTemplate {
+            version,
+            prev_block_hash: parse_hash(template, "previousblockhash")?,
+            transactions,
+            coinbase_value: get_u64(template, "coinbasevalue")?,
+            default_witness_commitment: get_optional_hex(template, "default_witness_commitment")?,
+            signet_challenge: get_optional_hex(template, "signet_challenge")?,
+            cur_time: get_u32(template, "curtime")?,
+            min_time: get_u32(template, "mintime")?,
+            bits,
+            height: get_u32(template, "height")?,
+        })
+    }
+
+    /// Total fees of all template transactions.
+    pub fn total_fees(&self) -> u64 {
+        self.transactions.iter().map(|tx| tx.fee).sum()
+    }
+}
+
+fn parse_transaction(value: &Value) -> Result<TemplateTransaction, TemplateError> {
+    let data = hex::decode(get_str(value, "data")?)
+        .map_err(|_| TemplateError::InvalidField("data"))?;
+    let txid = parse_hash(value, "txid")?;
+
+    // "hash" is the wtxid; it equals the txid for transactions without witness
+    let wtxid = match value.get("hash") {
+        Some(_) => parse_hash(value, "hash")?,
+        None => txid,
+    };
+
+    Ok(TemplateTransaction {
+        data,
+        txid,
+        wtxid,
+        fee: value.get("fee").and_then(Value::as_u64).unwrap_or(0),
+        weight: value.get("weight").and_then(Value::as_u64).unwrap_or(0),
+    })
+}
+
+fn get_str<'a>(value: &'a Value, name: &'static str) -> Result<&'a str, TemplateError> {
+    value
+        .get(name)
+        .ok_or(TemplateError::MissingField(name))?
+        .as_str()
+        .ok_or(TemplateError::InvalidField(name))
+}
+
+fn get_optional_hex(value: &Value, name: &'static str) -> Result<Option<Vec<u8>>, TemplateError> {
+    match value.get(name) {
+        Some(v) => {
+            let hex_str = v.as_str().ok_or(TemplateError::InvalidField(name))?;
+            Ok(Some(hex::decode(hex_str).map_err(|_| TemplateError::InvalidField(name))?))
+        }
+        None => Ok(None),
+    }
+}
+
+fn get_i64(value: &Value, name: &'static str) -> Result<i64, TemplateError> {
+    value
+        .get(name)
+        .ok_or(TemplateError::MissingField(name))?
+        .as_i64()
+        .ok_or(TemplateError::InvalidField(name))
+}
+
+fn get_u64(value: &Value, name: &'static str) -> Result<u64, TemplateError> {
+    value
+        .get(name)
+        .ok_or(TemplateError::MissingField(name))?
+        .as_u64()
+        .ok_or(TemplateError::InvalidField(name))
+}
+
+fn get_u32(value: &Value, name: &'static str) -> Result<u32, TemplateError> {
+    u32::try_from(get_u64(value, name)?).map_err(|_| TemplateError::InvalidField(name))
+}
+
+/// Parse a display-order hash and convert it to internal byte order.
+fn parse_hash(value: &Value, name: &'static str) -> Result<[u8; 32], TemplateError> {
+    let bytes = hex::decode(get_str(value, name)?).map_err(|_| TemplateError::InvalidField(name))?;
+    let mut hash: [u8; 32] = bytes.try_into().map_err(|_| TemplateError::InvalidField(name))?;
+    hash.reverse();
+    Ok(hash)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A regtest template with one witness and one legacy transaction.
+    pub(crate) const TEMPLATE_JSON: &str = r#"{
+        "result": {
+            "capabilities": ["proposal"],
+            "version": 536870912,
+            "rules": ["csv", "!segwit", "taproot"],
+            "vbavailable": {},
+            "vbrequired": 0,
+            "previousblockhash": "0f9188f13cb7b2c71f2a335e3a4fc328bf5beb436012afca590b1a11466e2206",
+            "transactions": [
+                {
+                    "data": "02000000000101aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa0000000000fdffffff01e8030000000000001600140000000000000000000000000000000000000000024730440220111111111111111111111111111111111111111111111111111111111111111102202222222222222222222222222222222222222222222222222222222222222222012103333333333333333333333333333333333333333333333333333333333333333300000000",
+                    "txid": "1111111111111111111111111111111111111111111111111111111111111111",
+                    "hash": "2222222222222222222222222222222222222222222222222222222222222222",
+                    "depends": [],
+                    "fee": 1500,
+                    "sigops": 1,
+                    "weight": 437
+                },
+                {
+                    "data": "0100000001bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb0000000000ffffffff01d007000000000000015100000000",
+                    "txid": "3333333333333333333333333333333333333333333333333333333333333333",
+                    "hash": "3333333333333333333333333333333333333333333333333333333333333333",
+                    "depends": [],
+                    "fee": 2500,
+                    "sigops": 0,
+                    "weight": 244
+                }
+            ],
+            "coinbaseaux": {},
+            "coinbasevalue": 5000004000,
+            "longpollid": "0f9188f13cb7b2c71f2a335e3a4fc328bf5beb436012afca590b1a11466e22061",
+            "target": "7fffff0000000000000000000000000000000000000000000000000000000000",
+            "mintime": 1296688603,
+            "mutable": ["time", "transactions", "prevblock"],
+            "noncerange": "00000000ffffffff",
+            "sigoplimit": 80000,
+            "sizelimit": 4000000,
+            "weightlimit": 4000000,
+            "curtime": 1700000000,
+            "bits": "207fffff",
+            "height": 101,
+            "default_witness_commitment": "6a24aa21a9ede2f61c3f71d1defd3fa999dfa36953755c690689799962b48bebd836974e8cf9"
+        },
+        "error": null,
+        "id": "miner"
+    }"#;
+
+    /// An empty template from a custom signet whose challenge is OP_TRUE
+    /// (`bitcoind -signet -signetchallenge=51`).
+    pub(crate) const SIGNET_TEMPLATE_JSON: &str = r#"{
+        "capabilities": ["proposal"],
+        "version": 536870912,
+        "rules": ["csv", "!segwit", "!signet", "taproot"],
+        "vbavailable": {},
+        "vbrequired": 0,
+        "previousblockhash": "00000008819873e925422c1ff0f99f7cc9bbb232af63a077a480a3633bee1ef6",
+        "transactions": [],
+        "coinbaseaux": {},
+        "coinbasevalue": 5000000000,
+        "longpollid": "00000008819873e925422c1ff0f99f7cc9bbb232af63a077a480a3633bee1ef61",
+        "target": "00000377ae000000000000000000000000000000000000000000000000000000",
+        "mintime": 1598918401,
+        "mutable": ["time", "transactions", "prevblock"],
+        "noncerange": "00000000ffffffff",
+        "sigoplimit": 80000,
+        "sizelimit": 4000000,
+        "weightlimit": 4000000,
+        "curtime": 1700000000,
+        "bits": "1e0377ae",
+        "height": 1,
+        "default_witness_commitment": "6a24aa21a9ede2f61c3f71d1defd3fa999dfa36953755c690689799962b48bebd836974e8cf9",
+        "signet_challenge": "51"
+    }"#;
+
+    #[test]
+    fn test_parse_template() {
+        let gbt = GetBlockTemplate::parse(TEMPLATE_JSON).unwrap();
+
+        assert_eq!(gbt.version, 0x2000_0000);
+        assert_eq!(gbt.bits, 0x207fffff);
+        assert_eq!(gbt.height, 101);
+        assert_eq!(gbt.cur_time, 1_700_000_000);
+        assert_eq!(gbt.min_time, 1_296_688_603);
+        assert_eq!(gbt.coinbase_value, 5_000_004_000);
+        assert_eq!(gbt.total_fees(), 4000);
+
+        // Hashes are converted from display order to internal order
+        assert_eq!(gbt.prev_block_hash[0], 0x06);
+        assert_eq!(gbt.prev_block_hash[31], 0x0f);
+
+        assert_eq!(gbt.transactions.len(), 2);
+        assert_eq!(gbt.transactions[0].txid, [0x11; 32]);
+        assert_eq!(gbt.transactions[0].wtxid, [0x22; 32]);
+        assert_eq!(gbt.transactions[1].weight, 244);
+
+        let commitment = gbt.default_witness_commitment.unwrap();
+        assert_eq!(commitment.len(), 38);
+        assert_eq!(&commitment[..6], &[0x6a, 0x24, 0xaa, 0x21, 0xa9, 0xed]);
+        assert_eq!(gbt.signet_challenge, None);
+
+        let gbt = GetBlockTemplate::parse(SIGNET_TEMPLATE_JSON).unwrap();
+        assert_eq!(gbt.signet_challenge, Some(alloc::vec![0x51]));
+        assert_eq!(gbt.bits, crate::network::Network::Signet.pow_limit_bits());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(matches!(GetBlockTemplate::parse("{"), Err(TemplateError::InvalidJson(_))));
+        assert!(matches!(
+            GetBlockTemplate::parse(r#"{"result": null, "error": {"code": -10, "message": "Bitcoin Core is in initial sync"}, "id": 1}"#),
+            Err(TemplateError::Rpc(_))
+        ));
+
+        let missing = TEMPLATE_JSON.replace("\"coinbasevalue\"", "\"coinbasevalue_\"");
+        assert_eq!(
+            GetBlockTemplate::parse(&missing),
+            Err(TemplateError::MissingField("coinbasevalue"))
+        );
+
+        let bad_bits = TEMPLATE_JSON.replace("207fffff", "xyz");
+        assert_eq!(GetBlockTemplate::parse(&bad_bits), Err(TemplateError::InvalidField("bits")));
+    }
+}
@@ -1,6 +1,34 @@
 //! SHA256 double-hashing and mining functions.
 
-use sha2::{Digest, Sha256};
+use sha2::digest::generic_array::GenericArray;
+use sha2::{compress256, Digest, Sha256};
+use crate::difficulty::hash_meets_target;
+use crate::simd::{Backend, MAX_LANES};
+
+/// SHA256 initial hash values (FIPS 180-4, section 5.3.3).
+const SHA256_IV: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a,
+    0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+/// Second block of an 80-byte message: 16 data bytes, then padding and
+/// the bit length (640) in the last 8 bytes.
+const HEADER_TAIL_PADDING: [u8; 48] = {
+    let mut padding = [0u8; 48];
+    padding[0] = 0x80;
+    padding[46] = 0x02;
+    padding[47] = 0x80;
+    padding
+};
+
+/// Padding for hashing a single 32-byte digest: 0x80, zeros, and the
+/// bit length (256).
+const DIGEST_PADDING: [u8; 32] = {
+    let mut padding = [0u8; 32];
+    padding[0] = 0x80;
+    padding[30] = 0x01;
+    padding
+};
 
 /// Bitcoin's double SHA256: SHA256(SHA256(data)).
 ///
@@ -8,7 +36,7 @@ use sha2::{Digest, Sha256};
 #[inline]
 pub fn double_sha256(data: &[u8]) -> [u8; 32] {
     let first = Sha256::digest(data);
-    let second = Sha256::digest(&first);
+    let second = Sha256::digest(first);
     let mut result = [0u8; 32];
     result.copy_from_slice(&second);
     result
@@ -23,9 +51,99 @@ pub fn sha256(data: &[u8]) -> [u8; 32] {
     result
 }
 
+/// Compress one 64-byte block into a SHA256 state.
+#[inline]
+fn compress(state: &mut [u32; 8], block: &[u8; 64]) {
+    compress256(state, core::slice::from_ref(GenericArray::from_slice(block)));
+}
+
+/// Serialize a SHA256 state as a big-endian digest.
+#[inline]
+fn state_to_bytes(state: &[u32; 8]) -> [u8; 32] {
+    let mut out = [0u8; 32];
+    for (i, word) in state.iter().enumerate() {
+        out[i * 4..i * 4 + 4].copy_from_slice(&word.to_be_bytes());
+    }
+    out
+}
+
+/// The SHA256 state after compressing a single 64-byte block.
+///
+/// This is the "midstate": the work on a message prefix that can be reused
+/// when only the bytes after it change.
+pub fn sha256_midstate(block: &[u8; 64]) -> [u32; 8] {
+    let mut state = SHA256_IV;
+    compress(&mut state, block);
+    state
+}
+
+/// Precomputed header hashing state for a fixed 76-byte header prefix.
+///
+/// The first 64 header bytes (version, previous hash and most of the merkle
+/// root) do not depend on the nonce, so they are compressed once. Each nonce
+/// then costs one compression for the header tail and one for the second
+/// SHA256, instead of three.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeaderMidstate {
+    /// SHA256 state after the first 64 header bytes.
+    state: [u32; 8],
+    /// Second block: merkle root tail, time, bits, nonce slot and padding.
+    tail: [u8; 64],
+}
+
+impl HeaderMidstate {
+    /// Precompute the midstate for a header without its nonce.
+    pub fn new(header_without_nonce: &[u8; 76]) -> Self {
+        let mut first = [0u8; 64];
+        first.copy_from_slice(&header_without_nonce[..64]);
+
+        let mut tail = [0u8; 64];
+        tail[..12].copy_from_slice(&header_without_nonce[64..]);
+        tail[16..].copy_from_slice(&HEADER_TAIL_PADDING);
+
+        HeaderMidstate {
+            state: sha256_midstate(&first),
+            tail,
+        }
+    }
+
+    /// The SHA256 state after the first 64 header bytes.
+    pub fn state(&self) -> [u32; 8] {
+        self.state
+    }
+
+    /// The second header block: header bytes 64..76, an empty nonce slot
+    /// and SHA256 padding.
+    pub fn tail(&self) -> &[u8; 64] {
+        &self.tail
+    }
+
+    /// Double SHA256 of the full header with `nonce`.
+    ///
+    /// Identical to `double_sha256` over the 80-byte header.
+    #[inline]
+    pub fn hash(&self, nonce: u32) -> [u8; 32] {
+        let mut tail = self.tail;
+        tail[12..16].copy_from_slice(&nonce.to_le_bytes());
+
+        let mut state = self.state;
+        compress(&mut state, &tail);
+
+        let mut second = [0u8; 64];
+        second[..32].copy_from_slice(&state_to_bytes(&state));
+        second[32..].copy_from_slice(&DIGEST_PADDING);
+
+        let mut state = SHA256_IV;
+        compress(&mut state, &second);
+        state_to_bytes(&state)
+    }
+}
+
 /// Result of a mining batch operation.
 #[derive(Debug, Clone)]
 pub struct MiningResult {
+    /// Version of the header that was searched.
+    pub version: i32,
     /// The nonce that produced the hash (if found).
     pub nonce: Option<u32>,
     /// The resulting block hash (if found).
@@ -40,8 +158,9 @@ pub struct MiningResult {
 
 impl MiningResult {
     /// Create a result indicating no match found.
-    pub fn not_found(hashes: u64) -> Self {
+    pub fn not_found(version: i32, hashes: u64) -> Self {
         MiningResult {
+            version,
             nonce: None,
             hash: None,
             hashes_computed: hashes,
@@ -51,8 +170,9 @@ impl MiningResult {
     }
 
     /// Create a result indicating a share was found.
-    pub fn share(nonce: u32, hash: [u8; 32], hashes: u64) -> Self {
+    pub fn share(version: i32, nonce: u32, hash: [u8; 32], hashes: u64) -> Self {
         MiningResult {
+            version,
             nonce: Some(nonce),
             hash: Some(hash),
             hashes_computed: hashes,
@@ -62,8 +182,9 @@ impl MiningResult {
     }
 
     /// Create a result indicating a valid block was found.
-    pub fn block(nonce: u32, hash: [u8; 32], hashes: u64) -> Self {
+    pub fn block(version: i32, nonce: u32, hash: [u8; 32], hashes: u64) -> Self {
         MiningResult {
+            version,
             nonce: Some(nonce),
             hash: Some(hash),
             hashes_computed: hashes,
@@ -75,10 +196,13 @@ impl MiningResult {
 
 /// Mine a range of nonces, checking for both shares and valid blocks.
 ///
+/// Uses the widest SIMD backend this CPU supports; see [`mine_batch_with`].
+///
 /// # Arguments
 /// * `header_without_nonce` - 76-byte block header (everything except the nonce)
-/// * `block_target` - 32-byte target that block hash must be below
-/// * `share_min_zeros` - Minimum leading zero bits (in display format) for a share
+/// * `block_target` - 32-byte target a block's hash must be at or below
+/// * `share_target` - 32-byte target a share's hash must be at or below
+///   (see `difficulty_to_target`); all zeros disables shares
 /// * `nonce_start` - Starting nonce value
 /// * `nonce_count` - Number of nonces to try
 ///
@@ -87,72 +211,161 @@ impl MiningResult {
 pub fn mine_batch(
     header_without_nonce: &[u8; 76],
     block_target: &[u8; 32],
-    share_min_zeros: u32,
+    share_target: &[u8; 32],
     nonce_start: u32,
     nonce_count: u32,
 ) -> MiningResult {
-    // Pre-allocate the full 80-byte header
-    let mut header = [0u8; 80];
-    header[..76].copy_from_slice(header_without_nonce);
+    mine_batch_with(
+        Backend::detect(),
+        header_without_nonce,
+        block_target,
+        share_target,
+        nonce_start,
+        nonce_count,
+    )
+}
+
+/// [`mine_batch`] on a specific SHA256 backend.
+///
+/// Every backend returns the same result for the same inputs.
+pub fn mine_batch_with(
+    backend: Backend,
+    header_without_nonce: &[u8; 76],
+    block_target: &[u8; 32],
+    share_target: &[u8; 32],
+    nonce_start: u32,
+    nonce_count: u32,
+) -> MiningResult {
+    mine_batch_shares_with(
+        backend,
+        header_without_nonce,
+        block_target,
+        share_target,
+        nonce_start,
+        nonce_count,
+        usize::MAX,
+        |_| {},
+    )
+}
+
+/// A hash that met the share target in [`mine_batch_shares`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FoundShare {
+    /// The nonce that produced the hash.
+    pub nonce: u32,
+    /// The hash (internal byte order).
+    pub hash: [u8; 32],
+    /// Whether the hash also meets the block target.
+    pub block: bool,
+}
+
+/// Mine a range of nonces, passing every share to `on_share` in nonce order.
+///
+/// Like [`mine_batch`], the batch stops at the first block, which is passed
+/// on too (flagged as a block). It also stops once `max_shares` shares have
+/// been passed on (0 for no limit), so none is dropped: `hashes_computed` in the result tells
+/// where the caller should resume. The result carries the best share or the
+/// block, as `mine_batch` would.
+pub fn mine_batch_shares(
+    header_without_nonce: &[u8; 76],
+    block_target: &[u8; 32],
+    share_target: &[u8; 32],
+    nonce_start: u32,
+    nonce_count: u32,
+    max_shares: usize,
+    on_share: impl FnMut(FoundShare),
+) -> MiningResult {
+    mine_batch_shares_with(
+        Backend::detect(),
+        header_without_nonce,
+        block_target,
+        share_target,
+        nonce_start,
+        nonce_count,
+        max_shares,
+        on_share,
+    )
+}
+
+/// [`mine_batch_shares`] on a specific SHA256 backend.
+#[allow(clippy::too_many_arguments)]
+pub fn mine_batch_shares_with(
+    backend: Backend,
+    header_without_nonce: &[u8; 76],
+    block_target: &[u8; 32],
+    share_target: &[u8; 32],
+    nonce_start: u32,
+    nonce_count: u32,
+    max_shares: usize,
+    mut on_share: impl FnMut(FoundShare),
+) -> MiningResult {
+    // The first 64 header bytes are the same for every nonce
+    let midstate = HeaderMidstate::new(header_without_nonce);
+    let version = i32::from_le_bytes([
+        header_without_nonce[0],
+        header_without_nonce[1],
+        header_without_nonce[2],
+        header_without_nonce[3],
+    ]);
+    let lanes = backend.lanes();
 
     let nonce_end = nonce_start.saturating_add(nonce_count);
-    let mut best_share: Option<(u32, [u8; 32], u32)> = None; // (nonce, hash, leading_zeros)
+    let mut best_share: Option<(u32, [u8; 32], [u8; 32])> = None; // (nonce, hash, display-order hash)
+    let mut shares = 0usize;
+    let mut hashes = [[0u8; 32]; MAX_LANES];
+    let mut nonce = nonce_start;
 
-    for nonce in nonce_start..nonce_end {
-        // Set the nonce (little-endian at bytes 76-79)
-        header[76..80].copy_from_slice(&nonce.to_le_bytes());
+    while nonce < nonce_end {
+        // Full vectors while they fit, then one nonce at a time
+        let count = if (nonce_end - nonce) as usize >= lanes {
+            backend.hash_lanes(&midstate, nonce, &mut hashes)
+        } else {
+            hashes[0] = midstate.hash(nonce);
+            1
+        };
 
-        // Compute double SHA256
-        let hash = double_sha256(&header);
+        for (i, hash) in hashes[..count].iter().enumerate() {
+            let nonce = nonce + i as u32;
+            let hashes = (nonce - nonce_start + 1) as u64;
 
-        // Check if hash meets block target (valid block!)
-        if hash_below_target(&hash, block_target) {
-            return MiningResult::block(nonce, hash, (nonce - nonce_start + 1) as u64);
-        }
+            // Check if hash meets block target (valid block!)
+            // The hash is a little-endian number, so compare it in display order
+            let display = reverse_bytes(hash);
+            if hash_meets_target(&display, block_target) {
+                on_share(FoundShare { nonce, hash: *hash, block: true });
+                return MiningResult::block(version, nonce, *hash, hashes);
+            }
 
-        // Check if hash qualifies as a share (enough leading zeros in display format)
-        let leading_zeros = count_leading_zeros(&hash);
-        if leading_zeros >= share_min_zeros {
-            // Keep track of the best (most leading zeros) share found
-            match &best_share {
-                None => best_share = Some((nonce, hash, leading_zeros)),
-                Some((_, _, best_zeros)) if leading_zeros > *best_zeros => {
-                    best_share = Some((nonce, hash, leading_zeros));
+            // Check if hash qualifies as a share, keeping the lowest (highest
+            // difficulty) one. Big-endian arrays compare like the numbers.
+            if hash_meets_target(&display, share_target) {
+                match &best_share {
+                    Some((_, _, best)) if display >= *best => {}
+                    _ => best_share = Some((nonce, *hash, display)),
+                }
+
+                on_share(FoundShare { nonce, hash: *hash, block: false });
+                shares += 1;
+                if max_shares != 0 && shares >= max_shares {
+                    let (nonce, hash, _) = best_share.unwrap();
+                    return MiningResult::share(version, nonce, hash, hashes);
                 }
-                _ => {}
             }
         }
+
+        nonce += count as u32;
     }
 
-    let hashes = nonce_count as u64;
+    let hashes = (nonce_end - nonce_start) as u64;
 
     // Return share if found, otherwise no result
     if let Some((nonce, hash, _)) = best_share {
-        MiningResult::share(nonce, hash, hashes)
+        MiningResult::share(version, nonce, hash, hashes)
     } else {
-        MiningResult::not_found(hashes)
+        MiningResult::not_found(version, hashes)
     }
 }
 
-/// Check if a hash is below a target (valid proof of work).
-///
-/// Both hash and target are treated as 256-bit big-endian numbers.
-/// The hash must be strictly less than the target.
-#[inline]
-pub fn hash_below_target(hash: &[u8; 32], target: &[u8; 32]) -> bool {
-    // Compare as big-endian: start from most significant byte
-    for i in 0..32 {
-        if hash[i] < target[i] {
-            return true;
-        }
-        if hash[i] > target[i] {
-            return false;
-        }
-    }
-    // Equal - not below
-    false
-}
-
 /// Reverse the byte order of a 32-byte array.
 ///
 /// Bitcoin often displays hashes in reverse byte order (little-endian display).
@@ -196,6 +409,8 @@ pub fn count_leading_zeros(hash: &[u8; 32]) -> u32 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::difficulty::{difficulty_to_target, hash_difficulty};
+    use crate::simd::Backend;
 
     #[test]
     fn test_double_sha256() {
@@ -211,34 +426,6 @@ mod tests {
         assert_eq!(hash.as_slice(), expected.as_slice());
     }
 
-    #[test]
-    fn test_hash_below_target() {
-        let target = [
-            0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
-            0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
-            0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
-            0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
-        ];
-
-        // Hash with two leading zero bytes - should pass
-        let good_hash = [
-            0x00, 0x00, 0x12, 0x34, 0xFF, 0xFF, 0xFF, 0xFF,
-            0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
-            0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
-            0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
-        ];
-        assert!(hash_below_target(&good_hash, &target));
-
-        // Hash with only one leading zero byte - should fail
-        let bad_hash = [
-            0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-        ];
-        assert!(!hash_below_target(&bad_hash, &target));
-    }
-
     #[test]
     fn test_count_leading_zeros() {
         let hash1 = [0x00; 32]; // All zeros
@@ -252,6 +439,189 @@ mod tests {
         assert_eq!(count_leading_zeros(&hash2), 20); // 8 + 8 + 4
     }
 
+    #[test]
+    fn test_mine_batch_compares_display_order() {
+        // Target 0x007fff...: roughly 1 in 512 hashes qualifies
+        let mut target = [0xFF; 32];
+        target[0] = 0x00;
+        target[1] = 0x7F;
+
+        let header = [0x5Au8; 76];
+        let result = mine_batch(&header, &target, &[0u8; 32], 0, 100_000);
+        assert!(result.block_found);
+
+        // The winning hash must be below target as a little-endian number
+        let hash = result.hash.unwrap();
+        assert_eq!(hash[31], 0x00);
+        assert!(hash[30] < 0x80);
+    }
+
+    /// The genesis block header without its nonce.
+    fn genesis_header() -> [u8; 76] {
+        let mut header = [0u8; 76];
+        header[0..4].copy_from_slice(&1u32.to_le_bytes());
+        let mut merkle_root = hex::decode(
+            "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b"
+        ).unwrap();
+        merkle_root.reverse();
+        header[36..68].copy_from_slice(&merkle_root);
+        header[68..72].copy_from_slice(&1231006505u32.to_le_bytes());
+        header[72..76].copy_from_slice(&0x1d00ffffu32.to_le_bytes());
+        header
+    }
+
+    #[test]
+    fn test_sha256_midstate() {
+        // A one-block message that is already padded: SHA256("abc")
+        let mut block = [0u8; 64];
+        block[..3].copy_from_slice(b"abc");
+        block[3] = 0x80;
+        block[63] = 0x18;
+
+        assert_eq!(
+            hex::encode(state_to_bytes(&sha256_midstate(&block))),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_header_midstate_genesis() {
+        let midstate = HeaderMidstate::new(&genesis_header());
+        let hash = midstate.hash(2083236893);
+
+        assert_eq!(
+            hash_to_display_hex(&hash),
+            "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"
+        );
+    }
+
+    #[test]
+    fn test_header_midstate_matches_double_sha256() {
+        // Deterministic pseudo-random headers (xorshift)
+        let mut seed = 0x9E37_79B9_7F4A_7C15u64;
+        let mut next = || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+
+        for _ in 0..32 {
+            let mut header = [0u8; 80];
+            for byte in header.iter_mut() {
+                *byte = next() as u8;
+            }
+            let nonce = next() as u32;
+            header[76..].copy_from_slice(&nonce.to_le_bytes());
+
+            let prefix: [u8; 76] = header[..76].try_into().unwrap();
+            assert_eq!(HeaderMidstate::new(&prefix).hash(nonce), double_sha256(&header));
+        }
+    }
+
+    #[test]
+    fn test_mine_batch_backends_agree() {
+        // Shares are common at this difficulty and a block shows up near
+        // the end, off any lane boundary
+        let header = genesis_header();
+        let mut target = [0xFF; 32];
+        target[0] = 0x00;
+        target[1] = 0x07;
+        let mut share_target = [0xFF; 32];
+        share_target[0] = 0x03;
+
+        for backend in Backend::available() {
+            for (start, count) in [(0u32, 37u32), (1000, 5003), (u32::MAX - 20, 100)] {
+                let expected = mine_batch_with(Backend::Scalar, &header, &target, &share_target, start, count);
+                let result = mine_batch_with(backend, &header, &target, &share_target, start, count);
+                assert_eq!(result.nonce, expected.nonce, "{}", backend.name());
+                assert_eq!(result.hash, expected.hash);
+                assert_eq!(result.hashes_computed, expected.hashes_computed);
+                assert_eq!(result.block_found, expected.block_found);
+            }
+        }
+    }
+
+    #[test]
+    fn test_mine_batch_returns_lowest_share() {
+        // About one hash in sixteen is a share; none is a block
+        let header = genesis_header();
+        let share_target = difficulty_to_target(1.0 / (1u64 << 28) as f64);
+        let result = mine_batch(&header, &[0u8; 32], &share_target, 0, 500);
+        assert!(result.share_found);
+        assert!(!result.block_found);
+
+        let midstate = HeaderMidstate::new(&header);
+        let best = (0..500).map(|nonce| reverse_bytes(&midstate.hash(nonce))).min().unwrap();
+        assert_eq!(result.hash.map(|hash| reverse_bytes(&hash)), Some(best));
+        assert!(hash_difficulty(&result.hash.unwrap()) >= 1.0 / (1u64 << 28) as f64);
+
+        // An all-zero share target finds nothing
+        let result = mine_batch(&header, &[0u8; 32], &[0u8; 32], 0, 500);
+        assert!(!result.share_found);
+        assert_eq!(result.hashes_computed, 500);
+    }
+
+    #[test]
+    fn test_mine_batch_shares_yields_every_share() {
+        let header = genesis_header();
+        let share_target = difficulty_to_target(1.0 / (1u64 << 28) as f64);
+        let midstate = HeaderMidstate::new(&header);
+        let expected: alloc::vec::Vec<u32> = (0..500)
+            .filter(|&nonce| hash_meets_target(&reverse_bytes(&midstate.hash(nonce)), &share_target))
+            .collect();
+        assert!(expected.len() > 3);
+
+        for backend in Backend::available() {
+            let mut found = alloc::vec::Vec::new();
+            let result =
+                mine_batch_shares_with(backend, &header, &[0u8; 32], &share_target, 0, 500, usize::MAX, |share| {
+                    found.push(share)
+                });
+            let nonces: alloc::vec::Vec<u32> = found.iter().map(|share| share.nonce).collect();
+            assert_eq!(nonces, expected, "{}", backend.name());
+            assert!(found.iter().all(|share| share.hash == midstate.hash(share.nonce) && !share.block));
+            assert_eq!(result.hashes_computed, 500);
+
+            // The result keeps the best of them, like mine_batch
+            let plain = mine_batch_with(backend, &header, &[0u8; 32], &share_target, 0, 500);
+            assert_eq!(result.hash, plain.hash);
+        }
+
+        // The cap stops the batch right after the last share passed on
+        let mut found = alloc::vec::Vec::new();
+        let result = mine_batch_shares(&header, &[0u8; 32], &share_target, 0, 500, 3, |share| found.push(share));
+        assert_eq!(found.len(), 3);
+        assert_eq!(result.hashes_computed, expected[2] as u64 + 1);
+        assert!(result.share_found);
+
+        // A cap of 0 means no cap
+        let mut found = alloc::vec::Vec::new();
+        let result = mine_batch_shares(&header, &[0u8; 32], &share_target, 0, 500, 0, |share| found.push(share));
+        assert_eq!(found.len(), expected.len());
+        assert_eq!(result.hashes_computed, 500);
+    }
+
+    #[test]
+    fn test_mine_batch_shares_stops_at_block() {
+        let header = genesis_header();
+        let mut block_target = [0xFF; 32];
+        block_target[0] = 0x00;
+        block_target[1] = 0x07;
+
+        let mut found = alloc::vec::Vec::new();
+        let result = mine_batch_shares(&header, &block_target, &[0xFF; 32], 0, 100_000, usize::MAX, |share| {
+            found.push(share)
+        });
+        assert!(result.block_found);
+
+        // Every hash before the block was a share, then the block itself
+        let nonce = result.nonce.unwrap();
+        assert_eq!(found.len(), nonce as usize + 1);
+        assert_eq!(found.last(), Some(&FoundShare { nonce, hash: result.hash.unwrap(), block: true }));
+        assert_eq!(result.hashes_computed, nonce as u64 + 1);
+    }
+
     #[test]
     fn test_reverse_bytes() {
         let original = [
@@ -4,8 +4,19 @@
 //! - Bitcoin address validation (P2PKH, P2SH, P2WPKH, P2WSH, P2TR)
 //! - Block header construction and serialization
 //! - Coinbase transaction building with BIP34 compliance
-//! - SHA256 double-hashing for mining
+//! - Coinbase reward splits between several recipients
+//! - Decoding of serialized headers, transactions and blocks
+//! - `getblocktemplate` (BIP22/BIP23) template parsing
+//! - SHA256 double-hashing for mining, with SIMD multi-lane backends
+//! - Header search over nonces and BIP320 version bits
 //! - Difficulty target conversion and comparison
+//! - Chainwork accounting and best-chain selection with reorg detection
+//! - 256-bit unsigned arithmetic for targets and work
+//! - Consensus checks on a block before submission
+//! - Variable share difficulty (vardiff) for a steady share rate
+//! - Signet block solutions (BIP325)
+//! - Stratum V1 and V2 pool protocols (with TCP clients under `std`)
+//! - A Bitcoin Core JSON-RPC client for templates and block submission (`std`)
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
@@ -13,17 +24,41 @@ extern crate alloc;
 
 pub mod address;
 pub mod block;
+pub mod chain;
 pub mod coinbase;
+pub mod decode;
 pub mod difficulty;
+pub mod gbt;
 pub mod hash;
 pub mod merkle;
 pub mod network;
+pub mod payout;
+#[cfg(feature = "std")]
+pub mod rpc;
+pub mod search;
+pub mod signet;
+pub mod simd;
+pub mod stratum;
+pub mod uint;
+pub mod validate;
+pub mod vardiff;
 
 pub use address::{validate_address, AddressError, AddressType, ValidatedAddress};
 pub use block::BlockInfo;
 pub use block::{BlockHeader, BlockTemplate};
-pub use coinbase::CoinbaseBuilder;
-pub use difficulty::{bits_to_target, hash_meets_target};
-pub use hash::{double_sha256, mine_batch, MiningResult};
-pub use merkle::compute_merkle_root;
+pub use chain::{ChainError, HeaderChain, Reorg, TipUpdate};
+pub use coinbase::{CoinbaseBuilder, CoinbaseError};
+pub use decode::{decode_block, decode_transaction, Block, DecodeError, Transaction};
+pub use difficulty::{bits_to_target, difficulty_to_target, hash_difficulty, hash_meets_target};
+pub use gbt::{GetBlockTemplate, TemplateError, TemplateTransaction};
+pub use hash::{double_sha256, mine_batch, mine_batch_shares, mine_batch_with, FoundShare, MiningResult};
+pub use merkle::{
+    compute_merkle_branch, compute_merkle_root, compute_witness_merkle_root, fold_merkle_branch, verify_merkle_proof,
+};
 pub use network::Network;
+pub use payout::{PayoutError, PayoutShare, Recipient};
+pub use search::HeaderSearch;
+pub use signet::{SignetError, SignetSolution, SignetTxs};
+pub use uint::U256;
+pub use validate::{validate_block, ValidationContext, Violation};
+pub use vardiff::Vardiff;
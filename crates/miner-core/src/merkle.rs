@@ -1,4 +1,8 @@
 //! Merkle tree computation for Bitcoin transactions.
+//!
+//! Besides the block's merkle root and witness commitment, this builds and
+//! checks merkle branches: the coinbase branch Stratum pools send, and
+//! inclusion proofs for any transaction.
 
 use alloc::vec::Vec;
 use crate::hash::double_sha256;
@@ -21,48 +25,123 @@ pub fn compute_merkle_root(txids: &[[u8; 32]]) -> [u8; 32] {
     let mut current_level: Vec<[u8; 32]> = txids.to_vec();
 
     while current_level.len() > 1 {
-        let mut next_level = Vec::with_capacity((current_level.len() + 1) / 2);
-
-        for i in (0..current_level.len()).step_by(2) {
-            let left = current_level[i];
-            // If odd number of elements, duplicate the last one
-            let right = if i + 1 < current_level.len() {
-                current_level[i + 1]
-            } else {
-                current_level[i]
-            };
-
-            // Concatenate and hash
-            let mut combined = [0u8; 64];
-            combined[..32].copy_from_slice(&left);
-            combined[32..].copy_from_slice(&right);
-            next_level.push(double_sha256(&combined));
-        }
-
-        current_level = next_level;
+        current_level = next_level(&current_level);
     }
 
     current_level[0]
 }
 
+/// Compute the merkle branch of the leaf at `index`: its sibling at each
+/// level, from the leaves up.
+///
+/// Together with the leaf and its index this proves the leaf is in the
+/// tree (an SPV inclusion proof). Returns `None` if `index` is out of range.
+pub fn compute_merkle_branch(txids: &[[u8; 32]], index: usize) -> Option<Vec<[u8; 32]>> {
+    if index >= txids.len() {
+        return None;
+    }
+
+    let mut branch = Vec::new();
+    let mut current_level: Vec<[u8; 32]> = txids.to_vec();
+    let mut index = index;
+
+    while current_level.len() > 1 {
+        // The last node of an odd level is its own sibling
+        let sibling = (index ^ 1).min(current_level.len() - 1);
+        branch.push(current_level[sibling]);
+        current_level = next_level(&current_level);
+        index /= 2;
+    }
+
+    Some(branch)
+}
+
+/// Compute the merkle branch Stratum sends for the coinbase.
+///
+/// `txids` are those of the transactions after the coinbase. The coinbase
+/// is the leftmost leaf, so its branch does not depend on its own txid and
+/// can be sent before the miner fills in the extranonce.
+pub fn compute_coinbase_branch(txids: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    let mut leaves = Vec::with_capacity(1 + txids.len());
+    leaves.push([0u8; 32]);
+    leaves.extend_from_slice(txids);
+    compute_merkle_branch(&leaves, 0).unwrap_or_default()
+}
+
+/// Fold a leaf and its merkle branch back into the root.
+///
+/// The bits of `index` say, level by level, whether the running hash is the
+/// right (1) or left (0) child.
+pub fn fold_merkle_branch(leaf: [u8; 32], branch: &[[u8; 32]], index: usize) -> [u8; 32] {
+    let mut root = leaf;
+    for (level, sibling) in branch.iter().enumerate() {
+        root = match (index >> level) & 1 {
+            0 => hash_pair(&root, sibling),
+            _ => hash_pair(sibling, &root),
+        };
+    }
+    root
+}
+
+/// Check that `leaf` sits at `index` in the tree with this `root`.
+///
+/// An index with bits beyond the branch's depth is rejected, so each leaf
+/// has a single valid index. As in Bitcoin, the duplicated last node of an
+/// odd level also proves index `n` for the last leaf `n - 1`; check the
+/// index against the transaction count when it matters.
+pub fn verify_merkle_proof(leaf: [u8; 32], branch: &[[u8; 32]], index: usize, root: &[u8; 32]) -> bool {
+    let in_range = index.checked_shr(branch.len() as u32).unwrap_or(0) == 0;
+    in_range && fold_merkle_branch(leaf, branch, index) == *root
+}
+
+/// Hash one level of the tree into the next, duplicating an odd last node.
+fn next_level(level: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    let mut next_level = Vec::with_capacity(level.len().div_ceil(2));
+
+    for i in (0..level.len()).step_by(2) {
+        let left = level[i];
+        // If odd number of elements, duplicate the last one
+        let right = if i + 1 < level.len() { level[i + 1] } else { level[i] };
+        next_level.push(hash_pair(&left, &right));
+    }
+
+    next_level
+}
+
+/// Concatenate two nodes and hash them.
+fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut combined = [0u8; 64];
+    combined[..32].copy_from_slice(left);
+    combined[32..].copy_from_slice(right);
+    double_sha256(&combined)
+}
+
+/// Script prefix of a witness commitment output: OP_RETURN, a 36-byte
+/// push and the commitment header 0xaa21a9ed.
+pub const WITNESS_COMMITMENT_PREFIX: [u8; 6] = [0x6a, 0x24, 0xaa, 0x21, 0xa9, 0xed];
+
+/// Compute the witness merkle root of a block (BIP141).
+///
+/// `wtxids` are those of the transactions after the coinbase; the coinbase
+/// itself counts as an all-zero wtxid, so a coinbase-only block has an
+/// all-zero root.
+pub fn compute_witness_merkle_root(wtxids: &[[u8; 32]]) -> [u8; 32] {
+    let mut leaves = Vec::with_capacity(1 + wtxids.len());
+    leaves.push([0u8; 32]);
+    leaves.extend_from_slice(wtxids);
+    compute_merkle_root(&leaves)
+}
+
 /// Compute the witness commitment for a SegWit block.
 ///
 /// The witness commitment is: SHA256d(witness_merkle_root || witness_reserved_value)
 ///
-/// For our coinbase-only block:
-/// - The witness merkle root for a single coinbase is the coinbase's wtxid
-/// - But the coinbase wtxid is defined as all zeros (32 zero bytes)
-/// - So witness_merkle_root = 0x00...00 for coinbase-only blocks
-///
 /// # Arguments
+/// * `witness_merkle_root` - From [`compute_witness_merkle_root`]
 /// * `witness_reserved_value` - The 32-byte witness reserved value from coinbase witness
-pub fn compute_witness_commitment(witness_reserved_value: &[u8; 32]) -> [u8; 32] {
-    // For coinbase-only block, witness merkle root is all zeros
-    // because wtxid of coinbase is defined as all zeros
-    let witness_merkle_root = [0u8; 32];
-
+pub fn compute_witness_commitment(witness_merkle_root: &[u8; 32], witness_reserved_value: &[u8; 32]) -> [u8; 32] {
     let mut data = [0u8; 64];
-    data[..32].copy_from_slice(&witness_merkle_root);
+    data[..32].copy_from_slice(witness_merkle_root);
     data[32..].copy_from_slice(witness_reserved_value);
 
     double_sha256(&data)
@@ -75,14 +154,8 @@ pub fn compute_witness_commitment(witness_reserved_value: &[u8; 32]) -> [u8; 32]
 pub fn witness_commitment_script(witness_commitment: &[u8; 32]) -> Vec<u8> {
     let mut script = Vec::with_capacity(38);
 
-    // OP_RETURN
-    script.push(0x6a);
-
-    // Push 36 bytes
-    script.push(0x24);
-
-    // Witness commitment header (magic bytes)
-    script.extend_from_slice(&[0xaa, 0x21, 0xa9, 0xed]);
+    // OP_RETURN, push 36 bytes, witness commitment header (magic bytes)
+    script.extend_from_slice(&WITNESS_COMMITMENT_PREFIX);
 
     // Witness commitment hash
     script.extend_from_slice(witness_commitment);
@@ -146,6 +219,77 @@ mod tests {
         assert_eq!(root, expected);
     }
 
+    /// Random leaves from a small xorshift generator, so the test is
+    /// deterministic.
+    fn random_leaves(state: &mut u64, count: usize) -> Vec<[u8; 32]> {
+        (0..count)
+            .map(|_| {
+                let mut leaf = [0u8; 32];
+                for chunk in leaf.as_chunks_mut::<8>().0 {
+                    *state ^= *state << 13;
+                    *state ^= *state >> 7;
+                    *state ^= *state << 17;
+                    *chunk = state.to_le_bytes();
+                }
+                leaf
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_merkle_branches_match_root() {
+        let mut state = 0x2545_F491_4F6C_DD1Du64;
+        for count in (1..=40).chain([63, 64, 65, 100, 257]) {
+            let leaves = random_leaves(&mut state, count);
+            let root = compute_merkle_root(&leaves);
+
+            for (index, leaf) in leaves.iter().enumerate() {
+                let branch = compute_merkle_branch(&leaves, index).unwrap();
+                assert_eq!(branch.len(), count.next_power_of_two().trailing_zeros() as usize);
+                assert_eq!(fold_merkle_branch(*leaf, &branch, index), root, "leaf {} of {}", index, count);
+                assert!(verify_merkle_proof(*leaf, &branch, index, &root));
+
+                // A different leaf or position does not verify
+                let mut wrong = *leaf;
+                wrong[0] ^= 1;
+                assert!(!verify_merkle_proof(wrong, &branch, index, &root));
+                if index ^ 1 < count {
+                    assert!(!verify_merkle_proof(*leaf, &branch, index ^ 1, &root));
+                }
+                assert!(!verify_merkle_proof(*leaf, &branch, index + (1 << branch.len()), &root));
+            }
+            assert_eq!(compute_merkle_branch(&leaves, count), None);
+        }
+    }
+
+    #[test]
+    fn test_coinbase_branch() {
+        let txids = [[0x11u8; 32], [0x22u8; 32], [0x33u8; 32]];
+        let branch = compute_coinbase_branch(&txids);
+        assert_eq!(branch, [txids[0], hash_pair(&txids[1], &txids[2])]);
+
+        let coinbase_txid = [0x42u8; 32];
+        assert_eq!(
+            fold_merkle_branch(coinbase_txid, &branch, 0),
+            compute_merkle_root(&[coinbase_txid, txids[0], txids[1], txids[2]])
+        );
+        assert!(compute_coinbase_branch(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_witness_merkle_root() {
+        // Coinbase-only: the zero wtxid is the root
+        assert_eq!(compute_witness_merkle_root(&[]), [0u8; 32]);
+
+        let wtxids = [[0x22u8; 32], [0x33u8; 32]];
+        assert_eq!(compute_witness_merkle_root(&wtxids), compute_merkle_root(&[[0u8; 32], wtxids[0], wtxids[1]]));
+
+        let mut data = [0u8; 64];
+        data[..32].copy_from_slice(&compute_witness_merkle_root(&wtxids));
+        data[32..].copy_from_slice(&[0x01; 32]);
+        assert_eq!(compute_witness_commitment(&compute_witness_merkle_root(&wtxids), &[0x01; 32]), double_sha256(&data));
+    }
+
     #[test]
     fn test_witness_commitment_script() {
         let commitment = [0xAB; 32];
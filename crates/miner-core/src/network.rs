@@ -1,12 +1,17 @@
 //! Bitcoin network definitions and constants.
 
 /// Bitcoin network type.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum Network {
     /// Bitcoin mainnet
+    #[default]
     Mainnet,
     /// Bitcoin testnet4
     Testnet4,
+    /// Local regression test network
+    Regtest,
+    /// Bitcoin signet, the default one or a custom signet
+    Signet,
 }
 
 impl Network {
@@ -14,7 +19,8 @@ impl Network {
     pub fn bech32_hrp(&self) -> &'static str {
         match self {
             Network::Mainnet => "bc",
-            Network::Testnet4 => "tb",
+            Network::Testnet4 | Network::Signet => "tb",
+            Network::Regtest => "bcrt",
         }
     }
 
@@ -22,7 +28,7 @@ impl Network {
     pub fn p2pkh_version(&self) -> u8 {
         match self {
             Network::Mainnet => 0x00,
-            Network::Testnet4 => 0x6f,
+            Network::Testnet4 | Network::Regtest | Network::Signet => 0x6f,
         }
     }
 
@@ -30,15 +36,16 @@ impl Network {
     pub fn p2sh_version(&self) -> u8 {
         match self {
             Network::Mainnet => 0x05,
-            Network::Testnet4 => 0xc4,
+            Network::Testnet4 | Network::Regtest | Network::Signet => 0xc4,
         }
     }
 
     /// Calculate block subsidy in satoshis for a given height.
     ///
-    /// The subsidy halves every 210,000 blocks, starting at 50 BTC.
+    /// The subsidy halves every 210,000 blocks (150 on regtest), starting
+    /// at 50 BTC.
     pub fn block_subsidy(&self, height: u32) -> u64 {
-        let halvings = height / 210_000;
+        let halvings = height / self.halving_interval();
         if halvings >= 64 {
             return 0;
         }
@@ -46,19 +53,98 @@ impl Network {
         5_000_000_000u64 >> halvings
     }
 
+    /// Number of blocks between subsidy halvings.
+    pub fn halving_interval(&self) -> u32 {
+        match self {
+            Network::Mainnet | Network::Testnet4 | Network::Signet => 210_000,
+            Network::Regtest => 150,
+        }
+    }
+
+    /// The easiest target a block may have, as a big-endian 256-bit number.
+    pub fn pow_limit(&self) -> [u8; 32] {
+        let mut limit = [0xFF; 32];
+        match self {
+            Network::Mainnet | Network::Testnet4 => limit[..4].fill(0),
+            Network::Regtest => limit[0] = 0x7F,
+            Network::Signet => {
+                limit[..5].copy_from_slice(&[0x00, 0x00, 0x03, 0x77, 0xae]);
+                limit[5..].fill(0);
+            }
+        }
+        limit
+    }
+
+    /// The pow limit in compact form, the `bits` of a minimum difficulty block.
+    pub fn pow_limit_bits(&self) -> u32 {
+        match self {
+            Network::Mainnet | Network::Testnet4 => 0x1d00ffff,
+            Network::Regtest => 0x207fffff,
+            Network::Signet => 0x1e0377ae,
+        }
+    }
+
+    /// Target time between blocks, in seconds.
+    pub fn pow_target_spacing(&self) -> u32 {
+        10 * 60
+    }
+
+    /// Target duration of a difficulty period, in seconds.
+    pub fn pow_target_timespan(&self) -> u32 {
+        14 * 24 * 60 * 60
+    }
+
+    /// Number of blocks between difficulty retargets.
+    pub fn difficulty_adjustment_interval(&self) -> u32 {
+        self.pow_target_timespan() / self.pow_target_spacing()
+    }
+
+    /// Whether a block more than twice the target spacing after its parent
+    /// may use the minimum difficulty (the testnet 20-minute rule).
+    pub fn allows_min_difficulty_blocks(&self) -> bool {
+        matches!(self, Network::Testnet4 | Network::Regtest)
+    }
+
+    /// Whether the difficulty stays fixed instead of retargeting.
+    pub fn pow_no_retargeting(&self) -> bool {
+        matches!(self, Network::Regtest)
+    }
+
+    /// Whether BIP94's testnet4 rules apply: retargets start from the
+    /// period's first block, and that block may not be more than
+    /// [`MAX_TIMEWARP`] seconds older than its parent.
+    pub fn enforces_bip94(&self) -> bool {
+        matches!(self, Network::Testnet4)
+    }
+
+    /// First height at which BIP34 requires the height in the coinbase.
+    pub fn bip34_height(&self) -> u32 {
+        match self {
+            Network::Mainnet => 227_931,
+            Network::Testnet4 | Network::Regtest | Network::Signet => 1,
+        }
+    }
+
     /// Get the default RPC port for this network.
     pub fn default_rpc_port(&self) -> u16 {
         match self {
             Network::Mainnet => 8332,
             Network::Testnet4 => 48332,
+            Network::Regtest => 18443,
+            Network::Signet => 38332,
         }
     }
 
     /// Get the mempool.space API base URL for this network.
+    ///
+    /// Regtest has no public explorer; this is the default address of a
+    /// local electrs Esplora server.
     pub fn mempool_api_url(&self) -> &'static str {
         match self {
             Network::Mainnet => "https://mempool.space/api",
             Network::Testnet4 => "https://mempool.space/testnet4/api",
+            Network::Regtest => "http://127.0.0.1:3002",
+            Network::Signet => "https://mempool.space/signet/api",
         }
     }
 
@@ -67,15 +153,18 @@ impl Network {
     pub fn blockstream_api_url(&self) -> Option<&'static str> {
         match self {
             Network::Mainnet => Some("https://blockstream.info/api"),
-            Network::Testnet4 => None, // Not supported
+            Network::Testnet4 | Network::Regtest | Network::Signet => None, // Not supported
         }
     }
 
     /// Parse network from string.
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         match s.to_lowercase().as_str() {
             "mainnet" | "main" | "bitcoin" => Some(Network::Mainnet),
             "testnet4" | "testnet" | "test" => Some(Network::Testnet4),
+            "regtest" => Some(Network::Regtest),
+            "signet" => Some(Network::Signet),
             _ => None,
         }
     }
@@ -85,6 +174,8 @@ impl Network {
         match self {
             Network::Mainnet => "mainnet",
             Network::Testnet4 => "testnet4",
+            Network::Regtest => "regtest",
+            Network::Signet => "signet",
         }
     }
 
@@ -93,6 +184,8 @@ impl Network {
         match self {
             Network::Mainnet => "Bitcoin Mainnet",
             Network::Testnet4 => "Bitcoin Testnet4",
+            Network::Regtest => "Bitcoin Regtest",
+            Network::Signet => "Bitcoin Signet",
         }
     }
 }
@@ -103,31 +196,38 @@ impl core::fmt::Display for Network {
     }
 }
 
-impl Default for Network {
-    fn default() -> Self {
-        Network::Mainnet
-    }
-}
-
 /// Block version with BIP9 versionbits signaling.
 pub const BLOCK_VERSION: i32 = 0x20000000;
 
+/// Version bits BIP320 leaves to miners (bits 13 to 28).
+pub const VERSION_ROLLING_MASK: u32 = 0x1fffe000;
+
 /// Size of a block header in bytes.
 pub const BLOCK_HEADER_SIZE: usize = 80;
 
 /// Coinbase maturity - blocks before coinbase can be spent.
 pub const COINBASE_MATURITY: u32 = 100;
 
+/// Maximum block weight (BIP141).
+pub const MAX_BLOCK_WEIGHT: u64 = 4_000_000;
+
+/// How far before its parent the first block of a difficulty period may
+/// be timestamped under BIP94, in seconds.
+pub const MAX_TIMEWARP: u32 = 600;
+
+/// How far ahead of the current time a block timestamp may be, in seconds.
+pub const MAX_FUTURE_BLOCK_TIME: u32 = 2 * 60 * 60;
+
 /// Maximum size of coinbase scriptSig.
 pub const MAX_COINBASE_SCRIPTSIG_SIZE: usize = 100;
 
 /// Minimum size of coinbase scriptSig (BIP34 requires at least height).
 pub const MIN_COINBASE_SCRIPTSIG_SIZE: usize = 2;
 
-/// Minimum leading zero bits required for a share (in display format).
-/// 8 bits = 1 zero byte = shares display as "00..." in hex.
-/// At 1 MH/s, this gives roughly 4000 shares per second (1 in 256 hashes).
-pub const SHARE_MIN_LEADING_ZEROS: u32 = 8;
+/// Starting pool difficulty of a share in the web miner, which vardiff
+/// adjusts to the hash rate from there.
+/// 2^-24 is about 1 in 256 hashes, whose display hex starts with "00".
+pub const SHARE_DIFFICULTY: f64 = 1.0 / 16_777_216.0;
 
 #[cfg(test)]
 mod tests {
@@ -153,11 +253,32 @@ mod tests {
         assert_eq!(network.block_subsidy(840_000), 312_500_000);
     }
 
+    #[test]
+    fn test_regtest_params() {
+        let network = Network::Regtest;
+        assert_eq!(network.block_subsidy(149), 5_000_000_000);
+        assert_eq!(network.block_subsidy(150), 2_500_000_000);
+        assert_eq!(network.block_subsidy(150 * 64), 0);
+        assert_eq!(network.bech32_hrp(), "bcrt");
+        assert_eq!(network.default_rpc_port(), 18443);
+
+        // The compact limit rounds the full limit down
+        let limit = crate::difficulty::bits_to_target(network.pow_limit_bits());
+        assert!(limit <= network.pow_limit());
+        assert_eq!(limit[..3], network.pow_limit()[..3]);
+        let limit = crate::difficulty::bits_to_target(Network::Mainnet.pow_limit_bits());
+        assert_eq!(limit[..6], Network::Mainnet.pow_limit()[..6]);
+        let limit = crate::difficulty::bits_to_target(Network::Signet.pow_limit_bits());
+        assert_eq!(limit, Network::Signet.pow_limit());
+    }
+
     #[test]
     fn test_network_from_str() {
         assert_eq!(Network::from_str("mainnet"), Some(Network::Mainnet));
         assert_eq!(Network::from_str("MAINNET"), Some(Network::Mainnet));
         assert_eq!(Network::from_str("testnet4"), Some(Network::Testnet4));
+        assert_eq!(Network::from_str("regtest"), Some(Network::Regtest));
+        assert_eq!(Network::from_str("signet"), Some(Network::Signet));
         assert_eq!(Network::from_str("invalid"), None);
     }
 }
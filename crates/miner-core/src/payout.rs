@@ -0,0 +1,262 @@
+//! Splitting the block reward between several recipients.
+//!
+//! A shared rig can pay each member straight from the coinbase. Every
+//! [`Recipient`] gets either a fixed amount or a weighted share of what the
+//! fixed amounts leave over. The split is deterministic, so every rebuild of
+//! the coinbase (e.g. for a new extra nonce) pays the same outputs:
+//!
+//! - Fixed amounts are paid exactly and must not be dust.
+//! - The rest is divided by weight, rounding down. The satoshis lost to
+//!   rounding go to the first weighted recipient in the list.
+//! - A weighted share below its output's dust threshold is dropped and the
+//!   split is redone without it, lowest weight first (the later recipient on
+//!   a tie), so no satoshis are lost to unspendable outputs.
+//!
+//! The amounts always add up to the reward; a split that cannot is an error.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use crate::address::ValidatedAddress;
+
+/// Relay fee rate Bitcoin Core uses for dust, in satoshis per kilo-vbyte.
+const DUST_RELAY_FEE: u64 = 3000;
+
+/// How much of the reward a recipient gets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayoutShare {
+    /// A share of the reward left after the fixed amounts.
+    Weight(u64),
+    /// A fixed amount in satoshis.
+    Amount(u64),
+}
+
+/// A coinbase output in a multi-recipient payout.
+#[derive(Debug, Clone)]
+pub struct Recipient {
+    /// The address paid.
+    pub address: ValidatedAddress,
+    /// Its part of the reward.
+    pub share: PayoutShare,
+}
+
+impl Recipient {
+    /// A recipient of a weighted share.
+    pub fn weight(address: ValidatedAddress, weight: u64) -> Self {
+        Recipient { address, share: PayoutShare::Weight(weight) }
+    }
+
+    /// A recipient of a fixed amount.
+    pub fn amount(address: ValidatedAddress, amount: u64) -> Self {
+        Recipient { address, share: PayoutShare::Amount(amount) }
+    }
+}
+
+/// Why a reward cannot be split.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PayoutError {
+    /// The recipient list is empty.
+    NoRecipients,
+    /// The recipient at this index has a weight of zero.
+    ZeroWeight(usize),
+    /// The recipient at this index would get a dust output.
+    Dust { index: usize, amount: u64, threshold: u64 },
+    /// The fixed amounts add up to more than the reward.
+    FixedExceedsReward { fixed: u64, reward: u64 },
+    /// Satoshis are left over with no weighted recipient to take them.
+    Unallocated(u64),
+}
+
+impl fmt::Display for PayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PayoutError::NoRecipients => write!(f, "No payout recipients"),
+            PayoutError::ZeroWeight(index) => write!(f, "Payout recipient {} has a weight of zero", index),
+            PayoutError::Dust { index, amount, threshold } => write!(
+                f,
+                "Payout recipient {} would get {} sats, below the dust threshold of {}",
+                index, amount, threshold
+            ),
+            PayoutError::FixedExceedsReward { fixed, reward } => {
+                write!(f, "Fixed payouts of {} sats exceed the reward of {} sats", fixed, reward)
+            }
+            PayoutError::Unallocated(amount) => {
+                write!(f, "{} sats of the reward are not paid to anyone; add a weighted recipient", amount)
+            }
+        }
+    }
+}
+
+/// The smallest output value Bitcoin Core relays for this script.
+///
+/// This is three times the fee to create and later spend the output at the
+/// dust relay fee rate: 546 sats for P2PKH, 294 for P2WPKH, 330 for P2TR.
+pub fn dust_threshold(script_pubkey: &[u8]) -> u64 {
+    let output_size = 8 + 1 + script_pubkey.len() as u64;
+
+    // Witness programs are spent with a discounted witness
+    let is_witness_program = script_pubkey.len() >= 4
+        && script_pubkey.len() <= 42
+        && (script_pubkey[0] == 0x00 || (0x51..=0x60).contains(&script_pubkey[0]))
+        && script_pubkey[1] as usize + 2 == script_pubkey.len();
+    let spend_size = if is_witness_program { 32 + 4 + 1 + 107 / 4 + 4 } else { 32 + 4 + 1 + 107 + 4 };
+
+    (output_size + spend_size) * DUST_RELAY_FEE / 1000
+}
+
+/// Split `total_reward` between `recipients`.
+///
+/// Returns each recipient's amount, in the same order; recipients whose
+/// share was dropped as dust get 0 and should not get an output.
+pub fn split_reward(recipients: &[Recipient], total_reward: u64) -> Result<Vec<u64>, PayoutError> {
+    if recipients.is_empty() {
+        return Err(PayoutError::NoRecipients);
+    }
+
+    let mut amounts = vec![0u64; recipients.len()];
+    let mut fixed = 0u64;
+    let mut weighted = Vec::new();
+    for (index, recipient) in recipients.iter().enumerate() {
+        match recipient.share {
+            PayoutShare::Weight(0) => return Err(PayoutError::ZeroWeight(index)),
+            PayoutShare::Weight(weight) => weighted.push((index, weight)),
+            PayoutShare::Amount(amount) => {
+                let threshold = dust_threshold(&recipient.address.script_pubkey);
+                if amount < threshold {
+                    return Err(PayoutError::Dust { index, amount, threshold });
+                }
+                fixed = fixed.saturating_add(amount);
+                amounts[index] = amount;
+            }
+        }
+    }
+
+    if fixed > total_reward {
+        return Err(PayoutError::FixedExceedsReward { fixed, reward: total_reward });
+    }
+    let rest = total_reward - fixed;
+    if rest == 0 {
+        return Ok(amounts);
+    }
+    if weighted.is_empty() {
+        return Err(PayoutError::Unallocated(rest));
+    }
+
+    loop {
+        let total_weight: u128 = weighted.iter().map(|&(_, weight)| weight as u128).sum();
+        let mut paid = 0;
+        for &(index, weight) in &weighted {
+            amounts[index] = (rest as u128 * weight as u128 / total_weight) as u64;
+            paid += amounts[index];
+        }
+        amounts[weighted[0].0] += rest - paid;
+
+        // Drop the smallest dust share and split again
+        let dust = weighted
+            .iter()
+            .enumerate()
+            .filter(|&(_, &(index, _))| amounts[index] < dust_threshold(&recipients[index].address.script_pubkey))
+            .min_by_key(|&(position, &(_, weight))| (weight, core::cmp::Reverse(position)));
+        let Some((position, &(index, _))) = dust else {
+            break;
+        };
+        if weighted.len() == 1 {
+            let threshold = dust_threshold(&recipients[index].address.script_pubkey);
+            return Err(PayoutError::Dust { index, amount: amounts[index], threshold });
+        }
+        amounts[index] = 0;
+        weighted.remove(position);
+    }
+
+    debug_assert_eq!(amounts.iter().sum::<u64>(), total_reward);
+    Ok(amounts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::address::validate_address;
+    use crate::network::Network;
+
+    fn p2wpkh() -> ValidatedAddress {
+        validate_address("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq", Network::Mainnet).unwrap()
+    }
+
+    fn p2pkh() -> ValidatedAddress {
+        validate_address("1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2", Network::Mainnet).unwrap()
+    }
+
+    #[test]
+    fn test_dust_threshold() {
+        assert_eq!(dust_threshold(&p2pkh().script_pubkey), 546);
+        assert_eq!(dust_threshold(&p2wpkh().script_pubkey), 294);
+        let p2tr = validate_address(
+            "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr",
+            Network::Mainnet,
+        )
+        .unwrap();
+        assert_eq!(dust_threshold(&p2tr.script_pubkey), 330);
+    }
+
+    #[test]
+    fn test_split_by_weight_and_amount() {
+        let recipients = [
+            Recipient::weight(p2wpkh(), 2),
+            Recipient::amount(p2pkh(), 10_000),
+            Recipient::weight(p2pkh(), 1),
+        ];
+
+        // 90_001 left after the fixed amount: 60_000 and 30_000, plus 1 from rounding
+        assert_eq!(split_reward(&recipients, 100_001).unwrap(), vec![60_001, 10_000, 30_000]);
+        assert_eq!(split_reward(&recipients, 10_000).unwrap(), vec![0, 10_000, 0]);
+        assert_eq!(
+            split_reward(&recipients, 9_999),
+            Err(PayoutError::FixedExceedsReward { fixed: 10_000, reward: 9_999 })
+        );
+    }
+
+    #[test]
+    fn test_split_always_adds_up() {
+        // A small xorshift generator keeps the test deterministic
+        let mut state = 0x9E37_79B9_7F4A_7C15u64;
+        let mut next = move |bound: u64| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state % bound
+        };
+
+        for _ in 0..500 {
+            let recipients: Vec<Recipient> = (0..1 + next(8))
+                .map(|i| match i % 3 {
+                    2 => Recipient::amount(p2wpkh(), 294 + next(5_000)),
+                    _ => Recipient::weight(p2wpkh(), 1 + next(1_000)),
+                })
+                .collect();
+            let reward = next(50_000_000);
+            if let Ok(amounts) = split_reward(&recipients, reward) {
+                assert_eq!(amounts.iter().sum::<u64>(), reward);
+                assert!(amounts.iter().all(|&amount| amount == 0 || amount >= 294));
+            }
+        }
+    }
+
+    #[test]
+    fn test_dust_and_errors() {
+        // The 1-weight share of 1_000 sats is dust, so the other takes it all
+        let recipients = [Recipient::weight(p2wpkh(), 1), Recipient::weight(p2wpkh(), 999)];
+        assert_eq!(split_reward(&recipients, 1_000).unwrap(), vec![0, 1_000]);
+        assert_eq!(
+            split_reward(&recipients, 100),
+            Err(PayoutError::Dust { index: 1, amount: 100, threshold: 294 })
+        );
+
+        assert_eq!(split_reward(&[], 1_000), Err(PayoutError::NoRecipients));
+        assert_eq!(split_reward(&[Recipient::weight(p2wpkh(), 0)], 1_000), Err(PayoutError::ZeroWeight(0)));
+        assert_eq!(
+            split_reward(&[Recipient::amount(p2pkh(), 500)], 1_000),
+            Err(PayoutError::Dust { index: 0, amount: 500, threshold: 546 })
+        );
+        assert_eq!(split_reward(&[Recipient::amount(p2pkh(), 600)], 1_000), Err(PayoutError::Unallocated(400)));
+    }
+}
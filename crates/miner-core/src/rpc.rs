@@ -0,0 +1,602 @@
+//! Bitcoin Core JSON-RPC client.
+//!
+//! Talks plain HTTP/1.1 to a local node, which is how blocks found while
+//! solo mining actually reach the network (`submitblock`). Block explorers
+//! only relay transactions.
+
+use std::fmt;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use serde_json::{json, Value};
+use crate::gbt::{GetBlockTemplate, TemplateError};
+
+/// Default timeout for a single RPC call.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Credentials for the node's RPC interface.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RpcAuth {
+    /// `rpcuser` / `rpcpassword` from bitcoin.conf.
+    UserPass(String, String),
+    /// Path to the `.cookie` file the node writes on startup.
+    ///
+    /// The file is re-read on every call, so a node restart does not
+    /// invalidate the client.
+    CookieFile(PathBuf),
+}
+
+impl RpcAuth {
+    /// Resolve the credentials to a `user:password` pair.
+    fn credentials(&self) -> Result<String, RpcError> {
+        match self {
+            RpcAuth::UserPass(user, password) => Ok(format!("{}:{}", user, password)),
+            RpcAuth::CookieFile(path) => {
+                let cookie = std::fs::read_to_string(path)
+                    .map_err(|e| RpcError::Io(format!("{}: {}", path.display(), e)))?;
+                Ok(cookie.trim().to_string())
+            }
+        }
+    }
+}
+
+/// Reasons bitcoind gives for not accepting a block from `submitblock`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubmitBlockError {
+    /// The node already has this block.
+    Duplicate,
+    /// The node already has this block and knows it is invalid.
+    DuplicateInvalid,
+    /// The block is valid but was not connected to the best chain.
+    Inconclusive,
+    /// The header hash does not meet its target (`high-hash`).
+    HighHash,
+    /// The bits do not match the required difficulty (`bad-diffbits`).
+    BadDiffBits,
+    /// The previous block is unknown or invalid.
+    BadPrevBlock,
+    /// The merkle root does not match the transactions (`bad-txnmrklroot`).
+    BadMerkleRoot,
+    /// The coinbase pays more than subsidy plus fees (`bad-cb-amount`).
+    BadCoinbaseAmount,
+    /// The coinbase does not start with the block height (`bad-cb-height`).
+    BadCoinbaseHeight,
+    /// The witness commitment is missing or wrong.
+    BadWitnessCommitment,
+    /// The timestamp is not after the median time past (`time-too-old`).
+    TimeTooOld,
+    /// The timestamp is too far in the future (`time-too-new`).
+    TimeTooNew,
+    /// The block version is obsolete (`bad-version(...)`).
+    BadVersion,
+    /// Any other reject reason, verbatim.
+    Other(String),
+}
+
+impl SubmitBlockError {
+    /// Interpret a reject reason string returned by `submitblock`.
+    pub fn from_reason(reason: &str) -> Self {
+        match reason {
+            "duplicate" => SubmitBlockError::Duplicate,
+            "duplicate-invalid" => SubmitBlockError::DuplicateInvalid,
+            "inconclusive" | "duplicate-inconclusive" => SubmitBlockError::Inconclusive,
+            "high-hash" => SubmitBlockError::HighHash,
+            "bad-diffbits" => SubmitBlockError::BadDiffBits,
+            "bad-prevblk" | "prev-blk-not-found" => SubmitBlockError::BadPrevBlock,
+            "bad-txnmrklroot" => SubmitBlockError::BadMerkleRoot,
+            "bad-cb-amount" => SubmitBlockError::BadCoinbaseAmount,
+            "bad-cb-height" => SubmitBlockError::BadCoinbaseHeight,
+            "bad-witness-merkle-match" | "bad-witness-nonce-size" | "unexpected-witness" => {
+                SubmitBlockError::BadWitnessCommitment
+            }
+            "time-too-old" => SubmitBlockError::TimeTooOld,
+            "time-too-new" => SubmitBlockError::TimeTooNew,
+            r if r.starts_with("bad-version") => SubmitBlockError::BadVersion,
+            other => SubmitBlockError::Other(other.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for SubmitBlockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SubmitBlockError::Duplicate => write!(f, "Block already known"),
+            SubmitBlockError::DuplicateInvalid => write!(f, "Block already known to be invalid"),
+            SubmitBlockError::Inconclusive => write!(f, "Block valid but not on the best chain"),
+            SubmitBlockError::HighHash => write!(f, "Block hash does not meet the target"),
+            SubmitBlockError::BadDiffBits => write!(f, "Incorrect difficulty bits"),
+            SubmitBlockError::BadPrevBlock => write!(f, "Unknown or invalid previous block"),
+            SubmitBlockError::BadMerkleRoot => write!(f, "Merkle root mismatch"),
+            SubmitBlockError::BadCoinbaseAmount => write!(f, "Coinbase pays too much"),
+            SubmitBlockError::BadCoinbaseHeight => write!(f, "Coinbase height mismatch"),
+            SubmitBlockError::BadWitnessCommitment => write!(f, "Invalid witness commitment"),
+            SubmitBlockError::TimeTooOld => write!(f, "Block timestamp too old"),
+            SubmitBlockError::TimeTooNew => write!(f, "Block timestamp too far in the future"),
+            SubmitBlockError::BadVersion => write!(f, "Obsolete block version"),
+            SubmitBlockError::Other(reason) => write!(f, "Block rejected: {}", reason),
+        }
+    }
+}
+
+/// Errors that can occur when calling the node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RpcError {
+    /// Connection or cookie file error.
+    Io(String),
+    /// The node rejected the credentials (HTTP 401).
+    Unauthorized,
+    /// The node answered with an HTTP error and no JSON-RPC error body.
+    Http(u16),
+    /// The response could not be understood.
+    InvalidResponse(String),
+    /// The node returned a JSON-RPC error.
+    Rpc { code: i64, message: String },
+    /// `submitblock` refused the block.
+    Rejected(SubmitBlockError),
+    /// The `getblocktemplate` result could not be parsed.
+    Template(TemplateError),
+}
+
+impl fmt::Display for RpcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RpcError::Io(e) => write!(f, "RPC connection error: {}", e),
+            RpcError::Unauthorized => write!(f, "RPC credentials rejected"),
+            RpcError::Http(status) => write!(f, "RPC HTTP error {}", status),
+            RpcError::InvalidResponse(e) => write!(f, "Invalid RPC response: {}", e),
+            RpcError::Rpc { code, message } => write!(f, "RPC error {}: {}", code, message),
+            RpcError::Rejected(reason) => write!(f, "{}", reason),
+            RpcError::Template(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<std::io::Error> for RpcError {
+    fn from(e: std::io::Error) -> Self {
+        RpcError::Io(e.to_string())
+    }
+}
+
+impl From<TemplateError> for RpcError {
+    fn from(e: TemplateError) -> Self {
+        RpcError::Template(e)
+    }
+}
+
+/// Summary of `getblockchaininfo`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockchainInfo {
+    /// Chain name as reported by the node ("main", "test", "testnet4", "signet", "regtest").
+    pub chain: String,
+    /// Height of the best validated block.
+    pub blocks: u32,
+    /// Height of the best known header.
+    pub headers: u32,
+    /// Hash of the best block (internal byte order).
+    pub best_block_hash: [u8; 32],
+    /// Current difficulty.
+    pub difficulty: f64,
+    /// Whether the node is still in initial block download.
+    pub initial_block_download: bool,
+}
+
+/// A blocking JSON-RPC client for Bitcoin Core.
+#[derive(Debug)]
+pub struct RpcClient {
+    host: String,
+    path: String,
+    auth: RpcAuth,
+    timeout: Duration,
+    next_id: AtomicU64,
+}
+
+impl RpcClient {
+    /// Create a client for `url`, e.g. `http://127.0.0.1:8332` or `127.0.0.1:8332/wallet/x`.
+    pub fn new(url: &str, auth: RpcAuth) -> Self {
+        let url = url.strip_prefix("http://").unwrap_or(url);
+        let (host, path) = match url.find('/') {
+            Some(i) => (&url[..i], &url[i..]),
+            None => (url, "/"),
+        };
+
+        RpcClient {
+            host: host.to_string(),
+            path: path.to_string(),
+            auth,
+            timeout: DEFAULT_TIMEOUT,
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Set the timeout for connecting, sending and receiving.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Call `getblockchaininfo`.
+    pub fn get_blockchain_info(&self) -> Result<BlockchainInfo, RpcError> {
+        let info = self.call("getblockchaininfo", json!([]))?;
+        let field = |name: &str| {
+            info.get(name)
+                .ok_or_else(|| RpcError::InvalidResponse(format!("missing '{}'", name)))
+        };
+        let height = |name: &str| {
+            field(name)?
+                .as_u64()
+                .map(|h| h as u32)
+                .ok_or_else(|| RpcError::InvalidResponse(format!("invalid '{}'", name)))
+        };
+
+        Ok(BlockchainInfo {
+            chain: field("chain")?.as_str().unwrap_or_default().to_string(),
+            blocks: height("blocks")?,
+            headers: height("headers")?,
+            best_block_hash: parse_hash(field("bestblockhash")?)?,
+            difficulty: field("difficulty")?.as_f64().unwrap_or(0.0),
+            initial_block_download: field("initialblockdownload")?.as_bool().unwrap_or(false),
+        })
+    }
+
+    /// Call `getblocktemplate` with the segwit (and signet) rules.
+    pub fn get_block_template(&self) -> Result<GetBlockTemplate, RpcError> {
+        let template = self.call("getblocktemplate", json!([{ "rules": ["segwit", "signet"] }]))?;
+        Ok(GetBlockTemplate::from_value(&template)?)
+    }
+
+    /// Call `submitblock` with a serialized block.
+    ///
+    /// Succeeds only if the node accepted the block; any reject reason is
+    /// returned as [`RpcError::Rejected`].
+    pub fn submit_block(&self, block_hex: &str) -> Result<(), RpcError> {
+        match self.call("submitblock", json!([block_hex]))? {
+            Value::Null => Ok(()),
+            Value::String(reason) => Err(RpcError::Rejected(SubmitBlockError::from_reason(&reason))),
+            other => Err(RpcError::InvalidResponse(other.to_string())),
+        }
+    }
+
+    /// Call `getbestblockhash`, returning the hash in internal byte order.
+    pub fn get_best_block_hash(&self) -> Result<[u8; 32], RpcError> {
+        parse_hash(&self.call("getbestblockhash", json!([]))?)
+    }
+
+    /// Perform a raw JSON-RPC call and return its `result`.
+    pub fn call(&self, method: &str, params: Value) -> Result<Value, RpcError> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let body = json!({ "jsonrpc": "1.0", "id": id, "method": method, "params": params }).to_string();
+        let credentials = base64_encode(self.auth.credentials()?.as_bytes());
+
+        let request = format!(
+            "POST {} HTTP/1.1\r\nHost: {}\r\nAuthorization: Basic {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.path, self.host, credentials, body.len(), body
+        );
+
+        let mut stream = self.connect()?;
+        stream.set_read_timeout(Some(self.timeout))?;
+        stream.set_write_timeout(Some(self.timeout))?;
+        stream.write_all(request.as_bytes())?;
+
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response)?;
+        let (status, body) = parse_http_response(&response)?;
+
+        if status == 401 {
+            return Err(RpcError::Unauthorized);
+        }
+
+        // bitcoind reports RPC errors with HTTP 500/404 and a JSON body
+        let reply: Value = match serde_json::from_slice(&body) {
+            Ok(reply) => reply,
+            Err(_) if status != 200 => return Err(RpcError::Http(status)),
+            Err(e) => return Err(RpcError::InvalidResponse(e.to_string())),
+        };
+
+        if let Some(error) = reply.get("error").filter(|e| !e.is_null()) {
+            return Err(RpcError::Rpc {
+                code: error.get("code").and_then(Value::as_i64).unwrap_or(0),
+                message: error.get("message").and_then(Value::as_str).unwrap_or_default().to_string(),
+            });
+        }
+        if status != 200 {
+            return Err(RpcError::Http(status));
+        }
+
+        Ok(reply.get("result").cloned().unwrap_or(Value::Null))
+    }
+
+    /// Connect to the node, trying each address `host` resolves to.
+    fn connect(&self) -> Result<TcpStream, RpcError> {
+        let mut last_error = None;
+        for addr in self.host.to_socket_addrs()? {
+            match TcpStream::connect_timeout(&addr, self.timeout) {
+                Ok(stream) => return Ok(stream),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(match last_error {
+            Some(e) => e.into(),
+            None => RpcError::Io(format!("{} did not resolve to any address", self.host)),
+        })
+    }
+}
+
+/// Split an HTTP response into its status code and (de-chunked) body.
+fn parse_http_response(response: &[u8]) -> Result<(u16, Vec<u8>), RpcError> {
+    let split = response
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .ok_or_else(|| RpcError::InvalidResponse("truncated HTTP response".into()))?;
+    let head = std::str::from_utf8(&response[..split])
+        .map_err(|_| RpcError::InvalidResponse("non-UTF-8 HTTP headers".into()))?;
+    let body = &response[split + 4..];
+
+    let mut lines = head.split("\r\n");
+    let status = lines
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or_else(|| RpcError::InvalidResponse("bad HTTP status line".into()))?;
+
+    let chunked = lines.any(|line| {
+        let line = line.to_ascii_lowercase();
+        line.starts_with("transfer-encoding:") && line.contains("chunked")
+    });
+
+    if chunked {
+        Ok((status, decode_chunked(body)?))
+    } else {
+        Ok((status, body.to_vec()))
+    }
+}
+
+/// Decode an HTTP/1.1 chunked transfer-encoded body.
+fn decode_chunked(mut body: &[u8]) -> Result<Vec<u8>, RpcError> {
+    let invalid = || RpcError::InvalidResponse("bad chunked encoding".into());
+    let mut out = Vec::new();
+
+    loop {
+        let line_end = body.windows(2).position(|w| w == b"\r\n").ok_or_else(invalid)?;
+        let size_str = std::str::from_utf8(&body[..line_end]).map_err(|_| invalid())?;
+        let size_str = size_str.split(';').next().unwrap_or_default().trim();
+        let size = usize::from_str_radix(size_str, 16).map_err(|_| invalid())?;
+        body = &body[line_end + 2..];
+
+        if size == 0 {
+            return Ok(out);
+        }
+        if body.len() < size + 2 {
+            return Err(invalid());
+        }
+        out.extend_from_slice(&body[..size]);
+        body = &body[size + 2..];
+    }
+}
+
+/// Parse a display-order hash string into internal byte order.
+fn parse_hash(value: &Value) -> Result<[u8; 32], RpcError> {
+    let invalid = || RpcError::InvalidResponse(format!("invalid block hash {}", value));
+    let bytes = hex::decode(value.as_str().ok_or_else(invalid)?).map_err(|_| invalid())?;
+    let mut hash: [u8; 32] = bytes.try_into().map_err(|_| invalid())?;
+    hash.reverse();
+    Ok(hash)
+}
+
+/// Standard base64 encoding (with padding) for the Basic auth header.
+fn base64_encode(input: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(input.len().div_ceil(3) * 4);
+
+    for chunk in input.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+
+        out.push(ALPHABET[(n >> 18) as usize & 63] as char);
+        out.push(ALPHABET[(n >> 12) as usize & 63] as char);
+        out.push(if chunk.len() > 1 { ALPHABET[(n >> 6) as usize & 63] as char } else { '=' });
+        out.push(if chunk.len() > 2 { ALPHABET[n as usize & 63] as char } else { '=' });
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+
+    const BEST_HASH: &str = "000000000000000000019e7a0e6e1a1c7d9bfb79d1bf9c0bd0b3c3bdb8e1f0a2";
+
+    /// Stub bitcoind: answers `connections` requests, one per connection.
+    ///
+    /// Requests without the expected Basic credentials get a bare 401.
+    fn stub_node(credentials: &'static str, connections: usize) -> (String, JoinHandle<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+
+        let handle = std::thread::spawn(move || {
+            for _ in 0..connections {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+                let mut authorized = false;
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let line = line.trim_end();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some(auth) = line.strip_prefix("Authorization: Basic ") {
+                        authorized = auth == base64_encode(credentials.as_bytes());
+                    }
+                    if let Some(len) = line.strip_prefix("Content-Length: ") {
+                        content_length = len.parse().unwrap();
+                    }
+                }
+                let mut body = vec![0u8; content_length];
+                reader.read_exact(&mut body).unwrap();
+
+                let mut stream = stream;
+                if !authorized {
+                    stream.write_all(b"HTTP/1.1 401 Unauthorized\r\nContent-Length: 0\r\n\r\n").unwrap();
+                    continue;
+                }
+
+                let request: Value = serde_json::from_slice(&body).unwrap();
+                let id = request["id"].clone();
+                let (status, reply) = match request["method"].as_str().unwrap() {
+                    "getbestblockhash" => (200, json!({ "result": BEST_HASH, "error": null, "id": id })),
+                    "getblockchaininfo" => (200, json!({
+                        "result": {
+                            "chain": "main",
+                            "blocks": 875000,
+                            "headers": 875001,
+                            "bestblockhash": BEST_HASH,
+                            "difficulty": 1.0e14,
+                            "initialblockdownload": false,
+                        },
+                        "error": null,
+                        "id": id,
+                    })),
+                    "getblocktemplate" => {
+                        assert_eq!(request["params"][0]["rules"][0], "segwit");
+                        (200, serde_json::from_str(crate::gbt::tests::TEMPLATE_JSON).unwrap())
+                    }
+                    "submitblock" => match request["params"][0].as_str().unwrap() {
+                        "00" => (500, json!({
+                            "result": null,
+                            "error": { "code": -22, "message": "Block decode failed" },
+                            "id": id,
+                        })),
+                        "01" => (200, json!({ "result": "high-hash", "error": null, "id": id })),
+                        _ => (200, json!({ "result": null, "error": null, "id": id })),
+                    },
+                    _ => (404, json!({
+                        "result": null,
+                        "error": { "code": -32601, "message": "Method not found" },
+                        "id": id,
+                    })),
+                };
+
+                // Answer with chunked encoding to exercise the decoder
+                let reply = reply.to_string();
+                let (first, second) = reply.split_at(reply.len() / 2);
+                let response = format!(
+                    "HTTP/1.1 {} X\r\nContent-Type: application/json\r\nTransfer-Encoding: chunked\r\n\r\n{:x}\r\n{}\r\n{:x}\r\n{}\r\n0\r\n\r\n",
+                    status, first.len(), first, second.len(), second
+                );
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        (addr, handle)
+    }
+
+    #[test]
+    fn test_base64_encode() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foo"), "Zm9v");
+        assert_eq!(base64_encode(b"user:pass"), "dXNlcjpwYXNz");
+    }
+
+    #[test]
+    fn test_submit_block_reject_reasons() {
+        assert_eq!(SubmitBlockError::from_reason("high-hash"), SubmitBlockError::HighHash);
+        assert_eq!(SubmitBlockError::from_reason("bad-txnmrklroot"), SubmitBlockError::BadMerkleRoot);
+        assert_eq!(
+            SubmitBlockError::from_reason("bad-version(0x00000001)"),
+            SubmitBlockError::BadVersion
+        );
+        assert_eq!(
+            SubmitBlockError::from_reason("bad-witness-merkle-match"),
+            SubmitBlockError::BadWitnessCommitment
+        );
+        assert_eq!(
+            SubmitBlockError::from_reason("bad-blk-sigops"),
+            SubmitBlockError::Other("bad-blk-sigops".into())
+        );
+    }
+
+    #[test]
+    fn test_rpc_calls_against_stub_node() {
+        let (addr, node) = stub_node("user:pass", 7);
+        let client = RpcClient::new(&format!("http://{}", addr), RpcAuth::UserPass("user".into(), "pass".into()));
+
+        let mut best = hex::decode(BEST_HASH).unwrap();
+        best.reverse();
+        assert_eq!(client.get_best_block_hash().unwrap()[..], best[..]);
+
+        let info = client.get_blockchain_info().unwrap();
+        assert_eq!(info.chain, "main");
+        assert_eq!(info.blocks, 875000);
+        assert_eq!(info.best_block_hash[..], best[..]);
+        assert!(!info.initial_block_download);
+
+        let template = client.get_block_template().unwrap();
+        assert_eq!(template.height, 101);
+        assert_eq!(template.transactions.len(), 2);
+
+        assert_eq!(client.submit_block("ff"), Ok(()));
+        assert_eq!(client.submit_block("01"), Err(RpcError::Rejected(SubmitBlockError::HighHash)));
+        assert_eq!(
+            client.submit_block("00"),
+            Err(RpcError::Rpc { code: -22, message: "Block decode failed".into() })
+        );
+        assert!(matches!(
+            client.call("getmininginfo2", json!([])),
+            Err(RpcError::Rpc { code: -32601, .. })
+        ));
+
+        node.join().unwrap();
+    }
+
+    #[test]
+    fn test_cookie_auth_and_unauthorized() {
+        let (addr, node) = stub_node("__cookie__:c0ffee", 2);
+
+        let cookie = std::env::temp_dir().join(format!("miner-core-rpc-{}.cookie", std::process::id()));
+        std::fs::write(&cookie, "__cookie__:c0ffee\n").unwrap();
+        let client = RpcClient::new(&addr, RpcAuth::CookieFile(cookie.clone()));
+        assert!(client.get_best_block_hash().is_ok());
+        std::fs::remove_file(&cookie).unwrap();
+
+        let client = RpcClient::new(&addr, RpcAuth::UserPass("user".into(), "wrong".into()));
+        assert_eq!(client.get_best_block_hash(), Err(RpcError::Unauthorized));
+
+        node.join().unwrap();
+
+        let missing = RpcClient::new(&addr, RpcAuth::CookieFile(cookie));
+        assert!(matches!(missing.get_best_block_hash(), Err(RpcError::Io(_))));
+    }
+
+    #[test]
+    fn test_connect_timeout() {
+        // A listener that never accepts: once its backlog is full, new
+        // connections get no answer and only the timeout ends them
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut backlog = Vec::new();
+        let error = loop {
+            match TcpStream::connect_timeout(&addr, Duration::from_millis(100)) {
+                Ok(stream) => backlog.push(stream),
+                Err(e) => break e,
+            }
+        };
+        assert_eq!(error.kind(), std::io::ErrorKind::TimedOut, "after {} connections", backlog.len());
+
+        let client = RpcClient::new(&addr.to_string(), RpcAuth::UserPass("user".into(), "pass".into()))
+            .with_timeout(Duration::from_millis(200));
+        let start = std::time::Instant::now();
+        assert!(matches!(client.get_best_block_hash(), Err(RpcError::Io(_))));
+        assert!(start.elapsed() >= Duration::from_millis(200));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,265 @@
+//! Header search across nonces and BIP320 version bits.
+//!
+//! A header has 2^32 nonces, which a fast miner gets through in seconds.
+//! BIP320 reserves 16 version bits for miners, so rolling them multiplies
+//! the search space without rebuilding the coinbase or merkle root.
+
+use crate::hash::{mine_batch_shares_with, FoundShare, MiningResult};
+use crate::simd::Backend;
+
+/// The `index`-th version reachable by flipping bits of `mask` in `base`.
+///
+/// The bits of `index` are spread over the set bits of `mask`, lowest first,
+/// and XORed into `base`. Index 0 is `base` itself; index bits beyond the
+/// mask's bit count are ignored.
+pub fn rolled_version(base: i32, mask: u32, index: u32) -> i32 {
+    let mut bits = 0u32;
+    let mut index = index;
+
+    for bit in (0..32).map(|i| 1u32 << i).filter(|bit| mask & bit != 0) {
+        if index & 1 == 1 {
+            bits |= bit;
+        }
+        index >>= 1;
+    }
+
+    base ^ bits as i32
+}
+
+/// Walks a header's nonce range, then its rolled versions.
+///
+/// Every version searches the same nonce range; once the range is used up
+/// the next version is taken, until all `2^popcount(mask)` versions are
+/// done. Results report the version they were found with, so the header
+/// can be rebuilt for submission.
+#[derive(Debug, Clone)]
+pub struct HeaderSearch {
+    header: [u8; 76],
+    base_version: i32,
+    version_mask: u32,
+    backend: Backend,
+    nonce_start: u32,
+    nonce_end: u32,
+    /// Index of the version being searched.
+    roll: u64,
+    /// Next nonce to hash.
+    nonce: u32,
+}
+
+impl HeaderSearch {
+    /// Search `header_without_nonce` as is, over nonces `0..u32::MAX`.
+    ///
+    /// Like `mine_batch`, the search takes half-open ranges, so nonce
+    /// `u32::MAX` itself is never tried.
+    pub fn new(header_without_nonce: &[u8; 76]) -> Self {
+        let base_version = i32::from_le_bytes([
+            header_without_nonce[0],
+            header_without_nonce[1],
+            header_without_nonce[2],
+            header_without_nonce[3],
+        ]);
+
+        HeaderSearch {
+            header: *header_without_nonce,
+            base_version,
+            version_mask: 0,
+            backend: Backend::detect(),
+            nonce_start: 0,
+            nonce_end: u32::MAX,
+            roll: 0,
+            nonce: 0,
+        }
+    }
+
+    /// Roll the version bits in `mask` once the nonce range is exhausted.
+    pub fn with_version_mask(mut self, mask: u32) -> Self {
+        self.version_mask = mask;
+        self
+    }
+
+    /// Search `count` nonces from `start` for each version.
+    pub fn with_nonce_range(mut self, start: u32, count: u32) -> Self {
+        self.nonce_start = start;
+        self.nonce_end = start.saturating_add(count);
+        self.nonce = start;
+        self
+    }
+
+    /// Hash with a specific SHA256 backend.
+    pub fn with_backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Number of versions the search goes through.
+    pub fn version_count(&self) -> u64 {
+        1u64 << self.version_mask.count_ones()
+    }
+
+    /// The version being searched (the base version once exhausted).
+    pub fn version(&self) -> i32 {
+        rolled_version(self.base_version, self.version_mask, self.roll as u32)
+    }
+
+    /// The next nonce to be hashed.
+    pub fn nonce(&self) -> u32 {
+        self.nonce
+    }
+
+    /// Whether every version and nonce has been searched.
+    pub fn is_exhausted(&self) -> bool {
+        self.roll >= self.version_count()
+    }
+
+    /// Hash up to `batch_size` nonces of the current version.
+    ///
+    /// A batch never spans two versions. It stops early when a block is
+    /// found, and the search resumes after the winning nonce.
+    pub fn next_batch(&mut self, block_target: &[u8; 32], share_target: &[u8; 32], batch_size: u32) -> MiningResult {
+        self.next_batch_shares(block_target, share_target, batch_size, usize::MAX, |_| {})
+    }
+
+    /// Like [`HeaderSearch::next_batch`], passing every share to `on_share`
+    /// (see `mine_batch_shares`).
+    ///
+    /// The batch also stops after `max_shares` shares (0 for no limit), and
+    /// the search resumes after the last one.
+    pub fn next_batch_shares(
+        &mut self,
+        block_target: &[u8; 32],
+        share_target: &[u8; 32],
+        batch_size: u32,
+        max_shares: usize,
+        on_share: impl FnMut(FoundShare),
+    ) -> MiningResult {
+        let version = self.version();
+        if self.is_exhausted() {
+            return MiningResult::not_found(version, 0);
+        }
+
+        let mut header = self.header;
+        header[0..4].copy_from_slice(&version.to_le_bytes());
+
+        let count = batch_size.min(self.nonce_end - self.nonce);
+        let result = mine_batch_shares_with(
+            self.backend,
+            &header,
+            block_target,
+            share_target,
+            self.nonce,
+            count,
+            max_shares,
+            on_share,
+        );
+
+        self.nonce += result.hashes_computed as u32;
+        if self.nonce == self.nonce_end {
+            self.roll += 1;
+            self.nonce = self.nonce_start;
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::difficulty::{difficulty_to_target, hash_meets_target};
+    use crate::hash::{double_sha256, reverse_bytes};
+    use crate::network::{BLOCK_VERSION, VERSION_ROLLING_MASK};
+
+    #[test]
+    fn test_rolled_version() {
+        assert_eq!(rolled_version(BLOCK_VERSION, VERSION_ROLLING_MASK, 0), BLOCK_VERSION);
+        assert_eq!(rolled_version(BLOCK_VERSION, VERSION_ROLLING_MASK, 1), 0x2000_2000);
+        assert_eq!(rolled_version(BLOCK_VERSION, VERSION_ROLLING_MASK, 0xFFFF), 0x3FFF_E000);
+        // Bits beyond the mask wrap back to the base version
+        assert_eq!(rolled_version(BLOCK_VERSION, VERSION_ROLLING_MASK, 0x1_0000), BLOCK_VERSION);
+
+        // Non-contiguous mask: index bits fill mask bits lowest first
+        assert_eq!(rolled_version(0, 0b1010_0100, 0b101), 0b1000_0100);
+        // Bits already set in the base are flipped, not forced
+        assert_eq!(rolled_version(0x2000_2000, VERSION_ROLLING_MASK, 1), BLOCK_VERSION);
+    }
+
+    #[test]
+    fn test_search_rolls_version_after_nonce_range() {
+        let mut header = [0x11u8; 76];
+        header[0..4].copy_from_slice(&BLOCK_VERSION.to_le_bytes());
+        let mut search = HeaderSearch::new(&header)
+            .with_version_mask(0x0000_6000)
+            .with_nonce_range(100, 10);
+        assert_eq!(search.version_count(), 4);
+
+        let target = [0u8; 32];
+        let mut versions = alloc::vec::Vec::new();
+        while !search.is_exhausted() {
+            let result = search.next_batch(&target, &[0u8; 32], 4);
+            assert!(result.hashes_computed <= 4);
+            if versions.last() != Some(&result.version) {
+                versions.push(result.version);
+            }
+        }
+        assert_eq!(versions, [0x2000_0000, 0x2000_2000, 0x2000_4000, 0x2000_6000]);
+        assert_eq!(search.next_batch(&target, &[0u8; 32], 4).hashes_computed, 0);
+    }
+
+    #[test]
+    fn test_search_reports_rolled_version_of_block() {
+        // Two nonces per version against a target about one in eight hashes
+        // meets, so the search usually rolls a few versions first
+        let header = [0x42u8; 76];
+        let mut target = [0xFF; 32];
+        target[0] = 0x1F;
+        let mut search = HeaderSearch::new(&header)
+            .with_version_mask(VERSION_ROLLING_MASK)
+            .with_nonce_range(0, 2);
+
+        let result = loop {
+            let result = search.next_batch(&target, &[0u8; 32], 2);
+            if result.block_found {
+                break result;
+            }
+        };
+
+        // Rebuild the full header from the result
+        let mut full = [0u8; 80];
+        full[..76].copy_from_slice(&header);
+        full[0..4].copy_from_slice(&result.version.to_le_bytes());
+        full[76..].copy_from_slice(&result.nonce.unwrap().to_le_bytes());
+        let hash = double_sha256(&full);
+        assert_eq!(Some(hash), result.hash);
+        assert!(hash_meets_target(&reverse_bytes(&hash), &target));
+        assert_eq!(result.version & !(VERSION_ROLLING_MASK as i32), 0x4242_4242 & !(VERSION_ROLLING_MASK as i32));
+
+        // The search picks up after the winning nonce
+        let nonce = result.nonce.unwrap();
+        if nonce == 0 {
+            assert_eq!(search.nonce(), 1);
+            assert_eq!(search.version(), result.version);
+        } else {
+            assert_eq!(search.nonce(), 0);
+            assert_ne!(search.version(), result.version);
+        }
+    }
+
+    #[test]
+    fn test_search_resumes_after_share_cap() {
+        let header = [0x42u8; 76];
+        let share_target = difficulty_to_target(1.0 / (1u64 << 28) as f64);
+        let mut all = alloc::vec::Vec::new();
+        HeaderSearch::new(&header)
+            .with_nonce_range(0, 400)
+            .next_batch_shares(&[0u8; 32], &share_target, 400, usize::MAX, |share| all.push(share.nonce));
+
+        // Two shares at a time, picking up where the last batch stopped
+        let mut search = HeaderSearch::new(&header).with_nonce_range(0, 400);
+        let mut capped = alloc::vec::Vec::new();
+        while !search.is_exhausted() {
+            search.next_batch_shares(&[0u8; 32], &share_target, 400, 2, |share| capped.push(share.nonce));
+        }
+        assert!(all.len() > 2);
+        assert_eq!(capped, all);
+    }
+}
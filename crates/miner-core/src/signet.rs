@@ -0,0 +1,393 @@
+//! Signet block solutions (BIP325).
+//!
+//! On signet a block needs a solution to the network's challenge script on
+//! top of proof of work. The solution (a scriptSig and witness stack) rides
+//! in the coinbase's witness commitment output, as an extra push starting
+//! with [`SIGNET_HEADER`]. It signs a pair of virtual transactions that
+//! commit to the block's version, previous hash, time and a merkle root
+//! computed without the solution, so the nonce can still be ground after
+//! signing but the version, time and transactions cannot change.
+//!
+//! The default signet's challenge is a 1-of-2 multisig held by its
+//! operators. Custom signets often use `OP_TRUE`, which an empty solution
+//! satisfies.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use crate::coinbase::encode_varint;
+use crate::decode::{Block, DecodeError, Reader, Transaction, TxIn, TxOut};
+use crate::hash::double_sha256;
+use crate::merkle::compute_merkle_root;
+
+/// Marks the witness commitment push that carries the signet solution.
+pub const SIGNET_HEADER: [u8; 4] = [0xec, 0xc7, 0xda, 0xa2];
+
+/// The default signet's challenge: a 1-of-2 multisig.
+pub const DEFAULT_SIGNET_CHALLENGE: [u8; 71] = [
+    0x51, 0x21, 0x03, 0xad, 0x5e, 0x0e, 0xda, 0xd1, 0x8c, 0xb1, 0xf0, 0xfc, 0x0d, 0x28, 0xa3, 0xd4,
+    0xf1, 0xf3, 0xe4, 0x45, 0x64, 0x03, 0x37, 0x48, 0x9a, 0xbb, 0x10, 0x40, 0x4f, 0x2d, 0x1e, 0x08,
+    0x6b, 0xe4, 0x30, 0x21, 0x03, 0x59, 0xef, 0x50, 0x21, 0x96, 0x4f, 0xe2, 0x2d, 0x6f, 0x8e, 0x05,
+    0xb2, 0x46, 0x3c, 0x95, 0x40, 0xce, 0x96, 0x88, 0x3f, 0xe3, 0xb2, 0x78, 0x76, 0x0f, 0x04, 0x8f,
+    0x51, 0x89, 0xf2, 0xe6, 0xc4, 0x52, 0xae,
+];
+
+/// Errors that can occur while extracting a block's signet transactions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignetError {
+    /// No signet challenge is known for the block.
+    MissingChallenge,
+    /// The block does not decode.
+    MalformedBlock(DecodeError),
+    /// The coinbase has no witness commitment to carry a solution.
+    MissingWitnessCommitment,
+    /// The solution in the witness commitment does not decode.
+    InvalidSolution(DecodeError),
+}
+
+impl fmt::Display for SignetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignetError::MissingChallenge => write!(f, "No signet challenge"),
+            SignetError::MalformedBlock(e) => write!(f, "Block does not decode: {}", e),
+            SignetError::MissingWitnessCommitment => write!(f, "Coinbase has no witness commitment"),
+            SignetError::InvalidSolution(e) => write!(f, "Invalid signet solution: {}", e),
+        }
+    }
+}
+
+/// The network magic of the signet defined by `challenge`.
+///
+/// The first four bytes of the double SHA256 of the serialized challenge.
+pub fn signet_magic(challenge: &[u8]) -> [u8; 4] {
+    let mut data = Vec::with_capacity(challenge.len() + 3);
+    encode_varint(challenge.len() as u64, &mut data);
+    data.extend_from_slice(challenge);
+    let hash = double_sha256(&data);
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+/// A solution to a signet challenge.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SignetSolution {
+    /// scriptSig of the spending transaction.
+    pub script_sig: Vec<u8>,
+    /// Witness stack of the spending transaction.
+    pub witness: Vec<Vec<u8>>,
+}
+
+impl SignetSolution {
+    /// Serialize as it appears after the signet header.
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(2 + self.script_sig.len());
+        encode_varint(self.script_sig.len() as u64, &mut out);
+        out.extend_from_slice(&self.script_sig);
+        encode_varint(self.witness.len() as u64, &mut out);
+        for item in &self.witness {
+            encode_varint(item.len() as u64, &mut out);
+            out.extend_from_slice(item);
+        }
+        out
+    }
+
+    /// Decode a serialized solution, rejecting trailing bytes.
+    pub fn parse(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader::new(bytes);
+        let script_sig = reader.read_var_bytes()?;
+        let count = reader.read_varint()?;
+        let mut witness = Vec::with_capacity(reader.capacity_hint(count));
+        for _ in 0..count {
+            witness.push(reader.read_var_bytes()?);
+        }
+        reader.finish()?;
+        Ok(SignetSolution { script_sig, witness })
+    }
+}
+
+/// Append `solution` to a witness commitment script.
+pub fn append_solution(commitment_script: &[u8], solution: &SignetSolution) -> Vec<u8> {
+    let mut data = SIGNET_HEADER.to_vec();
+    data.extend_from_slice(&solution.serialize());
+
+    let mut script = commitment_script.to_vec();
+    push_data(&data, &mut script);
+    script
+}
+
+/// The virtual transactions a signet block's solution signs.
+///
+/// `to_sign` spends output 0 of `to_spend`, whose scriptPubKey is the
+/// challenge, with the block's solution; the block is valid when that
+/// spend is. Signers fill in `to_sign`'s scriptSig and witness, which go
+/// back into the block as a [`SignetSolution`].
+#[derive(Debug, Clone)]
+pub struct SignetTxs {
+    /// Commits to the block and pays to the challenge.
+    pub to_spend: Transaction,
+    /// Spends `to_spend` with the block's current solution.
+    pub to_sign: Transaction,
+}
+
+impl SignetTxs {
+    /// Build the signet transactions of `block` for `challenge`.
+    ///
+    /// A commitment without a solution push counts as an empty solution.
+    pub fn new(block: &Block, challenge: &[u8]) -> Result<Self, SignetError> {
+        let (coinbase, solution) = split_solution(block)?;
+
+        // The signed merkle root uses the coinbase without the solution
+        let mut txids: Vec<[u8; 32]> = block.transactions.iter().map(|tx| tx.txid()).collect();
+        txids[0] = coinbase.txid();
+        let signet_merkle_root = compute_merkle_root(&txids);
+
+        let mut block_data = Vec::with_capacity(72);
+        block_data.extend_from_slice(&block.header.version.to_le_bytes());
+        block_data.extend_from_slice(&block.header.prev_block_hash);
+        block_data.extend_from_slice(&signet_merkle_root);
+        block_data.extend_from_slice(&block.header.timestamp.to_le_bytes());
+
+        // OP_0 <block data>
+        let mut script_sig = vec![0x00];
+        push_data(&block_data, &mut script_sig);
+
+        let to_spend = Transaction {
+            version: 0,
+            inputs: vec![TxIn {
+                prev_txid: [0u8; 32],
+                prev_vout: u32::MAX,
+                script_sig,
+                sequence: 0,
+                witness: Vec::new(),
+            }],
+            outputs: vec![TxOut { value: 0, script_pubkey: challenge.to_vec() }],
+            lock_time: 0,
+        };
+
+        let to_sign = Transaction {
+            version: 0,
+            inputs: vec![TxIn {
+                prev_txid: to_spend.txid(),
+                prev_vout: 0,
+                script_sig: solution.script_sig,
+                sequence: 0,
+                witness: solution.witness,
+            }],
+            // OP_RETURN
+            outputs: vec![TxOut { value: 0, script_pubkey: vec![0x6a] }],
+            lock_time: 0,
+        };
+
+        Ok(SignetTxs { to_spend, to_sign })
+    }
+}
+
+/// The signet solution a block carries.
+///
+/// A commitment without a solution push counts as an empty solution.
+pub fn block_solution(block: &Block) -> Result<SignetSolution, SignetError> {
+    split_solution(block).map(|(_, solution)| solution)
+}
+
+/// The block's coinbase with the solution cut out, and the solution.
+fn split_solution(block: &Block) -> Result<(Transaction, SignetSolution), SignetError> {
+    let index = block
+        .witness_commitment_index()
+        .ok_or(SignetError::MissingWitnessCommitment)?;
+
+    let mut coinbase = block.transactions[0].clone();
+    let script = &mut coinbase.outputs[index].script_pubkey;
+    let solution = match take_solution(script) {
+        Some((stripped, solution)) => {
+            *script = stripped;
+            SignetSolution::parse(&solution).map_err(SignetError::InvalidSolution)?
+        }
+        None => SignetSolution::default(),
+    };
+
+    Ok((coinbase, solution))
+}
+
+/// Split the solution out of a witness commitment script.
+///
+/// Like Bitcoin Core, the first push that starts with the header and has
+/// data after it holds the solution; it is cut back to the bare header and
+/// every push is re-encoded minimally. Returns the stripped script and the
+/// solution bytes, or `None` if there is no solution push.
+fn take_solution(script: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+    let mut stripped = Vec::with_capacity(script.len());
+    let mut solution = None;
+    let mut pos = 0;
+
+    while pos < script.len() {
+        let opcode = script[pos];
+        pos += 1;
+
+        let len = match opcode {
+            0x01..=0x4b => opcode as usize,
+            0x4c..=0x4e => {
+                let size = 1 << (opcode - 0x4c);
+                let Some(bytes) = script.get(pos..pos + size) else { break };
+                pos += size;
+                bytes.iter().rev().fold(0usize, |len, &b| (len << 8) | b as usize)
+            }
+            _ => {
+                stripped.push(opcode);
+                continue;
+            }
+        };
+        let Some(data) = pos.checked_add(len).and_then(|end| script.get(pos..end)) else { break };
+        pos += len;
+
+        if solution.is_none() && data.len() > SIGNET_HEADER.len() && data.starts_with(&SIGNET_HEADER) {
+            solution = Some(data[SIGNET_HEADER.len()..].to_vec());
+            push_data(&SIGNET_HEADER, &mut stripped);
+        } else {
+            push_data(data, &mut stripped);
+        }
+    }
+
+    solution.map(|solution| (stripped, solution))
+}
+
+/// Append the smallest push of `data` to `script`.
+pub(crate) fn push_data(data: &[u8], script: &mut Vec<u8>) {
+    match data.len() {
+        len @ 0..=0x4b => script.push(len as u8),
+        len @ 0x4c..=0xff => script.extend_from_slice(&[0x4c, len as u8]),
+        len @ 0x100..=0xffff => {
+            script.push(0x4d);
+            script.extend_from_slice(&(len as u16).to_le_bytes());
+        }
+        len => {
+            script.push(0x4e);
+            script.extend_from_slice(&(len as u32).to_le_bytes());
+        }
+    }
+    script.extend_from_slice(data);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::address::validate_address;
+    use crate::block::BlockTemplate;
+    use crate::coinbase::CoinbaseBuilder;
+    use crate::decode::decode_block;
+    use crate::gbt::tests::SIGNET_TEMPLATE_JSON;
+    use crate::gbt::GetBlockTemplate;
+    use crate::merkle::{compute_witness_commitment, witness_commitment_script};
+    use crate::network::Network;
+    use crate::validate::{validate_block, ValidationContext, Violation};
+
+    fn custom_signet_template() -> BlockTemplate {
+        let network = Network::Signet;
+        let gbt = GetBlockTemplate::parse(SIGNET_TEMPLATE_JSON).unwrap();
+        let address = validate_address("tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx", network).unwrap();
+        let builder = CoinbaseBuilder::new(network, gbt.height, address);
+        assert_eq!(builder.signet_challenge(), Some(&DEFAULT_SIGNET_CHALLENGE[..]));
+        BlockTemplate::from_getblocktemplate(network, gbt, builder).unwrap()
+    }
+
+    #[test]
+    fn test_signet_magic() {
+        assert_eq!(signet_magic(&DEFAULT_SIGNET_CHALLENGE), [0x0a, 0x03, 0xcf, 0x40]);
+    }
+
+    #[test]
+    fn test_solution_round_trip() {
+        let solution = SignetSolution {
+            script_sig: vec![0x01, 0x02],
+            witness: vec![vec![0x30; 71], vec![]],
+        };
+        let bytes = solution.serialize();
+        assert_eq!(SignetSolution::parse(&bytes), Ok(solution));
+        assert_eq!(SignetSolution::default().serialize(), [0x00, 0x00]);
+
+        let mut extra = bytes.clone();
+        extra.push(0x00);
+        assert_eq!(SignetSolution::parse(&extra), Err(DecodeError::TrailingBytes(1)));
+    }
+
+    #[test]
+    fn test_take_solution() {
+        let commitment = witness_commitment_script(&compute_witness_commitment(&[0u8; 32], &[0u8; 32]));
+        let solution = SignetSolution { script_sig: vec![], witness: vec![vec![0xAB; 100]] };
+        let script = append_solution(&commitment, &solution);
+
+        // 4-byte header plus a 103-byte solution needs OP_PUSHDATA1
+        assert_eq!(script[38], 0x4c);
+        assert_eq!(script.len(), 38 + 2 + 4 + 103);
+
+        let (stripped, bytes) = take_solution(&script).unwrap();
+        let mut expected = commitment.clone();
+        expected.extend_from_slice(&[0x04, 0xec, 0xc7, 0xda, 0xa2]);
+        assert_eq!(stripped, expected);
+        assert_eq!(SignetSolution::parse(&bytes), Ok(solution));
+
+        // A bare header is not a solution
+        assert_eq!(take_solution(&stripped), None);
+        assert_eq!(take_solution(&commitment), None);
+    }
+
+    #[test]
+    fn test_custom_signet_template() {
+        let mut template = custom_signet_template();
+        assert_eq!(template.coinbase_builder.signet_challenge(), Some(&[0x51][..]));
+
+        // The node's commitment followed by an empty solution
+        let block = decode_block(&template.serialize_block()).unwrap();
+        let index = block.witness_commitment_index().unwrap();
+        let script = &block.transactions[0].outputs[index].script_pubkey;
+        assert_eq!(&script[..38], &template.default_witness_commitment.clone().unwrap()[..]);
+        assert_eq!(&script[38..], &[0x06, 0xec, 0xc7, 0xda, 0xa2, 0x00, 0x00]);
+        assert_eq!(block_solution(&block), Ok(SignetSolution::default()));
+
+        let txs = template.signet_txs().unwrap();
+        assert_eq!(txs.to_spend.outputs[0].script_pubkey, [0x51]);
+        assert_eq!(txs.to_sign.inputs[0].prev_txid, txs.to_spend.txid());
+
+        // OP_0 <version, previous hash, signet merkle root, time>
+        let script_sig = &txs.to_spend.inputs[0].script_sig;
+        assert_eq!(&script_sig[..2], &[0x00, 0x48]);
+        assert_eq!(&script_sig[2..6], &template.header.version.to_le_bytes());
+        assert_eq!(&script_sig[6..38], &template.header.prev_block_hash);
+        assert_eq!(&script_sig[70..], &template.header.timestamp.to_le_bytes());
+
+        // The signed merkle root is over the coinbase without the solution
+        let mut coinbase = block.transactions[0].clone();
+        let script = &mut coinbase.outputs[index].script_pubkey;
+        script.truncate(38);
+        script.push(0x04);
+        script.extend_from_slice(&SIGNET_HEADER);
+        assert_eq!(&script_sig[38..70], &coinbase.txid());
+        assert_ne!(&script_sig[38..70], &template.header.merkle_root);
+
+        // Adding a solution changes the block but not what it signs
+        let solution = SignetSolution { script_sig: vec![], witness: vec![vec![0x01; 64]] };
+        let merkle_root = template.header.merkle_root;
+        template.set_signet_solution(solution.clone());
+        assert_ne!(template.header.merkle_root, merkle_root);
+        let signed = template.signet_txs().unwrap();
+        assert_eq!(signed.to_spend.txid(), txs.to_spend.txid());
+        assert_eq!(signed.to_sign.inputs[0].witness, solution.witness);
+
+        // Only the proof of work is missing
+        assert_eq!(template.validate(1_700_000_000), [Violation::HighHash]);
+    }
+
+    #[test]
+    fn test_signet_block_needs_commitment() {
+        let mut block = decode_block(&custom_signet_template().serialize_block()).unwrap();
+        let coinbase = &mut block.transactions[0];
+        coinbase.outputs.truncate(1);
+        coinbase.inputs[0].witness.clear();
+        block.header.merkle_root = block.transactions[0].txid();
+
+        let context = ValidationContext { network: Network::Signet, height: 1, fees: 0, min_time: 0, now: 0 };
+        assert_eq!(
+            validate_block(&block, &context),
+            [Violation::HighHash, Violation::BadSignetSolution(SignetError::MissingWitnessCommitment)]
+        );
+        assert!(matches!(SignetTxs::new(&block, &[0x51]), Err(SignetError::MissingWitnessCommitment)));
+    }
+}
@@ -0,0 +1,573 @@
+//! Multi-lane SHA-256 for nonce scanning.
+//!
+//! Header hashes for consecutive nonces differ only in one message word, so
+//! they vectorize perfectly: each SIMD lane runs the same SHA-256 rounds on a
+//! different nonce. The rounds are written once over the [`Lanes`] trait and
+//! instantiated for SSE4.1 (4 lanes), AVX2 (8), AVX-512 (16) and WASM
+//! simd128 (4). The x86 backends are picked at runtime; simd128 has no
+//! runtime detection and is used when the module is compiled with it.
+
+// Targets without a vector backend only use the scalar path
+#![cfg_attr(
+    not(any(target_arch = "x86", target_arch = "x86_64", all(target_arch = "wasm32", target_feature = "simd128"))),
+    allow(dead_code)
+)]
+
+use crate::hash::HeaderMidstate;
+
+/// Maximum number of lanes of any backend.
+pub const MAX_LANES: usize = 16;
+
+/// A SHA-256 implementation used to hash headers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// One header at a time via the `sha2` crate.
+    Scalar,
+    /// 4 lanes of 32-bit words in SSE registers.
+    Sse41,
+    /// 8 lanes in AVX2 registers.
+    Avx2,
+    /// 16 lanes in AVX-512 registers.
+    Avx512,
+    /// 4 lanes in WebAssembly SIMD registers.
+    Simd128,
+}
+
+impl Backend {
+    /// The fastest backend supported by this CPU.
+    ///
+    /// That is the widest one, except that `sha2` uses the SHA extensions
+    /// when present, and one such lane outruns 4 or 8 lanes of plain SIMD.
+    pub fn detect() -> Self {
+        if Backend::Avx512.is_supported() {
+            return Backend::Avx512;
+        }
+        if x86_feature!("sha") {
+            return Backend::Scalar;
+        }
+        [Backend::Avx2, Backend::Sse41, Backend::Simd128]
+            .into_iter()
+            .find(|backend| backend.is_supported())
+            .unwrap_or(Backend::Scalar)
+    }
+
+    /// All backends usable on this CPU, narrowest first.
+    pub fn available() -> alloc::vec::Vec<Self> {
+        [Backend::Scalar, Backend::Sse41, Backend::Simd128, Backend::Avx2, Backend::Avx512]
+            .into_iter()
+            .filter(|backend| backend.is_supported())
+            .collect()
+    }
+
+    /// Whether this backend can run here.
+    pub fn is_supported(self) -> bool {
+        match self {
+            Backend::Scalar => true,
+            Backend::Sse41 => x86_feature!("sse4.1"),
+            Backend::Avx2 => x86_feature!("avx2"),
+            Backend::Avx512 => x86_feature!("avx512f"),
+            Backend::Simd128 => cfg!(all(target_arch = "wasm32", target_feature = "simd128")),
+        }
+    }
+
+    /// Number of headers hashed per call.
+    pub fn lanes(self) -> usize {
+        match self {
+            Backend::Scalar => 1,
+            Backend::Sse41 | Backend::Simd128 => 4,
+            Backend::Avx2 => 8,
+            Backend::Avx512 => 16,
+        }
+    }
+
+    /// Short name for logs and benchmarks.
+    pub fn name(self) -> &'static str {
+        match self {
+            Backend::Scalar => "scalar",
+            Backend::Sse41 => "sse4.1",
+            Backend::Avx2 => "avx2",
+            Backend::Avx512 => "avx512",
+            Backend::Simd128 => "simd128",
+        }
+    }
+
+    /// Hash the header for nonces `nonce..nonce + lanes()` into `out`.
+    ///
+    /// Returns the number of hashes written. Unsupported backends fall back
+    /// to hashing a single nonce.
+    pub fn hash_lanes(self, midstate: &HeaderMidstate, nonce: u32, out: &mut [[u8; 32]; MAX_LANES]) -> usize {
+        if !self.is_supported() {
+            out[0] = midstate.hash(nonce);
+            return 1;
+        }
+
+        match self {
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            // SAFETY: the CPU supports the instructions (checked above)
+            Backend::Sse41 => unsafe { x86::hash_sse41(midstate, nonce, out) },
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            // SAFETY: as above
+            Backend::Avx2 => unsafe { x86::hash_avx2(midstate, nonce, out) },
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            // SAFETY: as above
+            Backend::Avx512 => unsafe { x86::hash_avx512(midstate, nonce, out) },
+            #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+            Backend::Simd128 => hash_header_lanes::<wasm::Simd128>(midstate, nonce, out),
+            _ => {
+                out[0] = midstate.hash(nonce);
+                1
+            }
+        }
+    }
+}
+
+/// Runtime x86 feature detection with `std`, compile-time otherwise.
+macro_rules! x86_feature {
+    ($feature:tt) => {{
+        #[cfg(all(feature = "std", any(target_arch = "x86", target_arch = "x86_64")))]
+        let supported = std::arch::is_x86_feature_detected!($feature);
+        #[cfg(not(all(feature = "std", any(target_arch = "x86", target_arch = "x86_64"))))]
+        let supported = cfg!(all(any(target_arch = "x86", target_arch = "x86_64"), target_feature = $feature));
+        supported
+    }};
+}
+use x86_feature;
+
+/// Vector of 32-bit lanes with the operations SHA-256 needs.
+trait Lanes: Copy {
+    const LANES: usize;
+
+    fn splat(value: u32) -> Self;
+    fn load(values: &[u32; MAX_LANES]) -> Self;
+    fn store(self, out: &mut [u32; MAX_LANES]);
+    fn add(self, other: Self) -> Self;
+    fn xor(self, other: Self) -> Self;
+    fn and(self, other: Self) -> Self;
+    fn or(self, other: Self) -> Self;
+    /// `!self & other`
+    fn andnot(self, other: Self) -> Self;
+    fn shr(self, n: u32) -> Self;
+    fn shl(self, n: u32) -> Self;
+
+    #[inline(always)]
+    fn rotr(self, n: u32) -> Self {
+        self.shr(n).or(self.shl(32 - n))
+    }
+}
+
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+const IV: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+/// One SHA-256 compression on every lane.
+#[inline(always)]
+fn compress<V: Lanes>(state: &[V; 8], block: &[V; 16]) -> [V; 8] {
+    let mut w = *block;
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+
+    for (i, k) in K.iter().enumerate() {
+        // The message schedule is kept in a rolling 16-word window
+        let wi = if i < 16 {
+            w[i]
+        } else {
+            let w15 = w[(i + 1) % 16];
+            let w2 = w[(i + 14) % 16];
+            let s0 = w15.rotr(7).xor(w15.rotr(18)).xor(w15.shr(3));
+            let s1 = w2.rotr(17).xor(w2.rotr(19)).xor(w2.shr(10));
+            let next = w[i % 16].add(s0).add(w[(i + 9) % 16]).add(s1);
+            w[i % 16] = next;
+            next
+        };
+
+        let s1 = e.rotr(6).xor(e.rotr(11)).xor(e.rotr(25));
+        let ch = e.and(f).xor(e.andnot(g));
+        let t1 = h.add(s1).add(ch).add(V::splat(*k)).add(wi);
+        let s0 = a.rotr(2).xor(a.rotr(13)).xor(a.rotr(22));
+        let maj = a.and(b).xor(a.and(c)).xor(b.and(c));
+        let t2 = s0.add(maj);
+
+        h = g;
+        g = f;
+        f = e;
+        e = d.add(t1);
+        d = c;
+        c = b;
+        b = a;
+        a = t1.add(t2);
+    }
+
+    [
+        state[0].add(a), state[1].add(b), state[2].add(c), state[3].add(d),
+        state[4].add(e), state[5].add(f), state[6].add(g), state[7].add(h),
+    ]
+}
+
+/// Double SHA-256 of the header for `V::LANES` consecutive nonces.
+#[inline(always)]
+fn hash_header_lanes<V: Lanes>(midstate: &HeaderMidstate, nonce: u32, out: &mut [[u8; 32]; MAX_LANES]) -> usize {
+    // The nonce sits in message word 3 of the header's second block
+    let mut nonces = [0u32; MAX_LANES];
+    for (i, lane) in nonces.iter_mut().enumerate().take(V::LANES) {
+        *lane = nonce.wrapping_add(i as u32).swap_bytes();
+    }
+
+    let tail = midstate.tail();
+    let mut block = [V::splat(0); 16];
+    for (i, word) in block.iter_mut().enumerate() {
+        *word = V::splat(u32::from_be_bytes([tail[4 * i], tail[4 * i + 1], tail[4 * i + 2], tail[4 * i + 3]]));
+    }
+    block[3] = V::load(&nonces);
+
+    let first = compress(&midstate.state().map(V::splat), &block);
+
+    // Second SHA-256 over the 32-byte digest: 0x80 padding and length 256
+    let mut block = [V::splat(0); 16];
+    block[..8].copy_from_slice(&first);
+    block[8] = V::splat(0x8000_0000);
+    block[15] = V::splat(256);
+    let digest = compress(&IV.map(V::splat), &block);
+
+    let mut words = [[0u32; MAX_LANES]; 8];
+    for (word, lanes) in digest.iter().zip(words.iter_mut()) {
+        word.store(lanes);
+    }
+    for (lane, hash) in out.iter_mut().enumerate().take(V::LANES) {
+        for (i, lanes) in words.iter().enumerate() {
+            hash[4 * i..4 * i + 4].copy_from_slice(&lanes[lane].to_be_bytes());
+        }
+    }
+
+    V::LANES
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+mod x86 {
+    #[cfg(target_arch = "x86")]
+    use core::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use core::arch::x86_64::*;
+    use super::{hash_header_lanes, Lanes, MAX_LANES};
+    use crate::hash::HeaderMidstate;
+
+    #[derive(Clone, Copy)]
+    pub struct Sse41(__m128i);
+
+    #[derive(Clone, Copy)]
+    pub struct Avx2(__m256i);
+
+    #[derive(Clone, Copy)]
+    pub struct Avx512(__m512i);
+
+    // The intrinsics below are only reached through the `#[target_feature]`
+    // entry points at the bottom, which callers guard with feature detection.
+
+    impl Lanes for Sse41 {
+        const LANES: usize = 4;
+
+        #[inline(always)]
+        fn splat(value: u32) -> Self {
+            unsafe { Sse41(_mm_set1_epi32(value as i32)) }
+        }
+        #[inline(always)]
+        fn load(values: &[u32; MAX_LANES]) -> Self {
+            unsafe { Sse41(_mm_loadu_si128(values.as_ptr().cast())) }
+        }
+        #[inline(always)]
+        fn store(self, out: &mut [u32; MAX_LANES]) {
+            unsafe { _mm_storeu_si128(out.as_mut_ptr().cast(), self.0) }
+        }
+        #[inline(always)]
+        fn add(self, other: Self) -> Self {
+            unsafe { Sse41(_mm_add_epi32(self.0, other.0)) }
+        }
+        #[inline(always)]
+        fn xor(self, other: Self) -> Self {
+            unsafe { Sse41(_mm_xor_si128(self.0, other.0)) }
+        }
+        #[inline(always)]
+        fn and(self, other: Self) -> Self {
+            unsafe { Sse41(_mm_and_si128(self.0, other.0)) }
+        }
+        #[inline(always)]
+        fn or(self, other: Self) -> Self {
+            unsafe { Sse41(_mm_or_si128(self.0, other.0)) }
+        }
+        #[inline(always)]
+        fn andnot(self, other: Self) -> Self {
+            unsafe { Sse41(_mm_andnot_si128(self.0, other.0)) }
+        }
+        #[inline(always)]
+        fn shr(self, n: u32) -> Self {
+            unsafe { Sse41(_mm_srl_epi32(self.0, _mm_cvtsi32_si128(n as i32))) }
+        }
+        #[inline(always)]
+        fn shl(self, n: u32) -> Self {
+            unsafe { Sse41(_mm_sll_epi32(self.0, _mm_cvtsi32_si128(n as i32))) }
+        }
+    }
+
+    impl Lanes for Avx2 {
+        const LANES: usize = 8;
+
+        #[inline(always)]
+        fn splat(value: u32) -> Self {
+            unsafe { Avx2(_mm256_set1_epi32(value as i32)) }
+        }
+        #[inline(always)]
+        fn load(values: &[u32; MAX_LANES]) -> Self {
+            unsafe { Avx2(_mm256_loadu_si256(values.as_ptr().cast())) }
+        }
+        #[inline(always)]
+        fn store(self, out: &mut [u32; MAX_LANES]) {
+            unsafe { _mm256_storeu_si256(out.as_mut_ptr().cast(), self.0) }
+        }
+        #[inline(always)]
+        fn add(self, other: Self) -> Self {
+            unsafe { Avx2(_mm256_add_epi32(self.0, other.0)) }
+        }
+        #[inline(always)]
+        fn xor(self, other: Self) -> Self {
+            unsafe { Avx2(_mm256_xor_si256(self.0, other.0)) }
+        }
+        #[inline(always)]
+        fn and(self, other: Self) -> Self {
+            unsafe { Avx2(_mm256_and_si256(self.0, other.0)) }
+        }
+        #[inline(always)]
+        fn or(self, other: Self) -> Self {
+            unsafe { Avx2(_mm256_or_si256(self.0, other.0)) }
+        }
+        #[inline(always)]
+        fn andnot(self, other: Self) -> Self {
+            unsafe { Avx2(_mm256_andnot_si256(self.0, other.0)) }
+        }
+        #[inline(always)]
+        fn shr(self, n: u32) -> Self {
+            unsafe { Avx2(_mm256_srl_epi32(self.0, _mm_cvtsi32_si128(n as i32))) }
+        }
+        #[inline(always)]
+        fn shl(self, n: u32) -> Self {
+            unsafe { Avx2(_mm256_sll_epi32(self.0, _mm_cvtsi32_si128(n as i32))) }
+        }
+    }
+
+    impl Lanes for Avx512 {
+        const LANES: usize = 16;
+
+        #[inline(always)]
+        fn splat(value: u32) -> Self {
+            unsafe { Avx512(_mm512_set1_epi32(value as i32)) }
+        }
+        #[inline(always)]
+        fn load(values: &[u32; MAX_LANES]) -> Self {
+            unsafe { Avx512(_mm512_loadu_si512(values.as_ptr().cast())) }
+        }
+        #[inline(always)]
+        fn store(self, out: &mut [u32; MAX_LANES]) {
+            unsafe { _mm512_storeu_si512(out.as_mut_ptr().cast(), self.0) }
+        }
+        #[inline(always)]
+        fn add(self, other: Self) -> Self {
+            unsafe { Avx512(_mm512_add_epi32(self.0, other.0)) }
+        }
+        #[inline(always)]
+        fn xor(self, other: Self) -> Self {
+            unsafe { Avx512(_mm512_xor_si512(self.0, other.0)) }
+        }
+        #[inline(always)]
+        fn and(self, other: Self) -> Self {
+            unsafe { Avx512(_mm512_and_si512(self.0, other.0)) }
+        }
+        #[inline(always)]
+        fn or(self, other: Self) -> Self {
+            unsafe { Avx512(_mm512_or_si512(self.0, other.0)) }
+        }
+        #[inline(always)]
+        fn andnot(self, other: Self) -> Self {
+            unsafe { Avx512(_mm512_andnot_si512(self.0, other.0)) }
+        }
+        #[inline(always)]
+        fn shr(self, n: u32) -> Self {
+            unsafe { Avx512(_mm512_srl_epi32(self.0, _mm_cvtsi32_si128(n as i32))) }
+        }
+        #[inline(always)]
+        fn shl(self, n: u32) -> Self {
+            unsafe { Avx512(_mm512_sll_epi32(self.0, _mm_cvtsi32_si128(n as i32))) }
+        }
+        #[inline(always)]
+        fn rotr(self, n: u32) -> Self {
+            // AVX-512 has a native rotate
+            unsafe { Avx512(_mm512_rorv_epi32(self.0, _mm512_set1_epi32(n as i32))) }
+        }
+    }
+
+    #[target_feature(enable = "sse4.1")]
+    pub unsafe fn hash_sse41(midstate: &HeaderMidstate, nonce: u32, out: &mut [[u8; 32]; MAX_LANES]) -> usize {
+        hash_header_lanes::<Sse41>(midstate, nonce, out)
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn hash_avx2(midstate: &HeaderMidstate, nonce: u32, out: &mut [[u8; 32]; MAX_LANES]) -> usize {
+        hash_header_lanes::<Avx2>(midstate, nonce, out)
+    }
+
+    #[target_feature(enable = "avx512f")]
+    pub unsafe fn hash_avx512(midstate: &HeaderMidstate, nonce: u32, out: &mut [[u8; 32]; MAX_LANES]) -> usize {
+        hash_header_lanes::<Avx512>(midstate, nonce, out)
+    }
+}
+
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+mod wasm {
+    use core::arch::wasm32::*;
+    use super::{Lanes, MAX_LANES};
+
+    #[derive(Clone, Copy)]
+    pub struct Simd128(v128);
+
+    impl Lanes for Simd128 {
+        const LANES: usize = 4;
+
+        #[inline(always)]
+        fn splat(value: u32) -> Self {
+            Simd128(u32x4_splat(value))
+        }
+        #[inline(always)]
+        fn load(values: &[u32; MAX_LANES]) -> Self {
+            Simd128(u32x4(values[0], values[1], values[2], values[3]))
+        }
+        #[inline(always)]
+        fn store(self, out: &mut [u32; MAX_LANES]) {
+            out[0] = u32x4_extract_lane::<0>(self.0);
+            out[1] = u32x4_extract_lane::<1>(self.0);
+            out[2] = u32x4_extract_lane::<2>(self.0);
+            out[3] = u32x4_extract_lane::<3>(self.0);
+        }
+        #[inline(always)]
+        fn add(self, other: Self) -> Self {
+            Simd128(u32x4_add(self.0, other.0))
+        }
+        #[inline(always)]
+        fn xor(self, other: Self) -> Self {
+            Simd128(v128_xor(self.0, other.0))
+        }
+        #[inline(always)]
+        fn and(self, other: Self) -> Self {
+            Simd128(v128_and(self.0, other.0))
+        }
+        #[inline(always)]
+        fn or(self, other: Self) -> Self {
+            Simd128(v128_or(self.0, other.0))
+        }
+        #[inline(always)]
+        fn andnot(self, other: Self) -> Self {
+            // v128_andnot(a, b) is a & !b
+            Simd128(v128_andnot(other.0, self.0))
+        }
+        #[inline(always)]
+        fn shr(self, n: u32) -> Self {
+            Simd128(u32x4_shr(self.0, n))
+        }
+        #[inline(always)]
+        fn shl(self, n: u32) -> Self {
+            Simd128(u32x4_shl(self.0, n))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Portable single-lane `Lanes`, to test the generic rounds anywhere.
+    impl Lanes for u32 {
+        const LANES: usize = 1;
+
+        fn splat(value: u32) -> Self {
+            value
+        }
+        fn load(values: &[u32; MAX_LANES]) -> Self {
+            values[0]
+        }
+        fn store(self, out: &mut [u32; MAX_LANES]) {
+            out[0] = self;
+        }
+        fn add(self, other: Self) -> Self {
+            self.wrapping_add(other)
+        }
+        fn xor(self, other: Self) -> Self {
+            self ^ other
+        }
+        fn and(self, other: Self) -> Self {
+            self & other
+        }
+        fn or(self, other: Self) -> Self {
+            self | other
+        }
+        fn andnot(self, other: Self) -> Self {
+            !self & other
+        }
+        fn shr(self, n: u32) -> Self {
+            self >> n
+        }
+        fn shl(self, n: u32) -> Self {
+            self << n
+        }
+    }
+
+    fn header() -> [u8; 76] {
+        let mut header = [0u8; 76];
+        for (i, byte) in header.iter_mut().enumerate() {
+            *byte = (i as u8).wrapping_mul(37).wrapping_add(11);
+        }
+        header
+    }
+
+    #[test]
+    fn test_generic_rounds_match_scalar() {
+        let midstate = HeaderMidstate::new(&header());
+        let mut out = [[0u8; 32]; MAX_LANES];
+
+        for nonce in [0u32, 1, 0x1234_5678, u32::MAX] {
+            assert_eq!(hash_header_lanes::<u32>(&midstate, nonce, &mut out), 1);
+            assert_eq!(out[0], midstate.hash(nonce));
+        }
+    }
+
+    #[test]
+    fn test_backends_match_scalar() {
+        let midstate = HeaderMidstate::new(&header());
+
+        for backend in Backend::available() {
+            let mut out = [[0u8; 32]; MAX_LANES];
+            // Includes a batch that wraps around the top of the nonce space
+            for start in [0u32, 0xDEAD_BEEF, u32::MAX - 5] {
+                let count = backend.hash_lanes(&midstate, start, &mut out);
+                assert_eq!(count, backend.lanes(), "{}", backend.name());
+                for (i, hash) in out.iter().take(count).enumerate() {
+                    let nonce = start.wrapping_add(i as u32);
+                    assert_eq!(*hash, midstate.hash(nonce), "{} nonce {:#x}", backend.name(), nonce);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_detect() {
+        let backend = Backend::detect();
+        assert!(backend.is_supported());
+        assert!(Backend::available().contains(&backend));
+        assert_eq!(Backend::available()[0], Backend::Scalar);
+    }
+}
@@ -0,0 +1,7 @@
+//! Pool mining protocols.
+//!
+//! The message types and job handling are pure and work without `std`;
+//! the TCP clients that talk to a real pool require the `std` feature.
+
+pub mod v1;
+pub mod v2;
@@ -0,0 +1,770 @@
+//! Stratum V1 mining protocol.
+//!
+//! Stratum V1 is newline-delimited JSON-RPC over TCP. The pool assigns an
+//! extranonce1 in `mining.subscribe` and pushes work with `mining.notify`.
+//! The miner assembles the coinbase as `coinb1 || extranonce1 || extranonce2
+//! || coinb2`, folds the merkle branch into a root, and reports shares with
+//! `mining.submit`.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use serde_json::{json, Value};
+use crate::block::BlockHeader;
+use crate::hash::double_sha256;
+use crate::merkle::fold_merkle_branch;
+
+/// Stratum V1 protocol errors.
+#[derive(Debug, Clone)]
+pub enum StratumError {
+    /// Transport failure (connection refused, reset, timed out)
+    Io(String),
+    /// The pool closed the connection
+    Disconnected,
+    /// A message from the pool could not be parsed
+    InvalidMessage(String),
+    /// The pool answered a request with an error
+    Rpc { code: i64, message: String },
+    /// The pool refused the worker credentials
+    Unauthorized,
+    /// Work was requested before `mining.subscribe` completed
+    NotSubscribed,
+    /// No complete message arrived within the read timeout
+    Timeout,
+}
+
+impl core::fmt::Display for StratumError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            StratumError::Io(e) => write!(f, "Connection error: {}", e),
+            StratumError::Disconnected => write!(f, "Pool closed the connection"),
+            StratumError::InvalidMessage(s) => write!(f, "Invalid stratum message: {}", s),
+            StratumError::Rpc { code, message } => write!(f, "Pool error {}: {}", code, message),
+            StratumError::Unauthorized => write!(f, "Worker authorization failed"),
+            StratumError::Timeout => write!(f, "Timed out waiting for the pool"),
+            StratumError::NotSubscribed => write!(f, "Not subscribed to the pool"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for StratumError {
+    fn from(e: std::io::Error) -> Self {
+        match e.kind() {
+            std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut => StratumError::Timeout,
+            _ => StratumError::Io(e.to_string()),
+        }
+    }
+}
+
+/// Extranonce assignment from `mining.subscribe` (or `mining.set_extranonce`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Subscription {
+    /// Pool-assigned prefix of the coinbase extranonce.
+    pub extranonce1: Vec<u8>,
+    /// Number of extranonce bytes the miner is free to roll.
+    pub extranonce2_size: usize,
+}
+
+impl Subscription {
+    /// Parse the `[extranonce1, extranonce2_size]` pair starting at `params[offset]`.
+    fn from_params(params: &[Value], offset: usize) -> Result<Self, StratumError> {
+        let extranonce1 = params
+            .get(offset)
+            .and_then(Value::as_str)
+            .ok_or_else(|| invalid("missing extranonce1"))?;
+        let extranonce2_size = params
+            .get(offset + 1)
+            .and_then(Value::as_u64)
+            .ok_or_else(|| invalid("missing extranonce2 size"))?;
+
+        Ok(Subscription {
+            extranonce1: decode_hex(extranonce1)?,
+            extranonce2_size: extranonce2_size as usize,
+        })
+    }
+
+    /// Parse the result of a `mining.subscribe` request.
+    ///
+    /// Format: `[[subscriptions...], extranonce1, extranonce2_size]`
+    pub fn from_result(result: &Value) -> Result<Self, StratumError> {
+        let items = result
+            .as_array()
+            .ok_or_else(|| invalid("subscribe result is not an array"))?;
+        Self::from_params(items, 1)
+    }
+
+    /// Encode a counter as an extranonce2 of the subscribed size.
+    ///
+    /// The counter is written big-endian and truncated to the low bytes.
+    pub fn extranonce2(&self, counter: u64) -> Vec<u8> {
+        let bytes = counter.to_be_bytes();
+        let mut extranonce2 = alloc::vec![0u8; self.extranonce2_size];
+        let n = self.extranonce2_size.min(8);
+        let len = extranonce2.len();
+        extranonce2[len - n..].copy_from_slice(&bytes[8 - n..]);
+        extranonce2
+    }
+}
+
+/// A unit of work from `mining.notify`.
+#[derive(Debug, Clone)]
+pub struct Job {
+    /// Pool identifier for this job.
+    pub job_id: String,
+    /// Hash of the previous block (internal byte order).
+    pub prev_block_hash: [u8; 32],
+    /// Coinbase transaction bytes before the extranonce.
+    pub coinb1: Vec<u8>,
+    /// Coinbase transaction bytes after the extranonce.
+    pub coinb2: Vec<u8>,
+    /// Sibling hashes on the path from the coinbase to the merkle root.
+    pub merkle_branch: Vec<[u8; 32]>,
+    /// Block version.
+    pub version: i32,
+    /// Difficulty target in compact "bits" format.
+    pub bits: u32,
+    /// Block timestamp suggested by the pool.
+    pub time: u32,
+    /// Whether previous jobs should be abandoned.
+    pub clean_jobs: bool,
+}
+
+impl Job {
+    /// Parse the params of a `mining.notify` notification.
+    ///
+    /// Format: `[job_id, prevhash, coinb1, coinb2, merkle_branch, version, nbits, ntime, clean_jobs]`
+    pub fn from_params(params: &Value) -> Result<Self, StratumError> {
+        let params = params
+            .as_array()
+            .ok_or_else(|| invalid("notify params are not an array"))?;
+        if params.len() < 9 {
+            return Err(invalid("notify requires 9 params"));
+        }
+
+        let field = |i: usize, name: &str| -> Result<&str, StratumError> {
+            params[i]
+                .as_str()
+                .ok_or_else(|| invalid(&format!("notify {} is not a string", name)))
+        };
+
+        let prev_hash_bytes = decode_hex(field(1, "prevhash")?)?;
+        if prev_hash_bytes.len() != 32 {
+            return Err(invalid("prevhash must be 32 bytes"));
+        }
+
+        // Stratum sends the previous hash as eight 32-bit words, each byte-swapped
+        let mut prev_block_hash = [0u8; 32];
+        for (word, chunk) in prev_block_hash.chunks_mut(4).zip(prev_hash_bytes.chunks(4)) {
+            word.copy_from_slice(chunk);
+            word.reverse();
+        }
+
+        let branch = params[4]
+            .as_array()
+            .ok_or_else(|| invalid("merkle branch is not an array"))?;
+        let mut merkle_branch = Vec::with_capacity(branch.len());
+        for node in branch {
+            let node = node
+                .as_str()
+                .ok_or_else(|| invalid("merkle branch entry is not a string"))?;
+            merkle_branch.push(decode_hash(node)?);
+        }
+
+        Ok(Job {
+            job_id: field(0, "job_id")?.to_string(),
+            prev_block_hash,
+            coinb1: decode_hex(field(2, "coinb1")?)?,
+            coinb2: decode_hex(field(3, "coinb2")?)?,
+            merkle_branch,
+            version: parse_u32_hex(field(5, "version")?)? as i32,
+            bits: parse_u32_hex(field(6, "nbits")?)?,
+            time: parse_u32_hex(field(7, "ntime")?)?,
+            clean_jobs: params[8].as_bool().unwrap_or(false),
+        })
+    }
+
+    /// Assemble the serialized coinbase transaction (without witness).
+    pub fn coinbase(&self, extranonce1: &[u8], extranonce2: &[u8]) -> Vec<u8> {
+        let mut coinbase = Vec::with_capacity(
+            self.coinb1.len() + extranonce1.len() + extranonce2.len() + self.coinb2.len(),
+        );
+        coinbase.extend_from_slice(&self.coinb1);
+        coinbase.extend_from_slice(extranonce1);
+        coinbase.extend_from_slice(extranonce2);
+        coinbase.extend_from_slice(&self.coinb2);
+        coinbase
+    }
+
+    /// Fold the merkle branch over the coinbase txid to get the merkle root.
+    ///
+    /// The coinbase is always the leftmost leaf, so each step hashes
+    /// `current || sibling`.
+    pub fn merkle_root(&self, coinbase_txid: [u8; 32]) -> [u8; 32] {
+        fold_merkle_branch(coinbase_txid, &self.merkle_branch, 0)
+    }
+
+    /// Build the block header for a given extranonce2.
+    ///
+    /// The returned header has nonce 0; pass `serialize_without_nonce()`
+    /// to `mine_batch` to search the nonce space.
+    pub fn header(&self, extranonce1: &[u8], extranonce2: &[u8]) -> BlockHeader {
+        let coinbase_txid = double_sha256(&self.coinbase(extranonce1, extranonce2));
+        let merkle_root = self.merkle_root(coinbase_txid);
+
+        let mut header = BlockHeader::new(self.prev_block_hash, merkle_root, self.time, self.bits);
+        header.version = self.version;
+        header
+    }
+}
+
+/// A share to report with `mining.submit`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Share {
+    /// The job the share was mined on.
+    pub job_id: String,
+    /// The extranonce2 used to build the coinbase.
+    pub extranonce2: Vec<u8>,
+    /// The header timestamp.
+    pub time: u32,
+    /// The winning nonce.
+    pub nonce: u32,
+}
+
+/// A message pushed by the pool without a matching request.
+#[derive(Debug, Clone)]
+pub enum Notification {
+    /// New work (`mining.notify`).
+    Notify(Job),
+    /// New share difficulty (`mining.set_difficulty`).
+    SetDifficulty(f64),
+    /// New extranonce assignment (`mining.set_extranonce`).
+    SetExtranonce(Subscription),
+    /// Any other method; carries the method name.
+    Unsupported(String),
+}
+
+/// A parsed line from the pool.
+#[derive(Debug, Clone)]
+pub enum Message {
+    /// Reply to one of our requests.
+    Response {
+        /// The request id being answered.
+        id: u64,
+        /// The result payload (`null` on error).
+        result: Value,
+        /// The error reported by the pool, if any.
+        error: Option<StratumError>,
+    },
+    /// A pool-initiated message.
+    Notification(Notification),
+}
+
+/// Parse a single line received from the pool.
+pub fn parse_message(line: &str) -> Result<Message, StratumError> {
+    let value: Value = serde_json::from_str(line.trim())
+        .map_err(|e| invalid(&format!("{}", e)))?;
+
+    if let Some(method) = value.get("method").and_then(Value::as_str) {
+        let params = value.get("params").cloned().unwrap_or(Value::Null);
+        let notification = match method {
+            "mining.notify" => Notification::Notify(Job::from_params(&params)?),
+            "mining.set_difficulty" => {
+                let difficulty = params
+                    .get(0)
+                    .and_then(Value::as_f64)
+                    .ok_or_else(|| invalid("set_difficulty requires a number"))?;
+                Notification::SetDifficulty(difficulty)
+            }
+            "mining.set_extranonce" => {
+                let items = params
+                    .as_array()
+                    .ok_or_else(|| invalid("set_extranonce params are not an array"))?;
+                Notification::SetExtranonce(Subscription::from_params(items, 0)?)
+            }
+            other => Notification::Unsupported(other.to_string()),
+        };
+        return Ok(Message::Notification(notification));
+    }
+
+    let id = value
+        .get("id")
+        .and_then(Value::as_u64)
+        .ok_or_else(|| invalid("response without id"))?;
+    let error = match value.get("error") {
+        None | Some(Value::Null) => None,
+        Some(err) => Some(parse_rpc_error(err)),
+    };
+
+    Ok(Message::Response {
+        id,
+        result: value.get("result").cloned().unwrap_or(Value::Null),
+        error,
+    })
+}
+
+/// Build a `mining.subscribe` request line.
+pub fn subscribe_request(id: u64, user_agent: &str) -> String {
+    json!({"id": id, "method": "mining.subscribe", "params": [user_agent]}).to_string()
+}
+
+/// Build a `mining.authorize` request line.
+pub fn authorize_request(id: u64, worker: &str, password: &str) -> String {
+    json!({"id": id, "method": "mining.authorize", "params": [worker, password]}).to_string()
+}
+
+/// Build a `mining.submit` request line.
+///
+/// `ntime` and `nonce` are sent as big-endian hex, as pools expect.
+pub fn submit_request(id: u64, worker: &str, share: &Share) -> String {
+    json!({
+        "id": id,
+        "method": "mining.submit",
+        "params": [
+            worker,
+            share.job_id,
+            hex::encode(&share.extranonce2),
+            format!("{:08x}", share.time),
+            format!("{:08x}", share.nonce),
+        ],
+    })
+    .to_string()
+}
+
+/// Parse a JSON-RPC error, either `[code, message, data]` or `{code, message}`.
+fn parse_rpc_error(err: &Value) -> StratumError {
+    let (code, message) = match err {
+        Value::Array(items) => (
+            items.first().and_then(Value::as_i64),
+            items.get(1).and_then(Value::as_str),
+        ),
+        Value::Object(_) => (
+            err.get("code").and_then(Value::as_i64),
+            err.get("message").and_then(Value::as_str),
+        ),
+        _ => (None, err.as_str()),
+    };
+
+    StratumError::Rpc {
+        code: code.unwrap_or(-1),
+        message: message.unwrap_or("unknown error").to_string(),
+    }
+}
+
+fn invalid(reason: &str) -> StratumError {
+    StratumError::InvalidMessage(reason.to_string())
+}
+
+fn decode_hex(s: &str) -> Result<Vec<u8>, StratumError> {
+    hex::decode(s).map_err(|_| invalid(&format!("invalid hex: {}", s)))
+}
+
+fn decode_hash(s: &str) -> Result<[u8; 32], StratumError> {
+    let bytes = decode_hex(s)?;
+    if bytes.len() != 32 {
+        return Err(invalid("hash must be 32 bytes"));
+    }
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&bytes);
+    Ok(hash)
+}
+
+fn parse_u32_hex(s: &str) -> Result<u32, StratumError> {
+    u32::from_str_radix(s, 16).map_err(|_| invalid(&format!("invalid hex u32: {}", s)))
+}
+
+#[cfg(feature = "std")]
+pub use client::StratumClient;
+
+#[cfg(feature = "std")]
+mod client {
+    use super::*;
+    use alloc::collections::VecDeque;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::{TcpStream, ToSocketAddrs};
+    use std::time::Duration;
+    use crate::difficulty::difficulty_to_target;
+
+    /// A blocking Stratum V1 client over TCP.
+    pub struct StratumClient {
+        reader: BufReader<TcpStream>,
+        writer: TcpStream,
+        next_id: u64,
+        /// Notifications received while waiting for a response.
+        pending: VecDeque<Notification>,
+        /// Bytes of a line not yet terminated, kept across read timeouts.
+        partial: Vec<u8>,
+        subscription: Option<Subscription>,
+        worker: Option<String>,
+        difficulty: f64,
+    }
+
+    impl StratumClient {
+        /// Connect to a pool.
+        pub fn connect<A: ToSocketAddrs>(addr: A) -> Result<Self, StratumError> {
+            let stream = TcpStream::connect(addr)?;
+            stream.set_nodelay(true)?;
+            let reader = BufReader::new(stream.try_clone()?);
+
+            Ok(StratumClient {
+                reader,
+                writer: stream,
+                next_id: 1,
+                pending: VecDeque::new(),
+                partial: Vec::new(),
+                subscription: None,
+                worker: None,
+                difficulty: 1.0,
+            })
+        }
+
+        /// Set a read timeout for blocking reads (`None` blocks forever).
+        ///
+        /// When it expires, reads return [`StratumError::Timeout`] and any
+        /// partially received line is kept for the next read.
+        pub fn set_read_timeout(&self, timeout: Option<Duration>) -> Result<(), StratumError> {
+            self.writer.set_read_timeout(timeout)?;
+            Ok(())
+        }
+
+        /// Subscribe to mining notifications and receive the extranonce assignment.
+        pub fn subscribe(&mut self, user_agent: &str) -> Result<Subscription, StratumError> {
+            let id = self.take_id();
+            let result = self.call(id, &subscribe_request(id, user_agent))?;
+            let subscription = Subscription::from_result(&result)?;
+            self.subscription = Some(subscription.clone());
+            Ok(subscription)
+        }
+
+        /// Authorize a worker. Shares are submitted under this worker name.
+        pub fn authorize(&mut self, worker: &str, password: &str) -> Result<(), StratumError> {
+            let id = self.take_id();
+            let result = self.call(id, &authorize_request(id, worker, password))?;
+            if result.as_bool() != Some(true) {
+                return Err(StratumError::Unauthorized);
+            }
+            self.worker = Some(worker.to_string());
+            Ok(())
+        }
+
+        /// Submit a share. Returns whether the pool accepted it.
+        pub fn submit(&mut self, share: &Share) -> Result<bool, StratumError> {
+            let worker = self.worker.clone().ok_or(StratumError::Unauthorized)?;
+            let id = self.take_id();
+            let result = self.call(id, &submit_request(id, &worker, share))?;
+            Ok(result.as_bool() == Some(true))
+        }
+
+        /// Wait for the next pool notification.
+        pub fn next_notification(&mut self) -> Result<Notification, StratumError> {
+            if let Some(notification) = self.pending.pop_front() {
+                return Ok(notification);
+            }
+
+            loop {
+                // Late responses to earlier requests are dropped
+                if let Message::Notification(notification) = self.read_message()? {
+                    return Ok(notification);
+                }
+            }
+        }
+
+        /// The extranonce assignment, once subscribed.
+        pub fn subscription(&self) -> Result<&Subscription, StratumError> {
+            self.subscription.as_ref().ok_or(StratumError::NotSubscribed)
+        }
+
+        /// The current share difficulty (1.0 until the pool sets one).
+        pub fn difficulty(&self) -> f64 {
+            self.difficulty
+        }
+
+        /// The 256-bit target for the current share difficulty.
+        pub fn share_target(&self) -> [u8; 32] {
+            difficulty_to_target(self.difficulty)
+        }
+
+        fn take_id(&mut self) -> u64 {
+            let id = self.next_id;
+            self.next_id += 1;
+            id
+        }
+
+        /// Send a request and wait for its response, queueing notifications.
+        fn call(&mut self, id: u64, request: &str) -> Result<Value, StratumError> {
+            self.writer.write_all(request.as_bytes())?;
+            self.writer.write_all(b"\n")?;
+            self.writer.flush()?;
+
+            loop {
+                match self.read_message()? {
+                    Message::Response { id: got, result, error } if got == id => {
+                        return match error {
+                            Some(err) => Err(err),
+                            None => Ok(result),
+                        };
+                    }
+                    Message::Response { .. } => {}
+                    Message::Notification(notification) => self.pending.push_back(notification),
+                }
+            }
+        }
+
+        /// Read the next non-empty line and track difficulty/extranonce changes.
+        fn read_message(&mut self) -> Result<Message, StratumError> {
+            let line = loop {
+                if self.reader.read_until(b'\n', &mut self.partial)? == 0 {
+                    return Err(StratumError::Disconnected);
+                }
+                if self.partial.last() != Some(&b'\n') {
+                    continue;
+                }
+                let line = core::mem::take(&mut self.partial);
+                if !line.trim_ascii().is_empty() {
+                    break line;
+                }
+            };
+
+            let line = core::str::from_utf8(&line)
+                .map_err(|_| StratumError::InvalidMessage("non-UTF-8 line".into()))?;
+            let message = parse_message(line)?;
+            match &message {
+                Message::Notification(Notification::SetDifficulty(difficulty)) => {
+                    self.difficulty = *difficulty;
+                }
+                Message::Notification(Notification::SetExtranonce(subscription)) => {
+                    self.subscription = Some(subscription.clone());
+                }
+                _ => {}
+            }
+            Ok(message)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::hash_to_display_hex;
+    use crate::merkle::compute_merkle_root;
+
+    const NOTIFY: &str = r#"{"params": ["bf", "4d16b6f85af6e2198f44ae2a6de67f78487ae5611b77c6c0440b921e00000000",
+        "01000000010000000000000000000000000000000000000000000000000000000000000000ffffffff20020862062f503253482f04b8864e5008",
+        "072f736c7573682f000000000100f2052a010000001976a914d23fcdf86f7e756a64a7a9688ef9903327048ed988ac00000000", [],
+        "00000002", "1c2ac4af", "504e86b9", false], "id": null, "method": "mining.notify"}"#;
+
+    fn parse_job(line: &str) -> Job {
+        match parse_message(line).unwrap() {
+            Message::Notification(Notification::Notify(job)) => job,
+            other => panic!("expected notify, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_notify() {
+        let job = parse_job(NOTIFY);
+
+        assert_eq!(job.job_id, "bf");
+        assert_eq!(
+            hash_to_display_hex(&job.prev_block_hash),
+            "00000000440b921e1b77c6c0487ae5616de67f788f44ae2a5af6e2194d16b6f8"
+        );
+        assert_eq!(job.version, 2);
+        assert_eq!(job.bits, 0x1c2ac4af);
+        assert_eq!(job.time, 0x504e86b9);
+        assert!(job.merkle_branch.is_empty());
+        assert!(!job.clean_jobs);
+    }
+
+    #[test]
+    fn test_merkle_branch_folding() {
+        let mut job = parse_job(NOTIFY);
+        let tx1 = [0x11u8; 32];
+        let tx2 = [0x22u8; 32];
+        let tx3 = [0x33u8; 32];
+
+        let extranonce1 = [0x08, 0x00, 0x00, 0x02];
+        let extranonce2 = [0x00, 0x00, 0x00, 0x01];
+        let coinbase_txid = double_sha256(&job.coinbase(&extranonce1, &extranonce2));
+
+        // Branch for leaf 0 of [cb, tx1, tx2, tx3]: tx1, then hash(tx2 || tx3)
+        let mut pair = [0u8; 64];
+        pair[..32].copy_from_slice(&tx2);
+        pair[32..].copy_from_slice(&tx3);
+        job.merkle_branch = alloc::vec![tx1, double_sha256(&pair)];
+
+        let header = job.header(&extranonce1, &extranonce2);
+        assert_eq!(header.merkle_root, compute_merkle_root(&[coinbase_txid, tx1, tx2, tx3]));
+        assert_eq!(header.version, 2);
+        assert_eq!(header.nonce, 0);
+    }
+
+    #[test]
+    fn test_parse_subscribe_and_errors() {
+        let line = r#"{"id":1,"result":[[["mining.notify","ae6812eb4cd7735a302a8a9dd95cf71f"]],"08000002",4],"error":null}"#;
+        let Message::Response { id, result, error } = parse_message(line).unwrap() else {
+            panic!("expected response");
+        };
+        assert_eq!(id, 1);
+        assert!(error.is_none());
+
+        let subscription = Subscription::from_result(&result).unwrap();
+        assert_eq!(subscription.extranonce1, alloc::vec![0x08, 0x00, 0x00, 0x02]);
+        assert_eq!(subscription.extranonce2_size, 4);
+        assert_eq!(subscription.extranonce2(0x0102), alloc::vec![0x00, 0x00, 0x01, 0x02]);
+
+        let line = r#"{"id":4,"result":null,"error":[23,"Low difficulty share",null]}"#;
+        let Message::Response { error, .. } = parse_message(line).unwrap() else {
+            panic!("expected response");
+        };
+        assert!(matches!(error, Some(StratumError::Rpc { code: 23, .. })));
+
+        assert!(matches!(parse_message("not json"), Err(StratumError::InvalidMessage(_))));
+    }
+
+    #[test]
+    fn test_submit_request_format() {
+        let share = Share {
+            job_id: "bf".to_string(),
+            extranonce2: alloc::vec![0x00, 0x00, 0x00, 0x01],
+            time: 0x504e86b9,
+            nonce: 0xb2957c02,
+        };
+        let request: Value = serde_json::from_str(&submit_request(4, "slush.miner1", &share)).unwrap();
+
+        assert_eq!(request["method"], "mining.submit");
+        assert_eq!(
+            request["params"],
+            json!(["slush.miner1", "bf", "00000001", "504e86b9", "b2957c02"])
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_client_against_mock_pool() {
+        use std::io::{BufRead, BufReader, Write};
+        use std::net::TcpListener;
+        use crate::difficulty::{difficulty_to_target, hash_meets_target};
+        use crate::hash::{mine_batch, reverse_bytes};
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let sibling = [0x5Au8; 32];
+
+        let pool = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut writer = stream;
+            let mut read_request = || {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                serde_json::from_str::<Value>(&line).unwrap()
+            };
+
+            let request = read_request();
+            assert_eq!(request["method"], "mining.subscribe");
+            writeln!(
+                writer,
+                r#"{{"id":{},"result":[[["mining.notify","1"]],"08000002",4],"error":null}}"#,
+                request["id"]
+            )
+            .unwrap();
+
+            // Push work ahead of the authorize response
+            let request = read_request();
+            assert_eq!(request["method"], "mining.authorize");
+            let notify = NOTIFY.replace('\n', "").replace(
+                "[],",
+                &format!("[\"{}\"],", hex::encode(sibling)),
+            );
+            writeln!(writer, r#"{{"id":null,"method":"mining.set_difficulty","params":[0.0000152587890625]}}"#).unwrap();
+            writeln!(writer, "{}", notify).unwrap();
+            writeln!(writer, r#"{{"id":{},"result":true,"error":null}}"#, request["id"]).unwrap();
+
+            // Verify the share independently of the client's job handling
+            let request = read_request();
+            assert_eq!(request["method"], "mining.submit");
+            let params = request["params"].as_array().unwrap();
+            let job = parse_job(NOTIFY);
+            let extranonce2 = hex::decode(params[2].as_str().unwrap()).unwrap();
+            let ntime = u32::from_str_radix(params[3].as_str().unwrap(), 16).unwrap();
+            let nonce = u32::from_str_radix(params[4].as_str().unwrap(), 16).unwrap();
+
+            let coinbase_txid = double_sha256(&job.coinbase(&[0x08, 0x00, 0x00, 0x02], &extranonce2));
+            let mut header = Vec::with_capacity(80);
+            header.extend_from_slice(&job.version.to_le_bytes());
+            header.extend_from_slice(&job.prev_block_hash);
+            header.extend_from_slice(&compute_merkle_root(&[coinbase_txid, sibling]));
+            header.extend_from_slice(&ntime.to_le_bytes());
+            header.extend_from_slice(&job.bits.to_le_bytes());
+            header.extend_from_slice(&nonce.to_le_bytes());
+            let hash = reverse_bytes(&double_sha256(&header));
+
+            let accepted = hash_meets_target(&hash, &difficulty_to_target(1.0 / 65536.0));
+            writeln!(writer, r#"{{"id":{},"result":{},"error":null}}"#, request["id"], accepted).unwrap();
+            accepted
+        });
+
+        let mut client = StratumClient::connect(addr).unwrap();
+        let subscription = client.subscribe("goblin-test/0.1").unwrap();
+        client.authorize("worker.1", "x").unwrap();
+        assert_eq!(client.difficulty(), 1.0 / 65536.0);
+
+        let job = loop {
+            if let Notification::Notify(job) = client.next_notification().unwrap() {
+                break job;
+            }
+        };
+        assert_eq!(job.merkle_branch, alloc::vec![sibling]);
+
+        let extranonce2 = subscription.extranonce2(0);
+        let header = job.header(&subscription.extranonce1, &extranonce2);
+        let result = mine_batch(
+            &header.serialize_without_nonce(),
+            &client.share_target(),
+            &[0u8; 32],
+            0,
+            2_000_000,
+        );
+        assert!(result.block_found);
+
+        let share = Share {
+            job_id: job.job_id.clone(),
+            extranonce2,
+            time: job.time,
+            nonce: result.nonce.unwrap(),
+        };
+        assert!(client.submit(&share).unwrap());
+        assert!(pool.join().unwrap());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_client_timeout_keeps_partial_line() {
+        use std::io::Write;
+        use std::net::TcpListener;
+        use std::sync::mpsc;
+        use std::time::Duration;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (resume_tx, resume_rx) = mpsc::channel::<()>();
+
+        let pool = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.write_all(br#"{"id":null,"method":"mining.set_"#).unwrap();
+            resume_rx.recv().unwrap();
+            stream.write_all(b"difficulty\",\"params\":[2]}\n").unwrap();
+        });
+
+        let mut client = StratumClient::connect(addr).unwrap();
+        client.set_read_timeout(Some(Duration::from_millis(50))).unwrap();
+        assert!(matches!(client.next_notification(), Err(StratumError::Timeout)));
+
+        resume_tx.send(()).unwrap();
+        client.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        assert!(matches!(client.next_notification(), Ok(Notification::SetDifficulty(d)) if d == 2.0));
+        assert_eq!(client.difficulty(), 2.0);
+        pool.join().unwrap();
+    }
+}
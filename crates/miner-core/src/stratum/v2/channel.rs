@@ -0,0 +1,185 @@
+//! Standard channel state: turns jobs and prev-hash updates into headers.
+
+use alloc::vec::Vec;
+use crate::block::BlockHeader;
+use crate::hash::reverse_bytes;
+use super::messages::{
+    Message, NewMiningJob, OpenStandardMiningChannelSuccess, SetNewPrevHash, SubmitSharesStandard,
+};
+
+/// Work ready to be mined on a standard channel.
+#[derive(Debug, Clone)]
+pub struct Work {
+    /// The channel the job belongs to.
+    pub channel_id: u32,
+    /// The pool's job identifier.
+    pub job_id: u32,
+    /// The block header with nonce 0.
+    pub header: BlockHeader,
+    /// The share target (big-endian, as used by `mine_batch`).
+    pub target: [u8; 32],
+}
+
+/// Tracks jobs, the current previous hash and the share target for one channel.
+#[derive(Debug, Clone)]
+pub struct StandardChannel {
+    /// The pool-assigned channel id.
+    pub channel_id: u32,
+    /// The share target (big-endian).
+    pub target: [u8; 32],
+    /// Extranonce prefix reserved for this channel.
+    pub extranonce_prefix: Vec<u8>,
+    future_jobs: Vec<NewMiningJob>,
+    active_job: Option<NewMiningJob>,
+    prev_hash: Option<SetNewPrevHash>,
+    next_sequence_number: u32,
+}
+
+impl StandardChannel {
+    /// Create channel state from the pool's `OpenStandardMiningChannel.Success`.
+    pub fn open(success: &OpenStandardMiningChannelSuccess) -> Self {
+        StandardChannel {
+            channel_id: success.channel_id,
+            target: reverse_bytes(&success.target),
+            extranonce_prefix: success.extranonce_prefix.clone(),
+            future_jobs: Vec::new(),
+            active_job: None,
+            prev_hash: None,
+            next_sequence_number: 0,
+        }
+    }
+
+    /// Apply a message from the pool.
+    ///
+    /// Returns new work whenever the active job, previous hash or target changes.
+    pub fn handle(&mut self, message: &Message) -> Option<Work> {
+        match message {
+            Message::NewMiningJob(job) if job.channel_id == self.channel_id => {
+                if job.min_ntime.is_none() {
+                    self.future_jobs.push(job.clone());
+                    return None;
+                }
+                self.active_job = Some(job.clone());
+            }
+            Message::SetNewPrevHash(prev) if prev.channel_id == self.channel_id => {
+                let activated = self.future_jobs.iter().position(|j| j.job_id == prev.job_id);
+                if let Some(index) = activated {
+                    self.active_job = Some(self.future_jobs.swap_remove(index));
+                }
+                self.future_jobs.clear();
+                self.prev_hash = Some(prev.clone());
+            }
+            Message::SetTarget(set) if set.channel_id == self.channel_id => {
+                self.target = reverse_bytes(&set.maximum_target);
+            }
+            _ => return None,
+        }
+        self.current_work()
+    }
+
+    /// The work for the active job, if both a job and a previous hash are known.
+    pub fn current_work(&self) -> Option<Work> {
+        let job = self.active_job.as_ref()?;
+        let prev = self.prev_hash.as_ref()?;
+
+        let time = prev.min_ntime.max(job.min_ntime.unwrap_or(0));
+        let mut header = BlockHeader::new(prev.prev_hash, job.merkle_root, time, prev.nbits);
+        header.version = job.version as i32;
+
+        Some(Work {
+            channel_id: self.channel_id,
+            job_id: job.job_id,
+            header,
+            target: self.target,
+        })
+    }
+
+    /// Build a share submission for a nonce found on `work`.
+    pub fn share(&mut self, work: &Work, nonce: u32) -> SubmitSharesStandard {
+        let sequence_number = self.next_sequence_number;
+        self.next_sequence_number = self.next_sequence_number.wrapping_add(1);
+
+        SubmitSharesStandard {
+            channel_id: self.channel_id,
+            sequence_number,
+            job_id: work.job_id,
+            nonce,
+            ntime: work.header.timestamp,
+            version: work.header.version as u32,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::messages::SetTarget;
+
+    fn channel() -> StandardChannel {
+        let mut target = [0u8; 32];
+        target[29] = 0xFF; // little-endian: 0x0000ff00...
+        StandardChannel::open(&OpenStandardMiningChannelSuccess {
+            request_id: 1,
+            channel_id: 9,
+            target,
+            extranonce_prefix: alloc::vec![0xAA; 4],
+            group_channel_id: 0,
+        })
+    }
+
+    #[test]
+    fn test_future_job_activation() {
+        let mut channel = channel();
+        assert_eq!(&channel.target[..3], &[0x00, 0x00, 0xFF]);
+
+        let job = Message::NewMiningJob(NewMiningJob {
+            channel_id: 9,
+            job_id: 4,
+            min_ntime: None,
+            version: 0x2000_0000,
+            merkle_root: [0x44; 32],
+        });
+        assert!(channel.handle(&job).is_none());
+
+        let prev = Message::SetNewPrevHash(SetNewPrevHash {
+            channel_id: 9,
+            job_id: 4,
+            prev_hash: [0x55; 32],
+            min_ntime: 1_700_000_000,
+            nbits: 0x1d00ffff,
+        });
+        let work = channel.handle(&prev).unwrap();
+
+        assert_eq!(work.job_id, 4);
+        assert_eq!(work.header.prev_block_hash, [0x55; 32]);
+        assert_eq!(work.header.merkle_root, [0x44; 32]);
+        assert_eq!(work.header.timestamp, 1_700_000_000);
+        assert_eq!(work.header.bits, 0x1d00ffff);
+        assert_eq!(work.header.version, 0x2000_0000);
+
+        let share = channel.share(&work, 42);
+        assert_eq!(share.sequence_number, 0);
+        assert_eq!(share.nonce, 42);
+        assert_eq!(channel.share(&work, 43).sequence_number, 1);
+    }
+
+    #[test]
+    fn test_ignores_other_channels_and_updates_target() {
+        let mut channel = channel();
+        let other = Message::NewMiningJob(NewMiningJob {
+            channel_id: 10,
+            job_id: 1,
+            min_ntime: Some(0),
+            version: 0,
+            merkle_root: [0; 32],
+        });
+        assert!(channel.handle(&other).is_none());
+
+        let set_target = Message::SetTarget(SetTarget {
+            channel_id: 9,
+            maximum_target: [0xFF; 32],
+        });
+        assert!(channel.handle(&set_target).is_none());
+        assert_eq!(channel.target, [0xFF; 32]);
+    }
+}
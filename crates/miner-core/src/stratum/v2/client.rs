@@ -0,0 +1,267 @@
+//! Blocking Stratum V2 client for a single standard channel.
+
+use alloc::string::ToString;
+use std::net::{TcpStream, ToSocketAddrs};
+use secp256k1::XOnlyPublicKey;
+use super::channel::{StandardChannel, Work};
+use super::messages::{
+    Message, OpenStandardMiningChannel, SetupConnection, SetupConnectionSuccess,
+};
+use super::noise::NoiseStream;
+use super::Sv2Error;
+
+/// A Stratum V2 miner connection with one standard channel.
+pub struct Sv2Client {
+    stream: NoiseStream<TcpStream>,
+    channel: Option<StandardChannel>,
+    next_request_id: u32,
+}
+
+impl Sv2Client {
+    /// Connect to a pool and complete the Noise handshake.
+    ///
+    /// `authority` is the pool's published authority key; the connection
+    /// fails if the pool cannot prove its static key was signed by it.
+    pub fn connect<A: ToSocketAddrs>(addr: A, authority: XOnlyPublicKey) -> Result<Self, Sv2Error> {
+        let stream = TcpStream::connect(addr)?;
+        stream.set_nodelay(true)?;
+
+        Ok(Sv2Client {
+            stream: NoiseStream::connect(stream, authority)?,
+            channel: None,
+            next_request_id: 1,
+        })
+    }
+
+    /// Send `SetupConnection` for the mining protocol.
+    pub fn setup_connection(&mut self, vendor: &str) -> Result<SetupConnectionSuccess, Sv2Error> {
+        let peer = self.stream.get_ref().peer_addr()?;
+        let setup = SetupConnection::mining(&peer.ip().to_string(), peer.port(), vendor);
+        self.stream.send(&Message::SetupConnection(setup))?;
+
+        match self.stream.recv()? {
+            Message::SetupConnectionSuccess(success) => Ok(success),
+            Message::SetupConnectionError(err) => Err(Sv2Error::SetupRejected(err.error_code)),
+            other => Err(Sv2Error::UnexpectedMessage(other.msg_type())),
+        }
+    }
+
+    /// Open a standard channel for `user_identity`.
+    pub fn open_standard_channel(
+        &mut self,
+        user_identity: &str,
+        nominal_hash_rate: f32,
+    ) -> Result<&StandardChannel, Sv2Error> {
+        let request_id = self.next_request_id;
+        self.next_request_id += 1;
+
+        self.stream.send(&Message::OpenStandardMiningChannel(OpenStandardMiningChannel {
+            request_id,
+            user_identity: user_identity.to_string(),
+            nominal_hash_rate,
+            max_target: [0xFF; 32],
+        }))?;
+
+        loop {
+            match self.stream.recv()? {
+                Message::OpenStandardMiningChannelSuccess(success) if success.request_id == request_id => {
+                    return Ok(self.channel.insert(StandardChannel::open(&success)));
+                }
+                Message::OpenMiningChannelError(err) if err.request_id == request_id => {
+                    return Err(Sv2Error::ChannelRejected(err.error_code));
+                }
+                // Jobs may race ahead of the channel confirmation on other channels
+                Message::NewMiningJob(_) | Message::SetNewPrevHash(_) | Message::SetTarget(_) => {}
+                other => return Err(Sv2Error::UnexpectedMessage(other.msg_type())),
+            }
+        }
+    }
+
+    /// Block until the pool provides new work for the channel.
+    pub fn next_work(&mut self) -> Result<Work, Sv2Error> {
+        loop {
+            let message = self.stream.recv()?;
+            let channel = self.channel.as_mut().ok_or(Sv2Error::UnexpectedMessage(message.msg_type()))?;
+            if let Some(work) = channel.handle(&message) {
+                return Ok(work);
+            }
+        }
+    }
+
+    /// Submit a share and wait for the pool's verdict.
+    ///
+    /// Returns `Ok(false)` when the pool rejects the share. Work updates
+    /// that arrive while waiting are applied to the channel.
+    pub fn submit(&mut self, work: &Work, nonce: u32) -> Result<bool, Sv2Error> {
+        let channel = self.channel.as_mut().ok_or(Sv2Error::ChannelRejected("no channel".into()))?;
+        let share = channel.share(work, nonce);
+        let sequence_number = share.sequence_number;
+        self.stream.send(&Message::SubmitSharesStandard(share))?;
+
+        loop {
+            let message = self.stream.recv()?;
+            match &message {
+                Message::SubmitSharesSuccess(ok) if ok.last_sequence_number >= sequence_number => {
+                    return Ok(true);
+                }
+                Message::SubmitSharesError(err) if err.sequence_number == sequence_number => {
+                    return Ok(false);
+                }
+                _ => {
+                    if let Some(channel) = self.channel.as_mut() {
+                        channel.handle(&message);
+                    }
+                }
+            }
+        }
+    }
+
+    /// The open channel, if any.
+    pub fn channel(&self) -> Option<&StandardChannel> {
+        self.channel.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use secp256k1::{Keypair, Secp256k1, SecretKey};
+    use crate::difficulty::hash_meets_target;
+    use crate::hash::{double_sha256, mine_batch, reverse_bytes};
+    use super::super::messages::{
+        NewMiningJob, OpenStandardMiningChannelSuccess, SetNewPrevHash, SubmitSharesError,
+        SubmitSharesSuccess,
+    };
+    use super::super::noise::{Responder, SignatureNoiseMessage};
+
+    /// Minimal pool: one channel, one job, verifies a single share.
+    fn run_pool(listener: TcpListener, responder: Responder, share_target_le: [u8; 32]) -> bool {
+        let (stream, _) = listener.accept().unwrap();
+        let mut conn = NoiseStream::accept(stream, responder).unwrap();
+
+        let Message::SetupConnection(setup) = conn.recv().unwrap() else { panic!("expected setup") };
+        assert_eq!(setup.protocol, 0);
+        conn.send(&Message::SetupConnectionSuccess(SetupConnectionSuccess { used_version: 2, flags: 0 }))
+            .unwrap();
+
+        let Message::OpenStandardMiningChannel(open) = conn.recv().unwrap() else { panic!("expected open") };
+        assert_eq!(open.user_identity, "goblin.rig1");
+        conn.send(&Message::OpenStandardMiningChannelSuccess(OpenStandardMiningChannelSuccess {
+            request_id: open.request_id,
+            channel_id: 1,
+            target: share_target_le,
+            extranonce_prefix: alloc::vec![0x01, 0x02, 0x03, 0x04],
+            group_channel_id: 0,
+        }))
+        .unwrap();
+
+        let job = NewMiningJob {
+            channel_id: 1,
+            job_id: 7,
+            min_ntime: None,
+            version: 0x2000_0000,
+            merkle_root: [0x4D; 32],
+        };
+        let prev = SetNewPrevHash {
+            channel_id: 1,
+            job_id: 7,
+            prev_hash: [0x6A; 32],
+            min_ntime: 1_700_000_000,
+            nbits: 0x1d00ffff,
+        };
+        conn.send(&Message::NewMiningJob(job.clone())).unwrap();
+        conn.send(&Message::SetNewPrevHash(prev.clone())).unwrap();
+
+        let Message::SubmitSharesStandard(share) = conn.recv().unwrap() else { panic!("expected share") };
+        let mut header = Vec::with_capacity(80);
+        header.extend_from_slice(&share.version.to_le_bytes());
+        header.extend_from_slice(&prev.prev_hash);
+        header.extend_from_slice(&job.merkle_root);
+        header.extend_from_slice(&share.ntime.to_le_bytes());
+        header.extend_from_slice(&prev.nbits.to_le_bytes());
+        header.extend_from_slice(&share.nonce.to_le_bytes());
+        let hash = reverse_bytes(&double_sha256(&header));
+
+        let accepted = share.job_id == 7 && hash_meets_target(&hash, &reverse_bytes(&share_target_le));
+        let reply = if accepted {
+            Message::SubmitSharesSuccess(SubmitSharesSuccess {
+                channel_id: 1,
+                last_sequence_number: share.sequence_number,
+                new_submits_accepted_count: 1,
+                new_shares_sum: 1,
+            })
+        } else {
+            Message::SubmitSharesError(SubmitSharesError {
+                channel_id: 1,
+                sequence_number: share.sequence_number,
+                error_code: "invalid-share".into(),
+            })
+        };
+        conn.send(&reply).unwrap();
+        accepted
+    }
+
+    #[test]
+    fn test_client_against_loopback_pool() {
+        let secp = Secp256k1::new();
+        let authority = Keypair::from_seckey_slice(&secp, &[0x11; 32]).unwrap();
+        let static_key = SecretKey::from_slice(&[0x22; 32]).unwrap();
+        let certificate = SignatureNoiseMessage::sign(
+            &authority,
+            &static_key.x_only_public_key(&secp).0,
+            0,
+            u32::MAX,
+        );
+        let responder = Responder::new(static_key, certificate);
+
+        // Share target 0x0000ffff...: about 1 in 65536 hashes
+        let mut share_target_le = [0xFF; 32];
+        share_target_le[31] = 0x00;
+        share_target_le[30] = 0x00;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let pool = std::thread::spawn(move || run_pool(listener, responder, share_target_le));
+
+        let mut client = Sv2Client::connect(addr, authority.x_only_public_key().0).unwrap();
+        client.setup_connection("goblin-test").unwrap();
+        let channel = client.open_standard_channel("goblin.rig1", 1.0e6).unwrap();
+        assert_eq!(channel.extranonce_prefix, alloc::vec![0x01, 0x02, 0x03, 0x04]);
+
+        let work = client.next_work().unwrap();
+        assert_eq!(work.job_id, 7);
+        assert_eq!(work.header.prev_block_hash, [0x6A; 32]);
+
+        let result = mine_batch(&work.header.serialize_without_nonce(), &work.target, &[0u8; 32], 0, 2_000_000);
+        assert!(result.block_found);
+
+        assert!(client.submit(&work, result.nonce.unwrap()).unwrap());
+        assert!(pool.join().unwrap());
+    }
+
+    #[test]
+    fn test_client_rejects_unknown_pool_key() {
+        let secp = Secp256k1::new();
+        let authority = Keypair::from_seckey_slice(&secp, &[0x11; 32]).unwrap();
+        let expected = Keypair::from_seckey_slice(&secp, &[0x33; 32]).unwrap();
+        let static_key = SecretKey::from_slice(&[0x22; 32]).unwrap();
+        let certificate = SignatureNoiseMessage::sign(
+            &authority,
+            &static_key.x_only_public_key(&secp).0,
+            0,
+            u32::MAX,
+        );
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let pool = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            NoiseStream::accept(stream, Responder::new(static_key, certificate)).is_ok()
+        });
+
+        let result = Sv2Client::connect(addr, expected.x_only_public_key().0);
+        assert!(matches!(result, Err(Sv2Error::Handshake(_))));
+        assert!(pool.join().unwrap());
+    }
+}
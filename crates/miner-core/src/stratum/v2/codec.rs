@@ -0,0 +1,314 @@
+//! Binary encoding of Stratum V2 data types and frames.
+//!
+//! All integers are little-endian. Variable-length fields carry a length
+//! prefix whose width is part of the type name (`STR0_255` has a 1-byte
+//! length, `B0_64K` a 2-byte length, and so on).
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use super::Sv2Error;
+
+/// Size of a plaintext frame header.
+pub const FRAME_HEADER_SIZE: usize = 6;
+
+/// Largest payload a frame header can announce (a 24-bit length).
+pub const MAX_MESSAGE_SIZE: usize = 0xFF_FFFF;
+
+/// Bit in `extension_type` marking a message addressed to a channel.
+pub const CHANNEL_MSG_BIT: u16 = 0x8000;
+
+/// Header preceding every Stratum V2 message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameHeader {
+    /// Extension type (0 for the base protocol), plus the channel bit.
+    pub extension_type: u16,
+    /// Message type within the extension.
+    pub msg_type: u8,
+    /// Payload length in bytes (24-bit).
+    pub msg_length: u32,
+}
+
+impl FrameHeader {
+    /// Serialize the header to 6 bytes.
+    pub fn serialize(&self) -> [u8; FRAME_HEADER_SIZE] {
+        let mut header = [0u8; FRAME_HEADER_SIZE];
+        header[0..2].copy_from_slice(&self.extension_type.to_le_bytes());
+        header[2] = self.msg_type;
+        header[3..6].copy_from_slice(&self.msg_length.to_le_bytes()[..3]);
+        header
+    }
+
+    /// Parse a 6-byte header.
+    pub fn parse(bytes: &[u8; FRAME_HEADER_SIZE]) -> Self {
+        FrameHeader {
+            extension_type: u16::from_le_bytes([bytes[0], bytes[1]]),
+            msg_type: bytes[2],
+            msg_length: u32::from_le_bytes([bytes[3], bytes[4], bytes[5], 0]),
+        }
+    }
+
+    /// Whether the message is addressed to a specific channel.
+    pub fn is_channel_msg(&self) -> bool {
+        self.extension_type & CHANNEL_MSG_BIT != 0
+    }
+}
+
+/// Writer for Stratum V2 payloads.
+#[derive(Debug, Default)]
+pub struct Encoder {
+    buf: Vec<u8>,
+}
+
+impl Encoder {
+    /// Create an empty encoder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Consume the encoder and return the payload.
+    pub fn finish(self) -> Vec<u8> {
+        self.buf
+    }
+
+    /// Write a `U8`.
+    pub fn u8(&mut self, value: u8) {
+        self.buf.push(value);
+    }
+
+    /// Write a `BOOL`.
+    pub fn bool(&mut self, value: bool) {
+        self.buf.push(value as u8);
+    }
+
+    /// Write a `U16`.
+    pub fn u16(&mut self, value: u16) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    /// Write a `U32`.
+    pub fn u32(&mut self, value: u32) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    /// Write a `U64`.
+    pub fn u64(&mut self, value: u64) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    /// Write a `F32`.
+    pub fn f32(&mut self, value: f32) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    /// Write a `U256`.
+    pub fn u256(&mut self, value: &[u8; 32]) {
+        self.buf.extend_from_slice(value);
+    }
+
+    /// `OPTION[U32]`: a presence byte followed by the value.
+    pub fn option_u32(&mut self, value: Option<u32>) {
+        match value {
+            Some(v) => {
+                self.buf.push(1);
+                self.u32(v);
+            }
+            None => self.buf.push(0),
+        }
+    }
+
+    /// `STR0_255`: 1-byte length and UTF-8 bytes.
+    pub fn str0_255(&mut self, value: &str, name: &'static str) -> Result<(), Sv2Error> {
+        self.bytes_with_u8_len(value.as_bytes(), 255, name)
+    }
+
+    /// `B0_32`: 1-byte length and up to 32 bytes.
+    pub fn b0_32(&mut self, value: &[u8], name: &'static str) -> Result<(), Sv2Error> {
+        self.bytes_with_u8_len(value, 32, name)
+    }
+
+    /// `B0_255`: 1-byte length and up to 255 bytes.
+    pub fn b0_255(&mut self, value: &[u8], name: &'static str) -> Result<(), Sv2Error> {
+        self.bytes_with_u8_len(value, 255, name)
+    }
+
+    /// `B0_64K`: 2-byte length and up to 65535 bytes.
+    pub fn b0_64k(&mut self, value: &[u8], name: &'static str) -> Result<(), Sv2Error> {
+        if value.len() > u16::MAX as usize {
+            return Err(Sv2Error::FieldTooLong(name));
+        }
+        self.u16(value.len() as u16);
+        self.buf.extend_from_slice(value);
+        Ok(())
+    }
+
+    fn bytes_with_u8_len(&mut self, value: &[u8], max: usize, name: &'static str) -> Result<(), Sv2Error> {
+        if value.len() > max {
+            return Err(Sv2Error::FieldTooLong(name));
+        }
+        self.buf.push(value.len() as u8);
+        self.buf.extend_from_slice(value);
+        Ok(())
+    }
+}
+
+/// Reader for Stratum V2 payloads.
+#[derive(Debug)]
+pub struct Decoder<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Decoder<'a> {
+    /// Start reading a payload.
+    pub fn new(data: &'a [u8]) -> Self {
+        Decoder { data, pos: 0 }
+    }
+
+    /// Number of bytes not yet consumed.
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], Sv2Error> {
+        if self.remaining() < n {
+            return Err(Sv2Error::Truncated);
+        }
+        let slice = &self.data[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(slice)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], Sv2Error> {
+        let mut out = [0u8; N];
+        out.copy_from_slice(self.take(N)?);
+        Ok(out)
+    }
+
+    /// Read a `U8`.
+    pub fn u8(&mut self) -> Result<u8, Sv2Error> {
+        Ok(self.take(1)?[0])
+    }
+
+    /// Read a `BOOL`.
+    pub fn bool(&mut self) -> Result<bool, Sv2Error> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(Sv2Error::InvalidFrame("invalid bool".into())),
+        }
+    }
+
+    /// Read a `U16`.
+    pub fn u16(&mut self) -> Result<u16, Sv2Error> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    /// Read a `U32`.
+    pub fn u32(&mut self) -> Result<u32, Sv2Error> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    /// Read a `U64`.
+    pub fn u64(&mut self) -> Result<u64, Sv2Error> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    /// Read a `F32`.
+    pub fn f32(&mut self) -> Result<f32, Sv2Error> {
+        Ok(f32::from_le_bytes(self.array()?))
+    }
+
+    /// Read a `U256`.
+    pub fn u256(&mut self) -> Result<[u8; 32], Sv2Error> {
+        self.array()
+    }
+
+    /// Read a `OPTION[U32]`.
+    pub fn option_u32(&mut self) -> Result<Option<u32>, Sv2Error> {
+        match self.u8()? {
+            0 => Ok(None),
+            1 => Ok(Some(self.u32()?)),
+            _ => Err(Sv2Error::InvalidFrame("invalid option tag".into())),
+        }
+    }
+
+    /// Read a `STR0_255`.
+    pub fn str0_255(&mut self) -> Result<String, Sv2Error> {
+        let len = self.u8()? as usize;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec())
+            .map_err(|_| Sv2Error::InvalidFrame("string is not UTF-8".into()))
+    }
+
+    /// Read a `B0_32`.
+    pub fn b0_32(&mut self) -> Result<Vec<u8>, Sv2Error> {
+        let len = self.u8()? as usize;
+        if len > 32 {
+            return Err(Sv2Error::InvalidFrame("B0_32 longer than 32 bytes".into()));
+        }
+        Ok(self.take(len)?.to_vec())
+    }
+
+    /// Read a `B0_255`.
+    pub fn b0_255(&mut self) -> Result<Vec<u8>, Sv2Error> {
+        let len = self.u8()? as usize;
+        Ok(self.take(len)?.to_vec())
+    }
+
+    /// Read a `B0_64K`.
+    pub fn b0_64k(&mut self) -> Result<Vec<u8>, Sv2Error> {
+        let len = self.u16()? as usize;
+        Ok(self.take(len)?.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_header_roundtrip() {
+        let header = FrameHeader {
+            extension_type: CHANNEL_MSG_BIT,
+            msg_type: 0x15,
+            msg_length: 0x012345,
+        };
+        let bytes = header.serialize();
+        assert_eq!(bytes, [0x00, 0x80, 0x15, 0x45, 0x23, 0x01]);
+
+        let parsed = FrameHeader::parse(&bytes);
+        assert_eq!(parsed, header);
+        assert!(parsed.is_channel_msg());
+    }
+
+    #[test]
+    fn test_primitive_roundtrip() {
+        let mut encoder = Encoder::new();
+        encoder.u16(0xBEEF);
+        encoder.u32(0xDEADBEEF);
+        encoder.option_u32(Some(7));
+        encoder.option_u32(None);
+        encoder.str0_255("goblin", "name").unwrap();
+        encoder.b0_32(&[1, 2, 3], "prefix").unwrap();
+        let payload = encoder.finish();
+
+        let mut decoder = Decoder::new(&payload);
+        assert_eq!(decoder.u16().unwrap(), 0xBEEF);
+        assert_eq!(decoder.u32().unwrap(), 0xDEADBEEF);
+        assert_eq!(decoder.option_u32().unwrap(), Some(7));
+        assert_eq!(decoder.option_u32().unwrap(), None);
+        assert_eq!(decoder.str0_255().unwrap(), "goblin");
+        assert_eq!(decoder.b0_32().unwrap(), alloc::vec![1, 2, 3]);
+        assert_eq!(decoder.remaining(), 0);
+        assert!(matches!(decoder.u8(), Err(Sv2Error::Truncated)));
+    }
+
+    #[test]
+    fn test_field_length_limits() {
+        let mut encoder = Encoder::new();
+        assert!(matches!(
+            encoder.b0_32(&[0u8; 33], "prefix"),
+            Err(Sv2Error::FieldTooLong("prefix"))
+        ));
+    }
+}
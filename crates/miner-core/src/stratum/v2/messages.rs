@@ -0,0 +1,528 @@
+//! Stratum V2 messages used by standard mining channels.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use super::codec::{Decoder, Encoder, FrameHeader, CHANNEL_MSG_BIT, FRAME_HEADER_SIZE, MAX_MESSAGE_SIZE};
+use super::Sv2Error;
+
+/// `SetupConnection.protocol` value for the mining protocol.
+pub const MINING_PROTOCOL: u8 = 0;
+
+/// Protocol version spoken by this implementation.
+pub const PROTOCOL_VERSION: u16 = 2;
+
+/// `SetupConnection` flag: the device only supports standard (header-only) jobs.
+pub const REQUIRES_STANDARD_JOBS: u32 = 0x01;
+
+/// `SetupConnection` flag: the device can roll the version field.
+pub const REQUIRES_VERSION_ROLLING: u32 = 0x04;
+
+// Message types for the common and mining sub-protocols
+pub const SETUP_CONNECTION: u8 = 0x00;
+pub const SETUP_CONNECTION_SUCCESS: u8 = 0x01;
+pub const SETUP_CONNECTION_ERROR: u8 = 0x02;
+pub const OPEN_STANDARD_MINING_CHANNEL: u8 = 0x10;
+pub const OPEN_STANDARD_MINING_CHANNEL_SUCCESS: u8 = 0x11;
+pub const OPEN_MINING_CHANNEL_ERROR: u8 = 0x12;
+pub const NEW_MINING_JOB: u8 = 0x15;
+pub const SUBMIT_SHARES_STANDARD: u8 = 0x1a;
+pub const SUBMIT_SHARES_SUCCESS: u8 = 0x1c;
+pub const SUBMIT_SHARES_ERROR: u8 = 0x1d;
+pub const SET_NEW_PREV_HASH: u8 = 0x20;
+pub const SET_TARGET: u8 = 0x21;
+
+/// First message on a connection, sent by the miner.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetupConnection {
+    pub protocol: u8,
+    pub min_version: u16,
+    pub max_version: u16,
+    pub flags: u32,
+    pub endpoint_host: String,
+    pub endpoint_port: u16,
+    pub vendor: String,
+    pub hardware_version: String,
+    pub firmware: String,
+    pub device_id: String,
+}
+
+impl SetupConnection {
+    /// A mining-protocol setup request for a header-only CPU miner.
+    pub fn mining(endpoint_host: &str, endpoint_port: u16, vendor: &str) -> Self {
+        SetupConnection {
+            protocol: MINING_PROTOCOL,
+            min_version: PROTOCOL_VERSION,
+            max_version: PROTOCOL_VERSION,
+            flags: REQUIRES_STANDARD_JOBS,
+            endpoint_host: endpoint_host.into(),
+            endpoint_port,
+            vendor: vendor.into(),
+            hardware_version: String::new(),
+            firmware: String::new(),
+            device_id: String::new(),
+        }
+    }
+}
+
+/// Pool accepted the connection setup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetupConnectionSuccess {
+    pub used_version: u16,
+    pub flags: u32,
+}
+
+/// Pool rejected the connection setup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetupConnectionError {
+    pub flags: u32,
+    pub error_code: String,
+}
+
+/// Request a standard (header-only) channel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpenStandardMiningChannel {
+    pub request_id: u32,
+    pub user_identity: String,
+    /// Expected hash rate in H/s, used by the pool to pick a target.
+    pub nominal_hash_rate: f32,
+    /// Largest target the miner accepts (little-endian 256-bit).
+    pub max_target: [u8; 32],
+}
+
+/// Pool opened the requested channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenStandardMiningChannelSuccess {
+    pub request_id: u32,
+    pub channel_id: u32,
+    /// Initial share target (little-endian 256-bit).
+    pub target: [u8; 32],
+    pub extranonce_prefix: Vec<u8>,
+    pub group_channel_id: u32,
+}
+
+/// Pool refused to open a channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenMiningChannelError {
+    pub request_id: u32,
+    pub error_code: String,
+}
+
+/// New header-only job for a standard channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewMiningJob {
+    pub channel_id: u32,
+    pub job_id: u32,
+    /// `None` marks a future job, activated by a later `SetNewPrevHash`.
+    pub min_ntime: Option<u32>,
+    pub version: u32,
+    pub merkle_root: [u8; 32],
+}
+
+/// New chain tip for a channel; activates the future job with `job_id`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetNewPrevHash {
+    pub channel_id: u32,
+    pub job_id: u32,
+    /// Previous block hash (internal byte order).
+    pub prev_hash: [u8; 32],
+    pub min_ntime: u32,
+    pub nbits: u32,
+}
+
+/// A share found on a standard channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubmitSharesStandard {
+    pub channel_id: u32,
+    pub sequence_number: u32,
+    pub job_id: u32,
+    pub nonce: u32,
+    pub ntime: u32,
+    pub version: u32,
+}
+
+/// Pool acknowledged shares up to `last_sequence_number`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubmitSharesSuccess {
+    pub channel_id: u32,
+    pub last_sequence_number: u32,
+    pub new_submits_accepted_count: u32,
+    pub new_shares_sum: u64,
+}
+
+/// Pool rejected a share.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubmitSharesError {
+    pub channel_id: u32,
+    pub sequence_number: u32,
+    pub error_code: String,
+}
+
+/// New share target for a channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetTarget {
+    pub channel_id: u32,
+    /// Largest accepted share hash (little-endian 256-bit).
+    pub maximum_target: [u8; 32],
+}
+
+/// A Stratum V2 message supported by this implementation.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    SetupConnection(SetupConnection),
+    SetupConnectionSuccess(SetupConnectionSuccess),
+    SetupConnectionError(SetupConnectionError),
+    OpenStandardMiningChannel(OpenStandardMiningChannel),
+    OpenStandardMiningChannelSuccess(OpenStandardMiningChannelSuccess),
+    OpenMiningChannelError(OpenMiningChannelError),
+    NewMiningJob(NewMiningJob),
+    SetNewPrevHash(SetNewPrevHash),
+    SubmitSharesStandard(SubmitSharesStandard),
+    SubmitSharesSuccess(SubmitSharesSuccess),
+    SubmitSharesError(SubmitSharesError),
+    SetTarget(SetTarget),
+}
+
+impl Message {
+    /// The message type byte.
+    pub fn msg_type(&self) -> u8 {
+        match self {
+            Message::SetupConnection(_) => SETUP_CONNECTION,
+            Message::SetupConnectionSuccess(_) => SETUP_CONNECTION_SUCCESS,
+            Message::SetupConnectionError(_) => SETUP_CONNECTION_ERROR,
+            Message::OpenStandardMiningChannel(_) => OPEN_STANDARD_MINING_CHANNEL,
+            Message::OpenStandardMiningChannelSuccess(_) => OPEN_STANDARD_MINING_CHANNEL_SUCCESS,
+            Message::OpenMiningChannelError(_) => OPEN_MINING_CHANNEL_ERROR,
+            Message::NewMiningJob(_) => NEW_MINING_JOB,
+            Message::SetNewPrevHash(_) => SET_NEW_PREV_HASH,
+            Message::SubmitSharesStandard(_) => SUBMIT_SHARES_STANDARD,
+            Message::SubmitSharesSuccess(_) => SUBMIT_SHARES_SUCCESS,
+            Message::SubmitSharesError(_) => SUBMIT_SHARES_ERROR,
+            Message::SetTarget(_) => SET_TARGET,
+        }
+    }
+
+    /// Whether the message is addressed to a channel.
+    pub fn is_channel_msg(&self) -> bool {
+        matches!(
+            self,
+            Message::NewMiningJob(_)
+                | Message::SetNewPrevHash(_)
+                | Message::SubmitSharesStandard(_)
+                | Message::SubmitSharesSuccess(_)
+                | Message::SubmitSharesError(_)
+                | Message::SetTarget(_)
+        )
+    }
+
+    /// Encode the message payload.
+    pub fn encode_payload(&self) -> Result<Vec<u8>, Sv2Error> {
+        let mut e = Encoder::new();
+        match self {
+            Message::SetupConnection(m) => {
+                e.u8(m.protocol);
+                e.u16(m.min_version);
+                e.u16(m.max_version);
+                e.u32(m.flags);
+                e.str0_255(&m.endpoint_host, "endpoint_host")?;
+                e.u16(m.endpoint_port);
+                e.str0_255(&m.vendor, "vendor")?;
+                e.str0_255(&m.hardware_version, "hardware_version")?;
+                e.str0_255(&m.firmware, "firmware")?;
+                e.str0_255(&m.device_id, "device_id")?;
+            }
+            Message::SetupConnectionSuccess(m) => {
+                e.u16(m.used_version);
+                e.u32(m.flags);
+            }
+            Message::SetupConnectionError(m) => {
+                e.u32(m.flags);
+                e.str0_255(&m.error_code, "error_code")?;
+            }
+            Message::OpenStandardMiningChannel(m) => {
+                e.u32(m.request_id);
+                e.str0_255(&m.user_identity, "user_identity")?;
+                e.f32(m.nominal_hash_rate);
+                e.u256(&m.max_target);
+            }
+            Message::OpenStandardMiningChannelSuccess(m) => {
+                e.u32(m.request_id);
+                e.u32(m.channel_id);
+                e.u256(&m.target);
+                e.b0_32(&m.extranonce_prefix, "extranonce_prefix")?;
+                e.u32(m.group_channel_id);
+            }
+            Message::OpenMiningChannelError(m) => {
+                e.u32(m.request_id);
+                e.str0_255(&m.error_code, "error_code")?;
+            }
+            Message::NewMiningJob(m) => {
+                e.u32(m.channel_id);
+                e.u32(m.job_id);
+                e.option_u32(m.min_ntime);
+                e.u32(m.version);
+                e.b0_32(&m.merkle_root, "merkle_root")?;
+            }
+            Message::SetNewPrevHash(m) => {
+                e.u32(m.channel_id);
+                e.u32(m.job_id);
+                e.u256(&m.prev_hash);
+                e.u32(m.min_ntime);
+                e.u32(m.nbits);
+            }
+            Message::SubmitSharesStandard(m) => {
+                e.u32(m.channel_id);
+                e.u32(m.sequence_number);
+                e.u32(m.job_id);
+                e.u32(m.nonce);
+                e.u32(m.ntime);
+                e.u32(m.version);
+            }
+            Message::SubmitSharesSuccess(m) => {
+                e.u32(m.channel_id);
+                e.u32(m.last_sequence_number);
+                e.u32(m.new_submits_accepted_count);
+                e.u64(m.new_shares_sum);
+            }
+            Message::SubmitSharesError(m) => {
+                e.u32(m.channel_id);
+                e.u32(m.sequence_number);
+                e.str0_255(&m.error_code, "error_code")?;
+            }
+            Message::SetTarget(m) => {
+                e.u32(m.channel_id);
+                e.u256(&m.maximum_target);
+            }
+        }
+        Ok(e.finish())
+    }
+
+    /// Encode the message as a plaintext frame (header and payload).
+    pub fn to_frame(&self) -> Result<Vec<u8>, Sv2Error> {
+        let payload = self.encode_payload()?;
+        if payload.len() > MAX_MESSAGE_SIZE {
+            return Err(Sv2Error::FieldTooLong("payload"));
+        }
+
+        let header = FrameHeader {
+            extension_type: if self.is_channel_msg() { CHANNEL_MSG_BIT } else { 0 },
+            msg_type: self.msg_type(),
+            msg_length: payload.len() as u32,
+        };
+
+        let mut frame = Vec::with_capacity(FRAME_HEADER_SIZE + payload.len());
+        frame.extend_from_slice(&header.serialize());
+        frame.extend_from_slice(&payload);
+        Ok(frame)
+    }
+
+    /// Decode a message from its frame header and payload.
+    pub fn decode(header: &FrameHeader, payload: &[u8]) -> Result<Self, Sv2Error> {
+        if header.extension_type & !CHANNEL_MSG_BIT != 0 {
+            return Err(Sv2Error::UnknownMessage(header.msg_type));
+        }
+
+        let mut d = Decoder::new(payload);
+        let message = match header.msg_type {
+            SETUP_CONNECTION => Message::SetupConnection(SetupConnection {
+                protocol: d.u8()?,
+                min_version: d.u16()?,
+                max_version: d.u16()?,
+                flags: d.u32()?,
+                endpoint_host: d.str0_255()?,
+                endpoint_port: d.u16()?,
+                vendor: d.str0_255()?,
+                hardware_version: d.str0_255()?,
+                firmware: d.str0_255()?,
+                device_id: d.str0_255()?,
+            }),
+            SETUP_CONNECTION_SUCCESS => Message::SetupConnectionSuccess(SetupConnectionSuccess {
+                used_version: d.u16()?,
+                flags: d.u32()?,
+            }),
+            SETUP_CONNECTION_ERROR => Message::SetupConnectionError(SetupConnectionError {
+                flags: d.u32()?,
+                error_code: d.str0_255()?,
+            }),
+            OPEN_STANDARD_MINING_CHANNEL => {
+                Message::OpenStandardMiningChannel(OpenStandardMiningChannel {
+                    request_id: d.u32()?,
+                    user_identity: d.str0_255()?,
+                    nominal_hash_rate: d.f32()?,
+                    max_target: d.u256()?,
+                })
+            }
+            OPEN_STANDARD_MINING_CHANNEL_SUCCESS => {
+                Message::OpenStandardMiningChannelSuccess(OpenStandardMiningChannelSuccess {
+                    request_id: d.u32()?,
+                    channel_id: d.u32()?,
+                    target: d.u256()?,
+                    extranonce_prefix: d.b0_32()?,
+                    group_channel_id: d.u32()?,
+                })
+            }
+            OPEN_MINING_CHANNEL_ERROR => Message::OpenMiningChannelError(OpenMiningChannelError {
+                request_id: d.u32()?,
+                error_code: d.str0_255()?,
+            }),
+            NEW_MINING_JOB => Message::NewMiningJob(NewMiningJob {
+                channel_id: d.u32()?,
+                job_id: d.u32()?,
+                min_ntime: d.option_u32()?,
+                version: d.u32()?,
+                merkle_root: {
+                    let root = d.b0_32()?;
+                    if root.len() != 32 {
+                        return Err(Sv2Error::InvalidFrame("merkle root must be 32 bytes".into()));
+                    }
+                    let mut merkle_root = [0u8; 32];
+                    merkle_root.copy_from_slice(&root);
+                    merkle_root
+                },
+            }),
+            SET_NEW_PREV_HASH => Message::SetNewPrevHash(SetNewPrevHash {
+                channel_id: d.u32()?,
+                job_id: d.u32()?,
+                prev_hash: d.u256()?,
+                min_ntime: d.u32()?,
+                nbits: d.u32()?,
+            }),
+            SUBMIT_SHARES_STANDARD => Message::SubmitSharesStandard(SubmitSharesStandard {
+                channel_id: d.u32()?,
+                sequence_number: d.u32()?,
+                job_id: d.u32()?,
+                nonce: d.u32()?,
+                ntime: d.u32()?,
+                version: d.u32()?,
+            }),
+            SUBMIT_SHARES_SUCCESS => Message::SubmitSharesSuccess(SubmitSharesSuccess {
+                channel_id: d.u32()?,
+                last_sequence_number: d.u32()?,
+                new_submits_accepted_count: d.u32()?,
+                new_shares_sum: d.u64()?,
+            }),
+            SUBMIT_SHARES_ERROR => Message::SubmitSharesError(SubmitSharesError {
+                channel_id: d.u32()?,
+                sequence_number: d.u32()?,
+                error_code: d.str0_255()?,
+            }),
+            SET_TARGET => Message::SetTarget(SetTarget {
+                channel_id: d.u32()?,
+                maximum_target: d.u256()?,
+            }),
+            other => return Err(Sv2Error::UnknownMessage(other)),
+        };
+
+        if d.remaining() != 0 {
+            return Err(Sv2Error::InvalidFrame("trailing bytes after message".into()));
+        }
+        Ok(message)
+    }
+
+    /// Decode a complete plaintext frame.
+    pub fn from_frame(frame: &[u8]) -> Result<Self, Sv2Error> {
+        if frame.len() < FRAME_HEADER_SIZE {
+            return Err(Sv2Error::Truncated);
+        }
+        let mut header_bytes = [0u8; FRAME_HEADER_SIZE];
+        header_bytes.copy_from_slice(&frame[..FRAME_HEADER_SIZE]);
+        let header = FrameHeader::parse(&header_bytes);
+
+        let payload = &frame[FRAME_HEADER_SIZE..];
+        if payload.len() != header.msg_length as usize {
+            return Err(Sv2Error::InvalidFrame("length does not match header".into()));
+        }
+        Self::decode(&header, payload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(message: Message) {
+        let frame = message.to_frame().unwrap();
+        assert_eq!(Message::from_frame(&frame).unwrap(), message);
+    }
+
+    #[test]
+    fn test_setup_connection_encoding() {
+        let setup = SetupConnection::mining("pool.example", 3336, "goblin");
+        let frame = Message::SetupConnection(setup.clone()).to_frame().unwrap();
+
+        // Header: extension 0, type 0x00, length
+        assert_eq!(&frame[..3], &[0x00, 0x00, 0x00]);
+        let payload_len = u32::from_le_bytes([frame[3], frame[4], frame[5], 0]) as usize;
+        assert_eq!(payload_len, frame.len() - FRAME_HEADER_SIZE);
+
+        // protocol, min/max version, flags, then "pool.example"
+        assert_eq!(&frame[6..15], &[0x00, 0x02, 0x00, 0x02, 0x00, 0x01, 0x00, 0x00, 0x00]);
+        assert_eq!(frame[15], 12);
+        assert_eq!(&frame[16..28], b"pool.example");
+
+        roundtrip(Message::SetupConnection(setup));
+    }
+
+    #[test]
+    fn test_mining_message_roundtrips() {
+        roundtrip(Message::OpenStandardMiningChannel(OpenStandardMiningChannel {
+            request_id: 1,
+            user_identity: "goblin.worker".into(),
+            nominal_hash_rate: 1.5e6,
+            max_target: [0xFF; 32],
+        }));
+        roundtrip(Message::OpenStandardMiningChannelSuccess(OpenStandardMiningChannelSuccess {
+            request_id: 1,
+            channel_id: 7,
+            target: [0x11; 32],
+            extranonce_prefix: alloc::vec![1, 2, 3, 4],
+            group_channel_id: 0,
+        }));
+        roundtrip(Message::NewMiningJob(NewMiningJob {
+            channel_id: 7,
+            job_id: 3,
+            min_ntime: None,
+            version: 0x2000_0000,
+            merkle_root: [0x22; 32],
+        }));
+        roundtrip(Message::SetNewPrevHash(SetNewPrevHash {
+            channel_id: 7,
+            job_id: 3,
+            prev_hash: [0x33; 32],
+            min_ntime: 1_700_000_000,
+            nbits: 0x1d00ffff,
+        }));
+        roundtrip(Message::SubmitSharesStandard(SubmitSharesStandard {
+            channel_id: 7,
+            sequence_number: 0,
+            job_id: 3,
+            nonce: 0xDEADBEEF,
+            ntime: 1_700_000_000,
+            version: 0x2000_0000,
+        }));
+        roundtrip(Message::SubmitSharesError(SubmitSharesError {
+            channel_id: 7,
+            sequence_number: 1,
+            error_code: "difficulty-too-low".into(),
+        }));
+    }
+
+    #[test]
+    fn test_channel_bit_and_unknown_types() {
+        let job = Message::NewMiningJob(NewMiningJob {
+            channel_id: 1,
+            job_id: 1,
+            min_ntime: Some(0),
+            version: 0,
+            merkle_root: [0; 32],
+        });
+        let frame = job.to_frame().unwrap();
+        assert_eq!(&frame[..3], &[0x00, 0x80, NEW_MINING_JOB]);
+
+        let unknown = [0x00, 0x00, 0x7F, 0x00, 0x00, 0x00];
+        assert!(matches!(Message::from_frame(&unknown), Err(Sv2Error::UnknownMessage(0x7F))));
+
+        let mut truncated = frame.clone();
+        truncated.pop();
+        assert!(Message::from_frame(&truncated).is_err());
+    }
+}
@@ -0,0 +1,83 @@
+//! Stratum V2 mining protocol (standard channels).
+//!
+//! Stratum V2 is a binary protocol: every message travels in a 6-byte frame
+//! header followed by a little-endian payload. On the wire, frames are
+//! encrypted with the `Noise_NX_Secp256k1+EllSwift_ChaChaPoly_SHA256`
+//! handshake, which also authenticates the pool against its authority key.
+//!
+//! Standard channels are header-only: the pool sends the merkle root
+//! directly, so a job plus the current previous hash is enough to build a
+//! `BlockHeader` and feed it to `mine_batch`.
+
+pub mod channel;
+pub mod codec;
+pub mod messages;
+
+#[cfg(feature = "std")]
+pub mod client;
+#[cfg(feature = "std")]
+pub mod noise;
+
+pub use channel::{StandardChannel, Work};
+pub use messages::Message;
+
+#[cfg(feature = "std")]
+pub use client::Sv2Client;
+
+use alloc::string::String;
+
+/// Stratum V2 protocol errors.
+#[derive(Debug, Clone)]
+pub enum Sv2Error {
+    /// Transport failure (connection refused, reset, timed out)
+    Io(String),
+    /// The peer closed the connection
+    Disconnected,
+    /// A frame or field ended early
+    Truncated,
+    /// A frame or field was malformed
+    InvalidFrame(String),
+    /// A field exceeded its maximum encoded length
+    FieldTooLong(&'static str),
+    /// A message type we do not understand
+    UnknownMessage(u8),
+    /// A valid message that was not expected at this point
+    UnexpectedMessage(u8),
+    /// The Noise handshake failed
+    Handshake(String),
+    /// An encrypted frame failed authentication
+    Decrypt,
+    /// The pool rejected `SetupConnection`
+    SetupRejected(String),
+    /// The pool refused to open the channel
+    ChannelRejected(String),
+}
+
+impl core::fmt::Display for Sv2Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Sv2Error::Io(e) => write!(f, "Connection error: {}", e),
+            Sv2Error::Disconnected => write!(f, "Peer closed the connection"),
+            Sv2Error::Truncated => write!(f, "Message truncated"),
+            Sv2Error::InvalidFrame(s) => write!(f, "Invalid frame: {}", s),
+            Sv2Error::FieldTooLong(name) => write!(f, "Field too long: {}", name),
+            Sv2Error::UnknownMessage(t) => write!(f, "Unknown message type: 0x{:02x}", t),
+            Sv2Error::UnexpectedMessage(t) => write!(f, "Unexpected message type: 0x{:02x}", t),
+            Sv2Error::Handshake(s) => write!(f, "Noise handshake failed: {}", s),
+            Sv2Error::Decrypt => write!(f, "Frame failed authentication"),
+            Sv2Error::SetupRejected(code) => write!(f, "Connection setup rejected: {}", code),
+            Sv2Error::ChannelRejected(code) => write!(f, "Channel open rejected: {}", code),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for Sv2Error {
+    fn from(e: std::io::Error) -> Self {
+        if e.kind() == std::io::ErrorKind::UnexpectedEof {
+            Sv2Error::Disconnected
+        } else {
+            Sv2Error::Io(alloc::string::ToString::to_string(&e))
+        }
+    }
+}
@@ -0,0 +1,614 @@
+//! Noise NX handshake and encrypted transport for Stratum V2.
+//!
+//! The handshake is `Noise_NX_Secp256k1+EllSwift_ChaChaPoly_SHA256`:
+//!
+//! ```text
+//! -> e
+//! <- e, ee, s, es, SIGNATURE_NOISE_MESSAGE
+//! ```
+//!
+//! Public keys travel ElligatorSwift-encoded (64 bytes) and ECDH uses the
+//! BIP324 x-only hash. The responder proves its static key with a Schnorr
+//! signature from the pool's authority key, which the miner knows up front.
+//! After the handshake each frame header and each payload chunk is sealed
+//! separately with ChaCha20-Poly1305.
+
+use alloc::vec::Vec;
+use std::io::{Read, Write};
+use chacha20poly1305::aead::{Aead, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, KeyInit, Nonce};
+use hmac::{Hmac, Mac};
+use secp256k1::ellswift::{ElligatorSwift, ElligatorSwiftParty};
+use secp256k1::schnorr::Signature;
+use secp256k1::{Keypair, PublicKey, Secp256k1, SecretKey, XOnlyPublicKey};
+use sha2::{Digest, Sha256};
+use crate::hash::sha256;
+use super::codec::{FrameHeader, FRAME_HEADER_SIZE};
+use super::messages::Message;
+use super::Sv2Error;
+
+/// Noise protocol name, hashed into the initial handshake state.
+pub const PROTOCOL_NAME: &[u8] = b"Noise_NX_Secp256k1+EllSwift_ChaChaPoly_SHA256";
+
+/// Size of an ElligatorSwift-encoded public key.
+pub const ELLSWIFT_KEY_SIZE: usize = 64;
+
+/// Size of a ChaCha20-Poly1305 authentication tag.
+pub const MAC_SIZE: usize = 16;
+
+/// Size of a serialized `SIGNATURE_NOISE_MESSAGE`.
+pub const SIGNATURE_MESSAGE_SIZE: usize = 74;
+
+/// Size of the initiator's handshake message (`e`).
+pub const INITIATOR_HANDSHAKE_SIZE: usize = ELLSWIFT_KEY_SIZE;
+
+/// Size of the responder's handshake message (`e, ee, s, es, signature`).
+pub const RESPONDER_HANDSHAKE_SIZE: usize =
+    ELLSWIFT_KEY_SIZE + ELLSWIFT_KEY_SIZE + MAC_SIZE + SIGNATURE_MESSAGE_SIZE + MAC_SIZE;
+
+/// Largest encrypted chunk, including its tag.
+pub const MAX_CHUNK_SIZE: usize = 65535;
+
+/// Size of an encrypted frame header.
+pub const ENCRYPTED_HEADER_SIZE: usize = FRAME_HEADER_SIZE + MAC_SIZE;
+
+/// Largest payload accepted from the peer: one Noise chunk.
+///
+/// The frame header can announce up to 16 MiB, but every message this
+/// client decodes fits in a few kilobytes, so longer frames are refused
+/// before their buffer is allocated.
+pub const MAX_RECV_PAYLOAD_SIZE: usize = MAX_CHUNK_SIZE - MAC_SIZE;
+
+/// One direction of an established Noise session.
+pub struct CipherState {
+    cipher: ChaCha20Poly1305,
+    nonce: u64,
+}
+
+impl CipherState {
+    fn new(key: &[u8; 32]) -> Self {
+        CipherState {
+            cipher: ChaCha20Poly1305::new(key.into()),
+            nonce: 0,
+        }
+    }
+
+    fn next_nonce(&mut self) -> [u8; 12] {
+        // 32 zero bits followed by the 64-bit little-endian counter
+        let mut nonce = [0u8; 12];
+        nonce[4..].copy_from_slice(&self.nonce.to_le_bytes());
+        self.nonce += 1;
+        nonce
+    }
+
+    /// Encrypt and authenticate `plaintext`, binding `ad`.
+    pub fn encrypt(&mut self, ad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, Sv2Error> {
+        let nonce = self.next_nonce();
+        self.cipher
+            .encrypt(Nonce::from_slice(&nonce), Payload { msg: plaintext, aad: ad })
+            .map_err(|_| Sv2Error::Handshake("encryption failed".into()))
+    }
+
+    /// Authenticate and decrypt `ciphertext`, binding `ad`.
+    pub fn decrypt(&mut self, ad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, Sv2Error> {
+        let nonce = self.next_nonce();
+        self.cipher
+            .decrypt(Nonce::from_slice(&nonce), Payload { msg: ciphertext, aad: ad })
+            .map_err(|_| Sv2Error::Decrypt)
+    }
+}
+
+/// Handshake hash and chaining key, plus the cipher once keyed.
+struct SymmetricState {
+    h: [u8; 32],
+    ck: [u8; 32],
+    cipher: Option<CipherState>,
+}
+
+impl SymmetricState {
+    fn new() -> Self {
+        // The protocol name is longer than 32 bytes, so h = SHA256(name)
+        let h = sha256(PROTOCOL_NAME);
+        let mut state = SymmetricState { h, ck: h, cipher: None };
+        // Empty prologue
+        state.mix_hash(&[]);
+        state
+    }
+
+    fn mix_hash(&mut self, data: &[u8]) {
+        let mut hasher = Sha256::new();
+        hasher.update(self.h);
+        hasher.update(data);
+        self.h.copy_from_slice(&hasher.finalize());
+    }
+
+    fn mix_key(&mut self, input_key_material: &[u8]) {
+        let (ck, key) = hkdf2(&self.ck, input_key_material);
+        self.ck = ck;
+        self.cipher = Some(CipherState::new(&key));
+    }
+
+    fn encrypt_and_hash(&mut self, plaintext: &[u8]) -> Result<Vec<u8>, Sv2Error> {
+        let h = self.h;
+        let ciphertext = match self.cipher.as_mut() {
+            Some(cipher) => cipher.encrypt(&h, plaintext)?,
+            None => plaintext.to_vec(),
+        };
+        self.mix_hash(&ciphertext);
+        Ok(ciphertext)
+    }
+
+    fn decrypt_and_hash(&mut self, ciphertext: &[u8]) -> Result<Vec<u8>, Sv2Error> {
+        let h = self.h;
+        let plaintext = match self.cipher.as_mut() {
+            Some(cipher) => cipher.decrypt(&h, ciphertext)?,
+            None => ciphertext.to_vec(),
+        };
+        self.mix_hash(ciphertext);
+        Ok(plaintext)
+    }
+
+    /// Derive the two transport keys (initiator-to-responder first).
+    fn split(&self) -> (CipherState, CipherState) {
+        let (k1, k2) = hkdf2(&self.ck, &[]);
+        (CipherState::new(&k1), CipherState::new(&k2))
+    }
+}
+
+fn hmac_sha256(key: &[u8], data: &[&[u8]]) -> [u8; 32] {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC accepts any key length");
+    for chunk in data {
+        mac.update(chunk);
+    }
+    let mut out = [0u8; 32];
+    out.copy_from_slice(&mac.finalize().into_bytes());
+    out
+}
+
+/// Noise HKDF with two outputs.
+fn hkdf2(chaining_key: &[u8; 32], input_key_material: &[u8]) -> ([u8; 32], [u8; 32]) {
+    let temp_key = hmac_sha256(chaining_key, &[input_key_material]);
+    let out1 = hmac_sha256(&temp_key, &[&[0x01]]);
+    let out2 = hmac_sha256(&temp_key, &[&out1, &[0x02]]);
+    (out1, out2)
+}
+
+fn ecdh(
+    initiator: ElligatorSwift,
+    responder: ElligatorSwift,
+    secret: SecretKey,
+    party: ElligatorSwiftParty,
+) -> [u8; 32] {
+    ElligatorSwift::shared_secret(initiator, responder, secret, party, None).to_secret_bytes()
+}
+
+fn random_secret_key() -> Result<SecretKey, Sv2Error> {
+    loop {
+        let mut bytes = [0u8; 32];
+        getrandom::getrandom(&mut bytes)
+            .map_err(|e| Sv2Error::Handshake(alloc::format!("no randomness: {}", e)))?;
+        if let Ok(key) = SecretKey::from_slice(&bytes) {
+            return Ok(key);
+        }
+    }
+}
+
+fn current_time() -> u32 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as u32)
+        .unwrap_or(0)
+}
+
+/// The pool's certificate: its static key signed by the authority key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignatureNoiseMessage {
+    pub version: u16,
+    pub valid_from: u32,
+    pub not_valid_after: u32,
+    pub signature: [u8; 64],
+}
+
+impl SignatureNoiseMessage {
+    /// Sign a static key with the authority key.
+    pub fn sign(
+        authority: &Keypair,
+        static_key: &XOnlyPublicKey,
+        valid_from: u32,
+        not_valid_after: u32,
+    ) -> Self {
+        let secp = Secp256k1::signing_only();
+        let digest = Self::digest(0, valid_from, not_valid_after, static_key);
+        let msg = secp256k1::Message::from_digest(digest);
+        let signature = secp.sign_schnorr_no_aux_rand(&msg, authority);
+
+        SignatureNoiseMessage {
+            version: 0,
+            valid_from,
+            not_valid_after,
+            signature: *signature.as_ref(),
+        }
+    }
+
+    /// Check the signature and validity window.
+    pub fn verify(&self, static_key: &XOnlyPublicKey, authority: &XOnlyPublicKey, now: u32) -> bool {
+        if now < self.valid_from || now > self.not_valid_after {
+            return false;
+        }
+        let Ok(signature) = Signature::from_slice(&self.signature) else {
+            return false;
+        };
+        let digest = Self::digest(self.version, self.valid_from, self.not_valid_after, static_key);
+        let msg = secp256k1::Message::from_digest(digest);
+        Secp256k1::verification_only()
+            .verify_schnorr(&signature, &msg, authority)
+            .is_ok()
+    }
+
+    fn digest(version: u16, valid_from: u32, not_valid_after: u32, static_key: &XOnlyPublicKey) -> [u8; 32] {
+        let mut data = Vec::with_capacity(42);
+        data.extend_from_slice(&version.to_le_bytes());
+        data.extend_from_slice(&valid_from.to_le_bytes());
+        data.extend_from_slice(&not_valid_after.to_le_bytes());
+        data.extend_from_slice(&static_key.serialize());
+        sha256(&data)
+    }
+
+    /// Serialize to 74 bytes.
+    pub fn serialize(&self) -> [u8; SIGNATURE_MESSAGE_SIZE] {
+        let mut out = [0u8; SIGNATURE_MESSAGE_SIZE];
+        out[0..2].copy_from_slice(&self.version.to_le_bytes());
+        out[2..6].copy_from_slice(&self.valid_from.to_le_bytes());
+        out[6..10].copy_from_slice(&self.not_valid_after.to_le_bytes());
+        out[10..].copy_from_slice(&self.signature);
+        out
+    }
+
+    /// Parse from 74 bytes.
+    pub fn parse(bytes: &[u8]) -> Result<Self, Sv2Error> {
+        if bytes.len() != SIGNATURE_MESSAGE_SIZE {
+            return Err(Sv2Error::Handshake("bad signature message length".into()));
+        }
+        let mut signature = [0u8; 64];
+        signature.copy_from_slice(&bytes[10..]);
+        Ok(SignatureNoiseMessage {
+            version: u16::from_le_bytes([bytes[0], bytes[1]]),
+            valid_from: u32::from_le_bytes([bytes[2], bytes[3], bytes[4], bytes[5]]),
+            not_valid_after: u32::from_le_bytes([bytes[6], bytes[7], bytes[8], bytes[9]]),
+            signature,
+        })
+    }
+}
+
+/// Miner side of the handshake.
+pub struct Initiator {
+    state: SymmetricState,
+    ephemeral: SecretKey,
+    ephemeral_ell: ElligatorSwift,
+    authority: XOnlyPublicKey,
+}
+
+impl Initiator {
+    /// Start a handshake with a pool identified by its authority key.
+    pub fn new(authority: XOnlyPublicKey) -> Result<Self, Sv2Error> {
+        let secp = Secp256k1::new();
+        let ephemeral = random_secret_key()?;
+        let ephemeral_ell = ElligatorSwift::from_seckey(&secp, ephemeral, None);
+
+        Ok(Initiator {
+            state: SymmetricState::new(),
+            ephemeral,
+            ephemeral_ell,
+            authority,
+        })
+    }
+
+    /// Produce the first handshake message (`-> e`).
+    pub fn first_message(&mut self) -> [u8; INITIATOR_HANDSHAKE_SIZE] {
+        let e = self.ephemeral_ell.to_array();
+        self.state.mix_hash(&e);
+        self.state.mix_hash(&[]); // empty payload
+        e
+    }
+
+    /// Process the responder's message and derive the transport keys.
+    pub fn finish(mut self, message: &[u8], now: u32) -> Result<Transport, Sv2Error> {
+        if message.len() != RESPONDER_HANDSHAKE_SIZE {
+            return Err(Sv2Error::Handshake("bad responder message length".into()));
+        }
+
+        // <- e, ee
+        let mut re = [0u8; ELLSWIFT_KEY_SIZE];
+        re.copy_from_slice(&message[..ELLSWIFT_KEY_SIZE]);
+        let re = ElligatorSwift::from_array(re);
+        self.state.mix_hash(&re.to_array());
+        self.state.mix_key(&ecdh(self.ephemeral_ell, re, self.ephemeral, ElligatorSwiftParty::A));
+
+        // s, es
+        let s_end = ELLSWIFT_KEY_SIZE * 2 + MAC_SIZE;
+        let rs = self.state.decrypt_and_hash(&message[ELLSWIFT_KEY_SIZE..s_end])?;
+        let mut rs_bytes = [0u8; ELLSWIFT_KEY_SIZE];
+        rs_bytes.copy_from_slice(&rs);
+        let rs = ElligatorSwift::from_array(rs_bytes);
+        self.state.mix_key(&ecdh(self.ephemeral_ell, rs, self.ephemeral, ElligatorSwiftParty::A));
+
+        // Certificate for the static key
+        let certificate = self.state.decrypt_and_hash(&message[s_end..])?;
+        let certificate = SignatureNoiseMessage::parse(&certificate)?;
+        let (static_key, _) = PublicKey::from_ellswift(rs).x_only_public_key();
+        if !certificate.verify(&static_key, &self.authority, now) {
+            return Err(Sv2Error::Handshake("pool certificate is not valid".into()));
+        }
+
+        let (send, recv) = self.state.split();
+        Ok(Transport { send, recv })
+    }
+}
+
+/// Pool side of the handshake.
+pub struct Responder {
+    state: SymmetricState,
+    static_key: SecretKey,
+    static_ell: ElligatorSwift,
+    certificate: SignatureNoiseMessage,
+}
+
+impl Responder {
+    /// Create a responder with a static key and its authority certificate.
+    pub fn new(static_key: SecretKey, certificate: SignatureNoiseMessage) -> Self {
+        let secp = Secp256k1::new();
+        Responder {
+            state: SymmetricState::new(),
+            static_key,
+            static_ell: ElligatorSwift::from_seckey(&secp, static_key, None),
+            certificate,
+        }
+    }
+
+    /// Process the initiator's `e` and produce the reply plus transport keys.
+    pub fn respond(mut self, message: &[u8]) -> Result<(Vec<u8>, Transport), Sv2Error> {
+        if message.len() != INITIATOR_HANDSHAKE_SIZE {
+            return Err(Sv2Error::Handshake("bad initiator message length".into()));
+        }
+        let mut re = [0u8; ELLSWIFT_KEY_SIZE];
+        re.copy_from_slice(message);
+        let re = ElligatorSwift::from_array(re);
+        self.state.mix_hash(&re.to_array());
+        self.state.mix_hash(&[]); // empty payload
+
+        let secp = Secp256k1::new();
+        let ephemeral = random_secret_key()?;
+        let ephemeral_ell = ElligatorSwift::from_seckey(&secp, ephemeral, None);
+
+        let mut reply = Vec::with_capacity(RESPONDER_HANDSHAKE_SIZE);
+
+        // e, ee
+        reply.extend_from_slice(&ephemeral_ell.to_array());
+        self.state.mix_hash(&ephemeral_ell.to_array());
+        self.state.mix_key(&ecdh(re, ephemeral_ell, ephemeral, ElligatorSwiftParty::B));
+
+        // s, es
+        let s = self.state.encrypt_and_hash(&self.static_ell.to_array())?;
+        reply.extend_from_slice(&s);
+        self.state.mix_key(&ecdh(re, self.static_ell, self.static_key, ElligatorSwiftParty::B));
+
+        let certificate = self.state.encrypt_and_hash(&self.certificate.serialize())?;
+        reply.extend_from_slice(&certificate);
+
+        let (recv, send) = self.state.split();
+        Ok((reply, Transport { send, recv }))
+    }
+}
+
+/// Established session keys for both directions.
+pub struct Transport {
+    send: CipherState,
+    recv: CipherState,
+}
+
+impl Transport {
+    /// Encrypt a plaintext frame: the header, then the payload in chunks.
+    pub fn encrypt_frame(&mut self, frame: &[u8]) -> Result<Vec<u8>, Sv2Error> {
+        if frame.len() < FRAME_HEADER_SIZE {
+            return Err(Sv2Error::Truncated);
+        }
+        let payload = &frame[FRAME_HEADER_SIZE..];
+        let mut out = Vec::with_capacity(ENCRYPTED_HEADER_SIZE + Self::encrypted_payload_len(payload.len()));
+
+        out.extend_from_slice(&self.send.encrypt(&[], &frame[..FRAME_HEADER_SIZE])?);
+        for chunk in payload.chunks(MAX_CHUNK_SIZE - MAC_SIZE) {
+            out.extend_from_slice(&self.send.encrypt(&[], chunk)?);
+        }
+        Ok(out)
+    }
+
+    /// Decrypt an encrypted frame header.
+    pub fn decrypt_header(&mut self, encrypted: &[u8]) -> Result<FrameHeader, Sv2Error> {
+        if encrypted.len() != ENCRYPTED_HEADER_SIZE {
+            return Err(Sv2Error::InvalidFrame(alloc::format!(
+                "encrypted header must be {} bytes, got {}",
+                ENCRYPTED_HEADER_SIZE,
+                encrypted.len()
+            )));
+        }
+        let plaintext = self.recv.decrypt(&[], encrypted)?;
+        let mut header = [0u8; FRAME_HEADER_SIZE];
+        header.copy_from_slice(&plaintext);
+        Ok(FrameHeader::parse(&header))
+    }
+
+    /// Decrypt an encrypted payload whose header has already been read.
+    pub fn decrypt_payload(&mut self, encrypted: &[u8]) -> Result<Vec<u8>, Sv2Error> {
+        let mut payload = Vec::with_capacity(encrypted.len());
+        for chunk in encrypted.chunks(MAX_CHUNK_SIZE) {
+            payload.extend_from_slice(&self.recv.decrypt(&[], chunk)?);
+        }
+        Ok(payload)
+    }
+
+    /// Encrypted size of a payload of `len` plaintext bytes.
+    pub fn encrypted_payload_len(len: usize) -> usize {
+        len + len.div_ceil(MAX_CHUNK_SIZE - MAC_SIZE) * MAC_SIZE
+    }
+}
+
+/// A byte stream carrying encrypted Stratum V2 frames.
+pub struct NoiseStream<S> {
+    stream: S,
+    transport: Transport,
+}
+
+impl<S: Read + Write> NoiseStream<S> {
+    /// Run the handshake as the miner and verify the pool's certificate.
+    pub fn connect(mut stream: S, authority: XOnlyPublicKey) -> Result<Self, Sv2Error> {
+        let mut initiator = Initiator::new(authority)?;
+        stream.write_all(&initiator.first_message())?;
+        stream.flush()?;
+
+        let mut reply = [0u8; RESPONDER_HANDSHAKE_SIZE];
+        stream.read_exact(&mut reply)?;
+        let transport = initiator.finish(&reply, current_time())?;
+
+        Ok(NoiseStream { stream, transport })
+    }
+
+    /// Run the handshake as the pool.
+    pub fn accept(mut stream: S, responder: Responder) -> Result<Self, Sv2Error> {
+        let mut first = [0u8; INITIATOR_HANDSHAKE_SIZE];
+        stream.read_exact(&mut first)?;
+        let (reply, transport) = responder.respond(&first)?;
+        stream.write_all(&reply)?;
+        stream.flush()?;
+
+        Ok(NoiseStream { stream, transport })
+    }
+
+    /// Encrypt and send a message.
+    pub fn send(&mut self, message: &Message) -> Result<(), Sv2Error> {
+        let encrypted = self.transport.encrypt_frame(&message.to_frame()?)?;
+        self.stream.write_all(&encrypted)?;
+        self.stream.flush()?;
+        Ok(())
+    }
+
+    /// Receive and decrypt the next message.
+    pub fn recv(&mut self) -> Result<Message, Sv2Error> {
+        let mut header = [0u8; ENCRYPTED_HEADER_SIZE];
+        self.stream.read_exact(&mut header)?;
+        let header = self.transport.decrypt_header(&header)?;
+        if header.msg_length as usize > MAX_RECV_PAYLOAD_SIZE {
+            return Err(Sv2Error::InvalidFrame(alloc::format!(
+                "announced payload of {} bytes exceeds the {} byte limit",
+                header.msg_length, MAX_RECV_PAYLOAD_SIZE
+            )));
+        }
+
+        let mut encrypted = alloc::vec![0u8; Transport::encrypted_payload_len(header.msg_length as usize)];
+        self.stream.read_exact(&mut encrypted)?;
+        let payload = self.transport.decrypt_payload(&encrypted)?;
+
+        Message::decode(&header, &payload)
+    }
+
+    /// The underlying stream.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::messages::{SetupConnection, SetupConnectionSuccess};
+
+    fn pool_keys(valid_from: u32, not_valid_after: u32) -> (XOnlyPublicKey, Responder) {
+        let secp = Secp256k1::new();
+        let authority = Keypair::from_seckey_slice(&secp, &[0x01; 32]).unwrap();
+        let static_key = SecretKey::from_slice(&[0x02; 32]).unwrap();
+        let (static_xonly, _) = static_key.x_only_public_key(&secp);
+
+        let certificate = SignatureNoiseMessage::sign(&authority, &static_xonly, valid_from, not_valid_after);
+        (authority.x_only_public_key().0, Responder::new(static_key, certificate))
+    }
+
+    #[test]
+    fn test_handshake_and_transport() {
+        let (authority, responder) = pool_keys(0, u32::MAX);
+
+        let mut initiator = Initiator::new(authority).unwrap();
+        let first = initiator.first_message();
+        assert_eq!(first.len(), INITIATOR_HANDSHAKE_SIZE);
+
+        let (reply, mut pool) = responder.respond(&first).unwrap();
+        assert_eq!(reply.len(), RESPONDER_HANDSHAKE_SIZE);
+        let mut miner = initiator.finish(&reply, 1_700_000_000).unwrap();
+
+        // Miner -> pool
+        let frame = Message::SetupConnection(SetupConnection::mining("pool", 3336, "goblin"))
+            .to_frame()
+            .unwrap();
+        let encrypted = miner.encrypt_frame(&frame).unwrap();
+        let header = pool.decrypt_header(&encrypted[..ENCRYPTED_HEADER_SIZE]).unwrap();
+        let payload = pool.decrypt_payload(&encrypted[ENCRYPTED_HEADER_SIZE..]).unwrap();
+        assert_eq!(&payload[..], &frame[FRAME_HEADER_SIZE..]);
+        assert_eq!(header.msg_length as usize, payload.len());
+
+        // Pool -> miner, with a tampered byte
+        let frame = Message::SetupConnectionSuccess(SetupConnectionSuccess { used_version: 2, flags: 0 })
+            .to_frame()
+            .unwrap();
+        let mut encrypted = pool.encrypt_frame(&frame).unwrap();
+        encrypted[0] ^= 1;
+        assert!(matches!(
+            miner.decrypt_header(&encrypted[..ENCRYPTED_HEADER_SIZE]),
+            Err(Sv2Error::Decrypt)
+        ));
+
+        // A header of the wrong size is rejected before decrypting
+        for len in [0, ENCRYPTED_HEADER_SIZE - 1, ENCRYPTED_HEADER_SIZE + 1] {
+            assert!(matches!(
+                miner.decrypt_header(&encrypted[..len]),
+                Err(Sv2Error::InvalidFrame(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_recv_rejects_oversized_payload() {
+        let (authority, responder) = pool_keys(0, u32::MAX);
+        let mut initiator = Initiator::new(authority).unwrap();
+        let (reply, mut pool) = responder.respond(&initiator.first_message()).unwrap();
+        let miner = initiator.finish(&reply, 1_700_000_000).unwrap();
+
+        // Only the header is sent: the length alone must be refused
+        let header = FrameHeader { extension_type: 0, msg_type: 0x15, msg_length: MAX_RECV_PAYLOAD_SIZE as u32 + 1 };
+        let encrypted = pool.encrypt_frame(&header.serialize()).unwrap();
+        let mut stream = NoiseStream { stream: std::io::Cursor::new(encrypted), transport: miner };
+        assert!(matches!(stream.recv(), Err(Sv2Error::InvalidFrame(_))));
+    }
+
+    #[test]
+    fn test_rejects_wrong_authority_and_expired_certificate() {
+        let secp = Secp256k1::new();
+        let (_, responder) = pool_keys(0, u32::MAX);
+        let impostor = Keypair::from_seckey_slice(&secp, &[0x03; 32]).unwrap();
+
+        let mut initiator = Initiator::new(impostor.x_only_public_key().0).unwrap();
+        let (reply, _) = responder.respond(&initiator.first_message()).unwrap();
+        assert!(matches!(initiator.finish(&reply, 1_700_000_000), Err(Sv2Error::Handshake(_))));
+
+        let (authority, responder) = pool_keys(0, 1_000);
+        let mut initiator = Initiator::new(authority).unwrap();
+        let (reply, _) = responder.respond(&initiator.first_message()).unwrap();
+        assert!(initiator.finish(&reply, 1_700_000_000).is_err());
+    }
+
+    #[test]
+    fn test_large_payload_chunking() {
+        assert_eq!(Transport::encrypted_payload_len(0), 0);
+        assert_eq!(Transport::encrypted_payload_len(10), 26);
+        assert_eq!(Transport::encrypted_payload_len(65519), 65535);
+        assert_eq!(Transport::encrypted_payload_len(65520), 65535 + 17);
+    }
+}
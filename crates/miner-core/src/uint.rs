@@ -0,0 +1,528 @@
+//! 256-bit unsigned integers for targets, difficulty and work.
+//!
+//! Elsewhere in the crate targets are 32-byte big-endian arrays and hashes
+//! are in their internal little-endian order; [`U256`] converts from and to
+//! both so comparisons and arithmetic on them are exact.
+
+use core::cmp::Ordering;
+use core::fmt;
+use core::ops::{Add, AddAssign, Div, Mul, Not, Rem, Shl, Shr, Sub, SubAssign};
+
+/// A 256-bit unsigned integer.
+///
+/// Stored as four little-endian 64-bit limbs. Like the primitive integers,
+/// the operators panic on overflow and division by zero; the `checked_*`
+/// methods return `None` instead.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct U256([u64; 4]);
+
+impl U256 {
+    /// Zero.
+    pub const ZERO: U256 = U256([0; 4]);
+    /// One.
+    pub const ONE: U256 = U256([1, 0, 0, 0]);
+    /// 2^256 - 1.
+    pub const MAX: U256 = U256([u64::MAX; 4]);
+
+    /// Create from a `u64`.
+    pub const fn from_u64(value: u64) -> Self {
+        U256([value, 0, 0, 0])
+    }
+
+    /// Create from 32 big-endian bytes (the byte order of targets).
+    pub fn from_be_bytes(bytes: [u8; 32]) -> Self {
+        let mut limbs = [0u64; 4];
+        for (limb, chunk) in limbs.iter_mut().zip(bytes.as_chunks::<8>().0.iter().rev()) {
+            *limb = u64::from_be_bytes(*chunk);
+        }
+        U256(limbs)
+    }
+
+    /// Create from 32 little-endian bytes (the internal byte order of hashes).
+    pub fn from_le_bytes(bytes: [u8; 32]) -> Self {
+        let mut limbs = [0u64; 4];
+        for (limb, chunk) in limbs.iter_mut().zip(bytes.as_chunks::<8>().0) {
+            *limb = u64::from_le_bytes(*chunk);
+        }
+        U256(limbs)
+    }
+
+    /// Serialize as 32 big-endian bytes.
+    pub fn to_be_bytes(&self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        for (chunk, limb) in bytes.as_chunks_mut::<8>().0.iter_mut().rev().zip(self.0) {
+            *chunk = limb.to_be_bytes();
+        }
+        bytes
+    }
+
+    /// Serialize as 32 little-endian bytes.
+    pub fn to_le_bytes(&self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        for (chunk, limb) in bytes.as_chunks_mut::<8>().0.iter_mut().zip(self.0) {
+            *chunk = limb.to_le_bytes();
+        }
+        bytes
+    }
+
+    /// Decode compact `bits` the way Bitcoin Core's `SetCompact` does.
+    ///
+    /// The top byte is a base-256 exponent and the low 23 bits a mantissa,
+    /// so the value is `mantissa * 256^(exponent - 3)`. Returns `None` if
+    /// the sign bit is set on a non-zero mantissa or the value overflows
+    /// 256 bits.
+    pub fn from_compact(bits: u32) -> Option<Self> {
+        let size = bits >> 24;
+        let mut word = bits & 0x007F_FFFF;
+
+        let value = if size <= 3 {
+            word >>= 8 * (3 - size);
+            U256::from_u64(word as u64)
+        } else {
+            U256::from_u64(word as u64) << (8 * (size - 3))
+        };
+
+        let negative = word != 0 && bits & 0x0080_0000 != 0;
+        let overflow = word != 0 && (size > 34 || (word > 0xff && size > 33) || (word > 0xffff && size > 32));
+        if negative || overflow {
+            return None;
+        }
+        Some(value)
+    }
+
+    /// Encode as compact bits, rounding down to a 23-bit mantissa.
+    ///
+    /// The inverse of [`U256::from_compact`] for values it produces.
+    pub fn to_compact(&self) -> u32 {
+        let mut size = self.bits().div_ceil(8);
+        let mut compact = if size <= 3 {
+            (self.low_u64() << (8 * (3 - size))) as u32
+        } else {
+            (*self >> (8 * (size - 3))).low_u64() as u32
+        };
+
+        // The mantissa's top bit is the sign: move it into the exponent
+        if compact & 0x0080_0000 != 0 {
+            compact >>= 8;
+            size += 1;
+        }
+        compact | (size << 24)
+    }
+
+    /// Whether the value is zero.
+    pub fn is_zero(&self) -> bool {
+        self.0 == [0; 4]
+    }
+
+    /// The lowest 64 bits.
+    pub fn low_u64(&self) -> u64 {
+        self.0[0]
+    }
+
+    /// Number of significant bits (0 for zero).
+    pub fn bits(&self) -> u32 {
+        256 - self.leading_zeros()
+    }
+
+    /// Number of leading zero bits.
+    pub fn leading_zeros(&self) -> u32 {
+        for (i, limb) in self.0.iter().enumerate().rev() {
+            if *limb != 0 {
+                return (3 - i as u32) * 64 + limb.leading_zeros();
+            }
+        }
+        256
+    }
+
+    /// Nearest `f64`.
+    pub fn to_f64(&self) -> f64 {
+        let bits = self.bits();
+        if bits <= 64 {
+            return self.low_u64() as f64;
+        }
+
+        // The top 64 bits, scaled by an exact power of two
+        let shift = bits - 64;
+        let scale = f64::from_bits((1023 + shift as u64) << 52);
+        (*self >> shift).low_u64() as f64 * scale
+    }
+
+    /// Sum and whether it wrapped.
+    pub fn overflowing_add(self, other: U256) -> (U256, bool) {
+        let mut result = [0u64; 4];
+        let mut carry = false;
+        for (i, limb) in result.iter_mut().enumerate() {
+            let (sum, c1) = self.0[i].overflowing_add(other.0[i]);
+            let (sum, c2) = sum.overflowing_add(carry as u64);
+            *limb = sum;
+            carry = c1 || c2;
+        }
+        (U256(result), carry)
+    }
+
+    /// Difference and whether it wrapped.
+    pub fn overflowing_sub(self, other: U256) -> (U256, bool) {
+        let mut result = [0u64; 4];
+        let mut borrow = false;
+        for (i, limb) in result.iter_mut().enumerate() {
+            let (diff, b1) = self.0[i].overflowing_sub(other.0[i]);
+            let (diff, b2) = diff.overflowing_sub(borrow as u64);
+            *limb = diff;
+            borrow = b1 || b2;
+        }
+        (U256(result), borrow)
+    }
+
+    /// Product and whether it wrapped.
+    pub fn overflowing_mul(self, other: U256) -> (U256, bool) {
+        let mut result = [0u64; 4];
+        let mut overflow = false;
+        for i in 0..4 {
+            let mut carry = 0u128;
+            for j in 0..4 {
+                let product = self.0[i] as u128 * other.0[j] as u128 + carry;
+                if i + j < 4 {
+                    let sum = result[i + j] as u128 + (product & u64::MAX as u128);
+                    result[i + j] = sum as u64;
+                    carry = (product >> 64) + (sum >> 64);
+                } else {
+                    overflow |= product != 0;
+                    carry = 0;
+                }
+            }
+            overflow |= carry != 0;
+        }
+        (U256(result), overflow)
+    }
+
+    /// Quotient and remainder.
+    ///
+    /// # Panics
+    ///
+    /// If `divisor` is zero.
+    pub fn div_rem(self, divisor: U256) -> (U256, U256) {
+        assert!(!divisor.is_zero(), "attempt to divide by zero");
+        if self < divisor {
+            return (U256::ZERO, self);
+        }
+
+        // Shift-and-subtract long division
+        let shift = self.bits() - divisor.bits();
+        let mut divisor = divisor << shift;
+        let mut remainder = self;
+        let mut quotient = U256::ZERO;
+        for bit in (0..=shift).rev() {
+            if remainder >= divisor {
+                remainder -= divisor;
+                quotient.0[bit as usize / 64] |= 1 << (bit % 64);
+            }
+            divisor = divisor >> 1;
+        }
+        (quotient, remainder)
+    }
+
+    /// `self + other`, or `None` on overflow.
+    pub fn checked_add(self, other: U256) -> Option<U256> {
+        match self.overflowing_add(other) {
+            (sum, false) => Some(sum),
+            _ => None,
+        }
+    }
+
+    /// `self - other`, or `None` on underflow.
+    pub fn checked_sub(self, other: U256) -> Option<U256> {
+        match self.overflowing_sub(other) {
+            (diff, false) => Some(diff),
+            _ => None,
+        }
+    }
+
+    /// `self * other`, or `None` on overflow.
+    pub fn checked_mul(self, other: U256) -> Option<U256> {
+        match self.overflowing_mul(other) {
+            (product, false) => Some(product),
+            _ => None,
+        }
+    }
+
+    /// `self / other`, or `None` if `other` is zero.
+    pub fn checked_div(self, other: U256) -> Option<U256> {
+        (!other.is_zero()).then(|| self.div_rem(other).0)
+    }
+
+    /// `self + other`, clamped to [`U256::MAX`].
+    pub fn saturating_add(self, other: U256) -> U256 {
+        self.checked_add(other).unwrap_or(U256::MAX)
+    }
+}
+
+impl From<u64> for U256 {
+    fn from(value: u64) -> Self {
+        U256::from_u64(value)
+    }
+}
+
+impl From<u32> for U256 {
+    fn from(value: u32) -> Self {
+        U256::from_u64(value as u64)
+    }
+}
+
+impl Ord for U256 {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.iter().rev().cmp(other.0.iter().rev())
+    }
+}
+
+impl PartialOrd for U256 {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Add for U256 {
+    type Output = U256;
+
+    fn add(self, other: U256) -> U256 {
+        self.checked_add(other).expect("attempt to add with overflow")
+    }
+}
+
+impl AddAssign for U256 {
+    fn add_assign(&mut self, other: U256) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for U256 {
+    type Output = U256;
+
+    fn sub(self, other: U256) -> U256 {
+        self.checked_sub(other).expect("attempt to subtract with overflow")
+    }
+}
+
+impl SubAssign for U256 {
+    fn sub_assign(&mut self, other: U256) {
+        *self = *self - other;
+    }
+}
+
+impl Mul for U256 {
+    type Output = U256;
+
+    fn mul(self, other: U256) -> U256 {
+        self.checked_mul(other).expect("attempt to multiply with overflow")
+    }
+}
+
+impl Div for U256 {
+    type Output = U256;
+
+    fn div(self, other: U256) -> U256 {
+        self.div_rem(other).0
+    }
+}
+
+impl Rem for U256 {
+    type Output = U256;
+
+    fn rem(self, other: U256) -> U256 {
+        self.div_rem(other).1
+    }
+}
+
+impl Not for U256 {
+    type Output = U256;
+
+    fn not(self) -> U256 {
+        U256(self.0.map(|limb| !limb))
+    }
+}
+
+impl Shl<u32> for U256 {
+    type Output = U256;
+
+    /// Shift left, dropping bits shifted past 256.
+    fn shl(self, shift: u32) -> U256 {
+        let mut result = [0u64; 4];
+        let (limbs, bits) = ((shift / 64) as usize, shift % 64);
+        for (i, limb) in result.iter_mut().enumerate().skip(limbs) {
+            *limb = self.0[i - limbs] << bits;
+            if bits > 0 && i > limbs {
+                *limb |= self.0[i - limbs - 1] >> (64 - bits);
+            }
+        }
+        U256(result)
+    }
+}
+
+impl Shr<u32> for U256 {
+    type Output = U256;
+
+    /// Shift right, dropping bits shifted past zero.
+    fn shr(self, shift: u32) -> U256 {
+        let mut result = [0u64; 4];
+        let (limbs, bits) = ((shift / 64) as usize, shift % 64);
+        for (i, limb) in result.iter_mut().enumerate().take(4usize.saturating_sub(limbs)) {
+            *limb = self.0[i + limbs] >> bits;
+            if bits > 0 && i + limbs + 1 < 4 {
+                *limb |= self.0[i + limbs + 1] << (64 - bits);
+            }
+        }
+        U256(result)
+    }
+}
+
+impl fmt::Display for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Peel off 19 decimal digits at a time
+        const CHUNK: u64 = 10_000_000_000_000_000_000;
+        let mut digits = [0u8; 95];
+        let mut start = digits.len();
+        let mut value = *self;
+        loop {
+            let (quotient, remainder) = value.div_rem(U256::from_u64(CHUNK));
+            let mut chunk = remainder.low_u64();
+            for _ in 0..19 {
+                start -= 1;
+                digits[start] = b'0' + (chunk % 10) as u8;
+                chunk /= 10;
+            }
+            value = quotient;
+            if value.is_zero() {
+                break;
+            }
+        }
+
+        while start < digits.len() - 1 && digits[start] == b'0' {
+            start += 1;
+        }
+        f.pad_integral(true, "", core::str::from_utf8(&digits[start..]).unwrap())
+    }
+}
+
+impl fmt::LowerHex for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut digits = [0u8; 64];
+        hex::encode_to_slice(self.to_be_bytes(), &mut digits).unwrap();
+        let start = digits.iter().position(|&d| d != b'0').unwrap_or(63);
+        f.pad_integral(true, "0x", core::str::from_utf8(&digits[start..]).unwrap())
+    }
+}
+
+impl fmt::Debug for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "U256({:#066x})", self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::format;
+
+    fn hex(s: &str) -> U256 {
+        let mut bytes = [0u8; 32];
+        let padded = format!("{:0>64}", s);
+        hex::decode_to_slice(padded, &mut bytes).unwrap();
+        U256::from_be_bytes(bytes)
+    }
+
+    #[test]
+    fn test_byte_order_and_ordering() {
+        let mut be = [0u8; 32];
+        be[0] = 0x01;
+        be[31] = 0x02;
+        let value = U256::from_be_bytes(be);
+        assert_eq!(value.to_be_bytes(), be);
+        assert_eq!(value.to_le_bytes()[0], 0x02);
+        assert_eq!(U256::from_le_bytes(value.to_le_bytes()), value);
+        assert_eq!(value.bits(), 249);
+
+        // The high limb decides the order
+        assert!(value > U256::from_u64(u64::MAX));
+        assert!(hex("ffff0000000000000000") < hex("1000000000000000000000"));
+        assert_eq!(U256::ZERO.bits(), 0);
+        assert_eq!(U256::MAX.leading_zeros(), 0);
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let a = hex("ffffffffffffffffffffffffffffffff");
+        assert_eq!(a + U256::ONE, U256::ONE << 128);
+        assert_eq!((U256::ONE << 128) - U256::ONE, a);
+        assert_eq!(a * a, hex("fffffffffffffffffffffffffffffffe00000000000000000000000000000001"));
+
+        assert_eq!(U256::MAX.checked_add(U256::ONE), None);
+        assert_eq!(U256::ZERO.checked_sub(U256::ONE), None);
+        assert_eq!((U256::ONE << 128).checked_mul(U256::ONE << 128), None);
+        assert_eq!(U256::MAX.overflowing_add(U256::ONE), (U256::ZERO, true));
+        assert_eq!(U256::MAX.saturating_add(U256::ONE), U256::MAX);
+        assert_eq!(!U256::ZERO, U256::MAX);
+    }
+
+    #[test]
+    fn test_shifts() {
+        let value = hex("123456789abcdef0fedcba9876543210");
+        assert_eq!(value << 4, hex("123456789abcdef0fedcba98765432100"));
+        assert_eq!(value >> 68, hex("123456789abcdef"));
+        assert_eq!((value << 200) >> 200, hex("dcba9876543210"));
+        assert_eq!(value << 256, U256::ZERO);
+        assert_eq!(U256::MAX >> 255, U256::ONE);
+    }
+
+    #[test]
+    fn test_division() {
+        let diff1 = hex("ffff0000000000000000000000000000000000000000000000000000");
+        let target = hex("1342190000000000000000000000000000000000000000");
+        let (quotient, remainder) = diff1.div_rem(target);
+        assert_eq!(quotient * target + remainder, diff1);
+        assert!(remainder < target);
+        assert_eq!(quotient, U256::from_u64(0xd4a_f792_3799));
+
+        assert_eq!(U256::from_u64(7) / U256::from_u64(8), U256::ZERO);
+        assert_eq!(U256::MAX / U256::MAX, U256::ONE);
+        assert_eq!(U256::MAX % U256::from_u64(10), U256::from_u64(5));
+        assert_eq!(U256::ONE.checked_div(U256::ZERO), None);
+    }
+
+    #[test]
+    fn test_compact() {
+        // Vectors from Bitcoin Core's arith_uint256 tests
+        for bits in [0x00000000, 0x00123456, 0x01003456, 0x02000056, 0x03000000, 0x04000000, 0x00923456, 0x01803456] {
+            assert_eq!(U256::from_compact(bits), Some(U256::ZERO), "{:08x}", bits);
+        }
+        let cases = [
+            (0x01123456, hex("12"), 0x01120000),
+            (0x02123456, hex("1234"), 0x02123400),
+            (0x03123456, hex("123456"), 0x03123456),
+            (0x04123456, hex("12345600"), 0x04123456),
+            (0x05009234, hex("92340000"), 0x05009234),
+            (0x20123456, U256::from_u64(0x123456) << 232, 0x20123456),
+        ];
+        for (bits, value, compact) in cases {
+            assert_eq!(U256::from_compact(bits), Some(value), "{:08x}", bits);
+            assert_eq!(value.to_compact(), compact, "{:08x}", bits);
+        }
+
+        // Negative and overflowing values
+        assert_eq!(U256::from_compact(0x01fedcba), None);
+        assert_eq!(U256::from_compact(0x04923456), None);
+        assert_eq!(U256::from_compact(0xff123456), None);
+        assert_eq!(U256::from_u64(0x80).to_compact(), 0x02008000);
+    }
+
+    #[test]
+    fn test_formatting() {
+        assert_eq!(format!("{}", U256::ZERO), "0");
+        assert_eq!(
+            format!("{}", U256::MAX),
+            "115792089237316195423570985008687907853269984665640564039457584007913129639935"
+        );
+        assert_eq!(format!("{:x}", hex("ffff")), "ffff");
+        assert_eq!(format!("{:#x}", U256::ZERO), "0x0");
+        assert_eq!(U256::from_u64(1 << 53).to_f64(), 9_007_199_254_740_992.0);
+        assert_eq!((U256::ONE << 200).to_f64(), 1.606_938_044_258_990_3e60);
+    }
+}
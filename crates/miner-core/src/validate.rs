@@ -0,0 +1,437 @@
+//! Consensus sanity checks for a block before it is submitted.
+//!
+//! These are the checks a node runs that a miner can get wrong on its own:
+//! proof of work, merkle root, BIP34 height, coinbase value, witness
+//! commitment, weight and timestamp. Every failed check is reported, so a
+//! caller can show all problems at once instead of the node's first one.
+//! Checks that need the UTXO set or chain history (signatures, expected
+//! `bits`, median time past) are left to the node; the latter two can be
+//! passed in through [`ValidationContext`].
+
+use alloc::vec::Vec;
+use core::fmt;
+use crate::block::BlockTemplate;
+use crate::coinbase::{encode_height_push, encode_varint};
+use crate::decode::{decode_block, Block, DecodeError};
+use crate::difficulty::{bits_to_target, hash_meets_target};
+use crate::hash::reverse_bytes;
+use crate::merkle::{compute_merkle_root, compute_witness_commitment, compute_witness_merkle_root};
+use crate::signet::{block_solution, SignetError};
+use crate::uint::U256;
+use crate::network::{
+    Network, MAX_BLOCK_WEIGHT, MAX_COINBASE_SCRIPTSIG_SIZE, MAX_FUTURE_BLOCK_TIME,
+    MIN_COINBASE_SCRIPTSIG_SIZE,
+};
+
+/// A consensus rule the block breaks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    /// The serialized block could not be decoded.
+    Malformed(DecodeError),
+    /// `bits` is negative, zero, overflows 256 bits or is easier than the
+    /// network's proof-of-work limit.
+    InvalidBits,
+    /// The block hash is above the target from `bits`.
+    HighHash,
+    /// The header's merkle root does not match the transactions.
+    BadMerkleRoot,
+    /// The block has no transactions.
+    NoTransactions,
+    /// The first transaction is not a coinbase.
+    FirstNotCoinbase,
+    /// A transaction other than the first is a coinbase.
+    MultipleCoinbases,
+    /// The coinbase scriptSig is outside 2..=100 bytes.
+    BadCoinbaseLength(usize),
+    /// The coinbase scriptSig does not start with the block height (BIP34).
+    BadCoinbaseHeight,
+    /// The coinbase pays out more than the subsidy plus fees.
+    BadCoinbaseAmount {
+        /// Subsidy plus fees.
+        allowed: u64,
+        /// Sum of the coinbase outputs.
+        actual: u64,
+    },
+    /// The coinbase witness is not a single 32-byte reserved value.
+    BadWitnessNonce,
+    /// The witness commitment does not match the transactions' wtxids.
+    BadWitnessCommitment,
+    /// Transactions carry witness data but the coinbase has no commitment.
+    UnexpectedWitness,
+    /// The block weight exceeds 4,000,000.
+    TooHeavy(u64),
+    /// The timestamp is before the earliest allowed time.
+    TimeTooOld,
+    /// The timestamp is more than two hours in the future.
+    TimeTooNew,
+    /// A signet block's solution is missing or does not decode.
+    BadSignetSolution(SignetError),
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::Malformed(e) => write!(f, "Block does not decode: {}", e),
+            Violation::InvalidBits => write!(f, "Invalid difficulty bits"),
+            Violation::HighHash => write!(f, "Block hash does not meet the target"),
+            Violation::BadMerkleRoot => write!(f, "Merkle root does not match the transactions"),
+            Violation::NoTransactions => write!(f, "Block has no transactions"),
+            Violation::FirstNotCoinbase => write!(f, "First transaction is not a coinbase"),
+            Violation::MultipleCoinbases => write!(f, "More than one coinbase transaction"),
+            Violation::BadCoinbaseLength(len) => {
+                write!(f, "Coinbase scriptSig is {} bytes (must be 2 to 100)", len)
+            }
+            Violation::BadCoinbaseHeight => write!(f, "Coinbase does not start with the block height"),
+            Violation::BadCoinbaseAmount { allowed, actual } => {
+                write!(f, "Coinbase pays {} sats but only {} are available", actual, allowed)
+            }
+            Violation::BadWitnessNonce => write!(f, "Coinbase witness must be one 32-byte item"),
+            Violation::BadWitnessCommitment => write!(f, "Witness commitment does not match"),
+            Violation::UnexpectedWitness => write!(f, "Witness data without a witness commitment"),
+            Violation::TooHeavy(weight) => write!(f, "Block weight {} exceeds the limit", weight),
+            Violation::TimeTooOld => write!(f, "Block timestamp is too early"),
+            Violation::TimeTooNew => write!(f, "Block timestamp is too far in the future"),
+            Violation::BadSignetSolution(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// What the validator needs to know beyond the block itself.
+#[derive(Debug, Clone)]
+pub struct ValidationContext {
+    /// The network the block is for.
+    pub network: Network,
+    /// Height of the block.
+    pub height: u32,
+    /// Total fees of the block's transactions.
+    pub fees: u64,
+    /// Earliest allowed timestamp (median time past + 1); 0 skips the check.
+    pub min_time: u32,
+    /// Current Unix time; 0 skips the future timestamp check.
+    pub now: u32,
+}
+
+/// Check `block` against the consensus rules a miner is responsible for.
+///
+/// Returns every violation found; an empty list means the block passed.
+pub fn validate_block(block: &Block, context: &ValidationContext) -> Vec<Violation> {
+    let mut violations = Vec::new();
+
+    check_header(block, context, &mut violations);
+
+    let Some(coinbase) = block.transactions.first() else {
+        violations.push(Violation::NoTransactions);
+        return violations;
+    };
+
+    let txids: Vec<[u8; 32]> = block.transactions.iter().map(|tx| tx.txid()).collect();
+    if compute_merkle_root(&txids) != block.header.merkle_root {
+        violations.push(Violation::BadMerkleRoot);
+    }
+
+    if !coinbase.is_coinbase() {
+        violations.push(Violation::FirstNotCoinbase);
+    } else {
+        check_coinbase(block, context, &mut violations);
+    }
+    if block.transactions[1..].iter().any(|tx| tx.is_coinbase()) {
+        violations.push(Violation::MultipleCoinbases);
+    }
+
+    check_witness_commitment(block, &mut violations);
+
+    // The solution's signature needs a script interpreter; only its
+    // placement and encoding are checked here
+    if context.network == Network::Signet {
+        if let Err(e) = block_solution(block) {
+            violations.push(Violation::BadSignetSolution(e));
+        }
+    }
+
+    let weight = block_weight(block);
+    if weight > MAX_BLOCK_WEIGHT {
+        violations.push(Violation::TooHeavy(weight));
+    }
+
+    violations
+}
+
+/// Block weight: three times the size without witness data plus the full size.
+pub fn block_weight(block: &Block) -> u64 {
+    let full = block.serialize().len() as u64;
+    let stripped: usize = block.transactions.iter().map(|tx| tx.serialize_without_witness().len()).sum();
+    let mut count = Vec::new();
+    encode_varint(block.transactions.len() as u64, &mut count);
+    let base = (80 + count.len() + stripped) as u64;
+    base * 3 + full
+}
+
+fn check_header(block: &Block, context: &ValidationContext, violations: &mut Vec<Violation>) {
+    let header = &block.header;
+
+    if !compact_is_valid(header.bits, context.network) {
+        violations.push(Violation::InvalidBits);
+    } else if !hash_meets_target(&reverse_bytes(&header.hash()), &bits_to_target(header.bits)) {
+        violations.push(Violation::HighHash);
+    }
+
+    if context.min_time != 0 && header.timestamp < context.min_time {
+        violations.push(Violation::TimeTooOld);
+    }
+    if context.now != 0 && header.timestamp > context.now.saturating_add(MAX_FUTURE_BLOCK_TIME) {
+        violations.push(Violation::TimeTooNew);
+    }
+}
+
+fn check_coinbase(block: &Block, context: &ValidationContext, violations: &mut Vec<Violation>) {
+    let coinbase = &block.transactions[0];
+    let script_sig = &coinbase.inputs[0].script_sig;
+
+    if !(MIN_COINBASE_SCRIPTSIG_SIZE..=MAX_COINBASE_SCRIPTSIG_SIZE).contains(&script_sig.len()) {
+        violations.push(Violation::BadCoinbaseLength(script_sig.len()));
+    }
+
+    if context.height >= context.network.bip34_height() && !script_sig.starts_with(&encode_height_push(context.height)) {
+        violations.push(Violation::BadCoinbaseHeight);
+    }
+
+    let allowed = context.network.block_subsidy(context.height).saturating_add(context.fees);
+    let actual = coinbase
+        .outputs
+        .iter()
+        .fold(0u64, |sum, output| sum.saturating_add(output.value));
+    if actual > allowed {
+        violations.push(Violation::BadCoinbaseAmount { allowed, actual });
+    }
+}
+
+fn check_witness_commitment(block: &Block, violations: &mut Vec<Violation>) {
+    let coinbase = &block.transactions[0];
+
+    let Some(commitment) = block.witness_commitment_index().map(|index| &coinbase.outputs[index]) else {
+        if block.transactions.iter().any(|tx| tx.has_witness()) {
+            violations.push(Violation::UnexpectedWitness);
+        }
+        return;
+    };
+
+    let witness = &coinbase.inputs[0].witness;
+    if witness.len() != 1 || witness[0].len() != 32 {
+        violations.push(Violation::BadWitnessNonce);
+        return;
+    }
+
+    // The coinbase's wtxid counts as zero
+    let wtxids: Vec<[u8; 32]> = block.transactions[1..].iter().map(|tx| tx.wtxid()).collect();
+    let mut reserved = [0u8; 32];
+    reserved.copy_from_slice(&witness[0]);
+    let expected = compute_witness_commitment(&compute_witness_merkle_root(&wtxids), &reserved);
+    if expected[..] != commitment.script_pubkey[6..38] {
+        violations.push(Violation::BadWitnessCommitment);
+    }
+}
+
+/// Whether compact `bits` decode to a positive target that fits in 256 bits
+/// and is no easier than the network's proof-of-work limit.
+fn compact_is_valid(bits: u32, network: Network) -> bool {
+    U256::from_compact(bits)
+        .is_some_and(|target| !target.is_zero() && target <= U256::from_be_bytes(network.pow_limit()))
+}
+
+impl BlockTemplate {
+    /// Check the serialized block against the consensus rules.
+    ///
+    /// `now` is the current Unix time (0 to skip the future timestamp check).
+    /// Fees come from the template's transactions, so a coinbase paying more
+    /// than the node offered is caught.
+    pub fn validate(&self, now: u32) -> Vec<Violation> {
+        let block = match decode_block(&self.serialize_block()) {
+            Ok(block) => block,
+            Err(e) => return alloc::vec![Violation::Malformed(e)],
+        };
+
+        let context = ValidationContext {
+            network: self.network,
+            height: self.height,
+            fees: self.transactions.iter().map(|tx| tx.fee).sum(),
+            min_time: self.min_time,
+            now,
+        };
+        validate_block(&block, &context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::address::validate_address;
+    use crate::coinbase::CoinbaseBuilder;
+    use crate::decode::tests::{BLOCK_170_HEX, GENESIS_BLOCK_HEX};
+    use crate::decode::{decode_block_hex, decode_transaction, TxOut};
+    use crate::gbt::GetBlockTemplate;
+
+    fn mainnet_context(height: u32) -> ValidationContext {
+        ValidationContext { network: Network::Mainnet, height, fees: 0, min_time: 0, now: 0 }
+    }
+
+    /// A solved coinbase-only block at an easy target.
+    fn solved_template(height: u32) -> BlockTemplate {
+        let network = Network::Regtest;
+        let address = validate_address("bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080", network).unwrap();
+        let builder = CoinbaseBuilder::new(network, height, address);
+        let mut template = BlockTemplate::new(
+            network,
+            height,
+            [0x22; 32],
+            0x207fffff,
+            1_700_000_000,
+            builder,
+            network.block_subsidy(height),
+        )
+        .unwrap();
+        solve(&mut template.header);
+        template
+    }
+
+    fn solve(header: &mut crate::block::BlockHeader) {
+        let target = bits_to_target(header.bits);
+        while !hash_meets_target(&reverse_bytes(&header.hash()), &target) {
+            header.nonce += 1;
+        }
+    }
+
+    #[test]
+    fn test_mainnet_blocks_are_valid() {
+        let genesis = decode_block_hex(GENESIS_BLOCK_HEX).unwrap();
+        assert_eq!(validate_block(&genesis, &mainnet_context(0)), []);
+
+        let block = decode_block_hex(BLOCK_170_HEX).unwrap();
+        assert_eq!(validate_block(&block, &mainnet_context(170)), []);
+    }
+
+    #[test]
+    fn test_template_blocks_are_valid() {
+        let template = solved_template(1000);
+        assert_eq!(template.validate(1_700_000_000), []);
+        assert!(block_weight(&decode_block(&template.serialize_block()).unwrap()) < 1000);
+
+        // With the node's transactions and witness commitment
+        let network = Network::Regtest;
+        let gbt = GetBlockTemplate::parse(crate::gbt::tests::TEMPLATE_JSON).unwrap();
+        let address = validate_address("bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080", network).unwrap();
+        let builder = CoinbaseBuilder::new(network, gbt.height, address.clone());
+        let mut template = BlockTemplate::from_getblocktemplate(network, gbt, builder).unwrap();
+        solve(&mut template.header);
+
+        // The fixture's txids and witness commitment are made up, so exactly
+        // those two checks fail
+        assert_eq!(
+            template.validate(1_700_000_000),
+            [Violation::BadMerkleRoot, Violation::BadWitnessCommitment]
+        );
+
+        // With the real ids and a commitment computed from the wtxids
+        for tx in &mut template.transactions {
+            let decoded = decode_transaction(&tx.data).unwrap();
+            tx.txid = decoded.txid();
+            tx.wtxid = decoded.wtxid();
+        }
+        let wtxids: Vec<[u8; 32]> = template.transactions.iter().map(|tx| tx.wtxid).collect();
+        template.coinbase_builder = CoinbaseBuilder::new(network, template.height, address)
+            .with_witness_merkle_root(compute_witness_merkle_root(&wtxids));
+        template.update_extra_nonce([0u8; 8]);
+        solve(&mut template.header);
+        assert_eq!(template.validate(1_700_000_000), []);
+    }
+
+    #[test]
+    fn test_regtest_blocks_are_valid() {
+        // Low heights use the OP_1..OP_16 height encoding
+        let network = Network::Regtest;
+        for height in [1, 16, 17, 151] {
+            let address = validate_address("bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080", network).unwrap();
+            let builder = CoinbaseBuilder::new(network, height, address);
+            let reward = network.block_subsidy(height);
+            let mut template =
+                BlockTemplate::new(network, height, [0x33; 32], network.pow_limit_bits(), 1_700_000_000, builder, reward)
+                    .unwrap();
+            solve(&mut template.header);
+            assert_eq!(template.validate(1_700_000_000), [], "height {}", height);
+        }
+    }
+
+    #[test]
+    fn test_header_violations() {
+        let mut block = decode_block_hex(GENESIS_BLOCK_HEX).unwrap();
+        block.header.nonce += 1;
+        assert_eq!(validate_block(&block, &mainnet_context(0)), [Violation::HighHash]);
+
+        block.header.bits = 0x1d80ffff;
+        assert_eq!(validate_block(&block, &mainnet_context(0)), [Violation::InvalidBits]);
+
+        // Easier than the mainnet limit, though the hash would meet it
+        block.header.bits = 0x207fffff;
+        assert_eq!(validate_block(&block, &mainnet_context(0)), [Violation::InvalidBits]);
+
+        let block = decode_block_hex(GENESIS_BLOCK_HEX).unwrap();
+        let context = ValidationContext { min_time: 1231006506, now: 1231006505 - 7201, ..mainnet_context(0) };
+        assert_eq!(validate_block(&block, &context), [Violation::TimeTooOld, Violation::TimeTooNew]);
+    }
+
+    #[test]
+    fn test_coinbase_violations() {
+        // Over-paying coinbase: the merkle root changes too
+        let mut block = decode_block_hex(BLOCK_170_HEX).unwrap();
+        block.transactions[0].outputs[0].value += 1;
+        assert_eq!(
+            validate_block(&block, &mainnet_context(170)),
+            [
+                Violation::BadMerkleRoot,
+                Violation::BadCoinbaseAmount { allowed: 5_000_000_000, actual: 5_000_000_001 },
+            ]
+        );
+
+        // Fees raise the limit
+        let context = ValidationContext { fees: 1, ..mainnet_context(170) };
+        assert_eq!(validate_block(&block, &context), [Violation::BadMerkleRoot]);
+
+        // Past the BIP34 activation the genesis-era scriptSig is not enough
+        let block = decode_block_hex(BLOCK_170_HEX).unwrap();
+        let context = ValidationContext { fees: 2_500_000_000, ..mainnet_context(300_000) };
+        assert_eq!(validate_block(&block, &context), [Violation::BadCoinbaseHeight]);
+
+        // The coinbase must come first, and only once
+        let mut block = decode_block_hex(BLOCK_170_HEX).unwrap();
+        block.transactions.swap(0, 1);
+        let violations = validate_block(&block, &mainnet_context(170));
+        assert!(violations.contains(&Violation::FirstNotCoinbase));
+        assert!(violations.contains(&Violation::MultipleCoinbases));
+    }
+
+    #[test]
+    fn test_witness_violations() {
+        let template = solved_template(1000);
+        let good = decode_block(&template.serialize_block()).unwrap();
+        let context = ValidationContext { network: Network::Regtest, ..mainnet_context(1000) };
+
+        // Tampered commitment (the txid changes, so re-mine)
+        let mut block = good.clone();
+        block.transactions[0].outputs[1].script_pubkey[10] ^= 1;
+        block.header.merkle_root = block.transactions[0].txid();
+        solve(&mut block.header);
+        assert_eq!(validate_block(&block, &context), [Violation::BadWitnessCommitment]);
+
+        // Missing reserved value (the witness is not part of the txid)
+        let mut block = good.clone();
+        block.transactions[0].inputs[0].witness.clear();
+        assert_eq!(validate_block(&block, &context), [Violation::BadWitnessNonce]);
+
+        // Witness data but no commitment output
+        let mut block = good.clone();
+        block.transactions[0].outputs.pop();
+        block.transactions[0].outputs.push(TxOut { value: 0, script_pubkey: alloc::vec![0x6a] });
+        block.header.merkle_root = block.transactions[0].txid();
+        solve(&mut block.header);
+        assert_eq!(validate_block(&block, &context), [Violation::UnexpectedWitness]);
+    }
+}
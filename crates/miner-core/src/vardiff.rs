@@ -0,0 +1,235 @@
+//! Variable share difficulty (vardiff).
+//!
+//! A fixed share difficulty gives a slow CPU a share every few minutes and a
+//! fast one thousands per second. [`Vardiff`] watches how much work comes in
+//! and moves the difficulty so shares arrive at a steady rate, the way pools
+//! tune each connection.
+//!
+//! The controller has no clock of its own: callers pass the current time in
+//! milliseconds, so it works the same in a browser, a CLI or a server.
+
+use crate::difficulty::{difficulty_to_target, expected_hashes};
+use crate::hash::MiningResult;
+
+/// Default share rate to aim for.
+pub const DEFAULT_SHARES_PER_MINUTE: f64 = 20.0;
+
+/// Default time between difficulty adjustments.
+pub const DEFAULT_RETARGET_INTERVAL_MS: u64 = 30_000;
+
+/// Adjusts share difficulty to hit a target share rate.
+///
+/// Each retarget interval it estimates the difficulty that would have given
+/// the target rate. When hash counts are known (local mining) the estimate
+/// comes from the hash rate, which is accurate even with few shares. Without
+/// them (a pool server seeing only submissions) it scales the difficulty by
+/// the observed share rate. The difficulty only moves when the estimate is
+/// off by more than the allowed variance, and by at most `max_step` times
+/// per interval.
+#[derive(Debug, Clone)]
+pub struct Vardiff {
+    difficulty: f64,
+    shares_per_minute: f64,
+    retarget_interval_ms: u64,
+    variance: f64,
+    max_step: f64,
+    min_difficulty: f64,
+    max_difficulty: f64,
+    /// Start of the current window (`None` until the first record).
+    window_start_ms: Option<u64>,
+    window_hashes: u64,
+    window_shares: u64,
+}
+
+impl Vardiff {
+    /// Start at `difficulty` with the default rate and interval.
+    pub fn new(difficulty: f64) -> Self {
+        Vardiff {
+            difficulty,
+            shares_per_minute: DEFAULT_SHARES_PER_MINUTE,
+            retarget_interval_ms: DEFAULT_RETARGET_INTERVAL_MS,
+            variance: 0.3,
+            max_step: 4.0,
+            min_difficulty: 0.0,
+            max_difficulty: f64::MAX,
+            window_start_ms: None,
+            window_hashes: 0,
+            window_shares: 0,
+        }
+    }
+
+    /// Aim for `shares_per_minute` shares.
+    pub fn with_shares_per_minute(mut self, shares_per_minute: f64) -> Self {
+        self.shares_per_minute = shares_per_minute;
+        self
+    }
+
+    /// Adjust at most once per `interval_ms`.
+    pub fn with_retarget_interval(mut self, interval_ms: u64) -> Self {
+        self.retarget_interval_ms = interval_ms;
+        self
+    }
+
+    /// Leave the difficulty alone while the estimate is within `variance`
+    /// (a fraction, e.g. 0.3 for 30%) of it.
+    pub fn with_variance(mut self, variance: f64) -> Self {
+        self.variance = variance;
+        self
+    }
+
+    /// Change the difficulty by at most a factor of `max_step` per interval.
+    pub fn with_max_step(mut self, max_step: f64) -> Self {
+        self.max_step = max_step;
+        self
+    }
+
+    /// Keep the difficulty between `min` and `max`.
+    pub fn with_bounds(mut self, min: f64, max: f64) -> Self {
+        self.min_difficulty = min;
+        self.max_difficulty = max;
+        self.difficulty = self.difficulty.clamp(min, max);
+        self
+    }
+
+    /// The current share difficulty.
+    pub fn difficulty(&self) -> f64 {
+        self.difficulty
+    }
+
+    /// The 256-bit target for the current share difficulty.
+    pub fn share_target(&self) -> [u8; 32] {
+        difficulty_to_target(self.difficulty)
+    }
+
+    /// Set the difficulty and start a new window.
+    pub fn set_difficulty(&mut self, difficulty: f64) {
+        self.difficulty = difficulty.clamp(self.min_difficulty, self.max_difficulty);
+        self.window_start_ms = None;
+        self.window_hashes = 0;
+        self.window_shares = 0;
+    }
+
+    /// Record a mining batch. Returns the new difficulty if it changed.
+    ///
+    /// A batch reports at most one share, so the hash count drives the
+    /// estimate here.
+    pub fn record_result(&mut self, result: &MiningResult, now_ms: u64) -> Option<f64> {
+        self.record(result.hashes_computed, result.share_found as u64, now_ms)
+    }
+
+    /// Record `hashes` (0 if unknown) and `shares` done since the last call.
+    /// Returns the new difficulty if it changed.
+    pub fn record(&mut self, hashes: u64, shares: u64, now_ms: u64) -> Option<f64> {
+        let start = *self.window_start_ms.get_or_insert(now_ms);
+        self.window_hashes += hashes;
+        self.window_shares += shares;
+
+        let elapsed_ms = now_ms.saturating_sub(start);
+        if elapsed_ms < self.retarget_interval_ms.max(1) {
+            return None;
+        }
+
+        let minutes = elapsed_ms as f64 / 60_000.0;
+        let estimate = if self.window_hashes > 0 {
+            let hashes_per_share = self.window_hashes as f64 / minutes / self.shares_per_minute;
+            hashes_per_share / expected_hashes(1.0)
+        } else {
+            self.difficulty * (self.window_shares as f64 / minutes) / self.shares_per_minute
+        };
+
+        self.window_start_ms = Some(now_ms);
+        self.window_hashes = 0;
+        self.window_shares = 0;
+
+        let ratio = estimate / self.difficulty;
+        if (ratio - 1.0).abs() <= self.variance || ratio.is_nan() {
+            return None;
+        }
+
+        let step = ratio.clamp(1.0 / self.max_step, self.max_step);
+        let difficulty = (self.difficulty * step).clamp(self.min_difficulty, self.max_difficulty);
+        if difficulty == self.difficulty {
+            return None;
+        }
+        self.difficulty = difficulty;
+        Some(difficulty)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feed `hashes_per_second` for `seconds` in one-second batches.
+    fn run(vardiff: &mut Vardiff, start_ms: u64, seconds: u64, hashes_per_second: u64) -> Option<f64> {
+        let mut changed = None;
+        for second in 0..=seconds {
+            if let Some(difficulty) = vardiff.record(hashes_per_second, 0, start_ms + second * 1000) {
+                changed = Some(difficulty);
+            }
+        }
+        changed
+    }
+
+    #[test]
+    fn test_converges_on_hash_rate() {
+        // 20 shares a minute at 1 MH/s is one share per 3 million hashes
+        let mut vardiff = Vardiff::new(1.0);
+        let ideal = 3_000_000.0 / expected_hashes(1.0);
+
+        // Far too hard: steps down four times per interval until close
+        let mut now = 0;
+        for _ in 0..10 {
+            run(&mut vardiff, now, 30, 1_000_000);
+            now += 31_000;
+        }
+        let difficulty = vardiff.difficulty();
+        assert!((ideal / difficulty - 1.0).abs() <= 0.3, "{} vs {}", difficulty, ideal);
+
+        // Once there, it stays put
+        assert_eq!(run(&mut vardiff, now, 30, 1_000_000), None);
+        assert_eq!(vardiff.difficulty(), difficulty);
+    }
+
+    #[test]
+    fn test_step_and_bounds() {
+        let mut vardiff = Vardiff::new(1.0).with_bounds(0.5, 2.0);
+
+        // No change before a full interval
+        assert_eq!(vardiff.record(1_000_000_000, 0, 0), None);
+        assert_eq!(vardiff.record(1_000_000_000, 0, 29_999), None);
+
+        // A huge hash rate moves at most four times, then hits the bound
+        let mut vardiff = Vardiff::new(1.0);
+        assert_eq!(run(&mut vardiff, 0, 30, 1 << 40), Some(4.0));
+        let mut vardiff = Vardiff::new(1.0).with_bounds(0.5, 2.0);
+        assert_eq!(run(&mut vardiff, 0, 30, 1 << 40), Some(2.0));
+        assert_eq!(run(&mut vardiff, 31_000, 30, 1 << 40), None);
+    }
+
+    #[test]
+    fn test_share_count_without_hashes() {
+        // A pool server only sees shares: 40 a minute is twice the target
+        let mut vardiff = Vardiff::new(8.0).with_retarget_interval(60_000);
+        for second in 0..60 {
+            vardiff.record(0, if second % 3 == 0 { 2 } else { 0 }, second * 1000);
+        }
+        assert_eq!(vardiff.record(0, 0, 60_000), Some(16.0));
+
+        // No shares at all: ease off by the maximum step
+        assert_eq!(vardiff.record(0, 0, 120_000), Some(4.0));
+    }
+
+    #[test]
+    fn test_record_result() {
+        let mut vardiff = Vardiff::new(1.0).with_shares_per_minute(60.0).with_retarget_interval(1000);
+        let batch = MiningResult::not_found(0x2000_0000, 1 << 20);
+        assert_eq!(vardiff.record_result(&batch, 0), None);
+        assert_eq!(vardiff.record_result(&batch, 1000), Some(0.25));
+        assert_eq!(vardiff.share_target(), difficulty_to_target(0.25));
+
+        vardiff.set_difficulty(3.0);
+        assert_eq!(vardiff.difficulty(), 3.0);
+        assert_eq!(vardiff.record_result(&batch, 5000), None);
+    }
+}
@@ -21,6 +21,8 @@ impl BlockchainApi {
         let base_url = match network {
             "mainnet" | "main" => "https://mempool.space/api".to_string(),
             "testnet4" | "testnet" => "https://mempool.space/testnet4/api".to_string(),
+            "regtest" => "http://127.0.0.1:3002".to_string(),
+            "signet" => "https://mempool.space/signet/api".to_string(),
             _ => "https://mempool.space/api".to_string(),
         };
 
@@ -50,13 +52,22 @@ impl BlockchainApi {
         self.fetch_json(&url).await
     }
 
+    /// Get the hash of the block at a height.
+    pub async fn get_block_hash(&self, height: u32) -> Result<String, JsValue> {
+        let url = format!("{}/block-height/{}", self.base_url, height);
+        self.fetch_text(&url).await
+    }
+
     /// Get the current difficulty adjustment data.
     pub async fn get_difficulty_adjustment(&self) -> Result<JsValue, JsValue> {
         let url = format!("{}/v1/difficulty-adjustment", self.base_url);
         self.fetch_json(&url).await
     }
 
-    /// Submit a raw transaction or block.
+    /// Submit a raw transaction.
+    ///
+    /// The `/tx` endpoint only relays transactions; found blocks have to go
+    /// to a node via `submitblock` (see `miner_core::rpc`).
     pub async fn submit_tx(&self, hex: &str) -> Result<String, JsValue> {
         let url = format!("{}/tx", self.base_url);
         self.post_text(&url, hex).await
@@ -2,10 +2,13 @@
 
 use wasm_bindgen::prelude::*;
 use miner_core::{
-    validate_address, BlockTemplate, CoinbaseBuilder, Network, mine_batch,
+    validate_address, BlockHeader, BlockTemplate, CoinbaseBuilder, Network, Vardiff, mine_batch,
+    coinbase::{DEFAULT_COINBASE_TAG, MAX_COINBASE_TAG_SIZE},
     hash::{count_leading_zeros, hash_to_display_hex},
-    network::SHARE_MIN_LEADING_ZEROS,
-    difficulty::{bits_to_difficulty, format_difficulty},
+    network::SHARE_DIFFICULTY,
+    difficulty::{
+        bits_to_difficulty, difficulty_to_target, format_difficulty, hash_difficulty, next_work_required,
+    },
 };
 use crate::state::{MiningStats, TemplateInfo, MiningResultInfo};
 
@@ -30,6 +33,14 @@ pub struct Miner {
     extra_nonce: u64,
     /// Best hash found so far.
     best_hash: Option<[u8; 32]>,
+    /// Hashes at or below this target count as shares.
+    share_target: [u8; 32],
+    /// Keeps the share rate steady across hardware.
+    vardiff: Vardiff,
+    /// Tag identifying this miner in the coinbase scriptSig.
+    coinbase_tag: Vec<u8>,
+    /// Arbitrary bytes after the tag.
+    coinbase_data: Vec<u8>,
 }
 
 #[wasm_bindgen]
@@ -38,7 +49,7 @@ impl Miner {
     ///
     /// # Arguments
     /// * `address` - The Bitcoin address to receive mining rewards
-    /// * `network` - The network ("mainnet" or "testnet4")
+    /// * `network` - The network ("mainnet", "testnet4", "regtest" or "signet")
     #[wasm_bindgen(constructor)]
     pub fn new(address: &str, network: &str) -> Result<Miner, JsValue> {
         let net = Network::from_str(network)
@@ -57,6 +68,10 @@ impl Miner {
             current_nonce: 0,
             extra_nonce: 0,
             best_hash: None,
+            share_target: difficulty_to_target(SHARE_DIFFICULTY),
+            vardiff: Vardiff::new(SHARE_DIFFICULTY),
+            coinbase_tag: DEFAULT_COINBASE_TAG.to_vec(),
+            coinbase_data: Vec::new(),
         })
     }
 
@@ -72,6 +87,29 @@ impl Miner {
         }
     }
 
+    /// Compute the difficulty bits of the block after the tip.
+    ///
+    /// # Arguments
+    /// * `tip_height` - The current tip block height
+    /// * `tip_bits` / `tip_time` - Bits and timestamp of the tip block
+    /// * `period_bits` / `period_time` - Bits and timestamp of the first block
+    ///   of the tip's difficulty period (height `tip_height - tip_height % 2016`)
+    /// * `timestamp` - The new block's timestamp
+    #[wasm_bindgen]
+    pub fn next_block_bits(
+        &self,
+        tip_height: u32,
+        tip_bits: u32,
+        tip_time: u32,
+        period_bits: u32,
+        period_time: u32,
+        timestamp: u32,
+    ) -> u32 {
+        let tip = BlockHeader::new([0u8; 32], [0u8; 32], tip_time, tip_bits);
+        let period_start = BlockHeader::new([0u8; 32], [0u8; 32], period_time, period_bits);
+        next_work_required(self.network, tip_height, &tip, &period_start, timestamp)
+    }
+
     /// Build a block template from API data.
     ///
     /// # Arguments
@@ -113,11 +151,7 @@ impl Miner {
         let reward = self.network.block_subsidy(height);
 
         // Build coinbase
-        let coinbase_builder = CoinbaseBuilder::new(
-            self.network,
-            height,
-            self.address.clone(),
-        ).with_extra_nonce(self.extra_nonce.to_le_bytes());
+        let coinbase_builder = self.coinbase_builder(height);
 
         // Create template
         let template = BlockTemplate::new(
@@ -128,7 +162,8 @@ impl Miner {
             ts,
             coinbase_builder,
             reward,
-        );
+        )
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
         // Calculate difficulty
         let difficulty = bits_to_difficulty(bits);
@@ -163,8 +198,15 @@ impl Miner {
     /// Mining result with share/block found status and statistics.
     #[wasm_bindgen]
     pub fn mine_batch(&mut self, batch_size: u32) -> Result<JsValue, JsValue> {
-        let template = self.template.as_mut()
-            .ok_or_else(|| JsValue::from_str("No template built"))?;
+        self.mine_next_batch(batch_size)
+            .map_err(JsValue::from_str)?
+            .to_js()
+    }
+
+    /// Mine the next batch of nonces, rolling the extra nonce once the
+    /// nonce space of the current coinbase is exhausted.
+    fn mine_next_batch(&mut self, batch_size: u32) -> Result<MiningResultInfo, &'static str> {
+        let template = self.template.as_mut().ok_or("No template built")?;
 
         // Get header without nonce for efficient hashing
         template.header.nonce = self.current_nonce;
@@ -174,7 +216,7 @@ impl Miner {
         let result = mine_batch(
             &header_without_nonce,
             &template.target,
-            SHARE_MIN_LEADING_ZEROS,
+            &self.share_target,
             self.current_nonce,
             batch_size,
         );
@@ -184,12 +226,16 @@ impl Miner {
         self.current_nonce = self.current_nonce.saturating_add(batch_size);
         self.stats.current_nonce = self.current_nonce;
 
-        // Update elapsed time
+        // Update elapsed time and retune the share difficulty
         if self.start_time > 0.0 {
             let now = js_sys::Date::now();
             self.stats.elapsed_ms = now - self.start_time;
             self.stats.update_hash_rate();
+            if self.vardiff.record_result(&result, now as u64).is_some() {
+                self.share_target = self.vardiff.share_target();
+            }
         }
+        self.stats.share_difficulty = self.vardiff.difficulty();
 
         // Create result info
         let mut info = MiningResultInfo {
@@ -198,25 +244,26 @@ impl Miner {
             nonce: result.nonce,
             hash: None,
             leading_zeros: 0,
+            difficulty: 0.0,
             hashes_computed: result.hashes_computed,
         };
 
         // Handle found results
         if let (Some(nonce), Some(hash)) = (result.nonce, result.hash) {
             let leading_zeros = count_leading_zeros(&hash);
+            let difficulty = hash_difficulty(&hash);
             info.leading_zeros = leading_zeros;
+            info.difficulty = difficulty;
             info.hash = Some(hash_to_display_hex(&hash));
 
             // Update best hash if this is better
-            let is_better = match &self.best_hash {
-                None => true,
-                Some(best) => hash < *best,
-            };
+            let is_better = self.best_hash.is_none() || difficulty > self.stats.best_difficulty;
 
             if is_better {
                 self.best_hash = Some(hash);
                 self.stats.best_hash = Some(hash_to_display_hex(&hash));
                 self.stats.best_leading_zeros = leading_zeros;
+                self.stats.best_difficulty = difficulty;
             }
 
             if result.share_found {
@@ -230,14 +277,54 @@ impl Miner {
             }
         }
 
-        // Check if we need to update extra nonce (nonce overflow)
-        if self.current_nonce == u32::MAX {
+        // Nonce space exhausted: new extra nonce, new coinbase and merkle root.
+        // A found block keeps its template so it can still be submitted.
+        if self.current_nonce == u32::MAX && !result.block_found {
             self.extra_nonce += 1;
+            template.update_extra_nonce(self.extra_nonce.to_le_bytes());
             self.current_nonce = 0;
-            // Would need to rebuild template here with new extra_nonce
+            self.stats.current_nonce = 0;
         }
 
-        info.to_js()
+        Ok(info)
+    }
+
+    /// Set the tag and data written into the coinbase scriptSig.
+    ///
+    /// Takes effect from the next template.
+    ///
+    /// # Arguments
+    /// * `tag` - Text identifying the miner, e.g. "/alice/"
+    /// * `data_hex` - Arbitrary bytes after the tag, in hex (empty for none)
+    #[wasm_bindgen]
+    pub fn set_coinbase_tag(&mut self, tag: &str, data_hex: &str) -> Result<(), JsValue> {
+        let data = hex::decode(data_hex).map_err(|_| JsValue::from_str("Invalid coinbase data hex"))?;
+        if tag.len() + data.len() > MAX_COINBASE_TAG_SIZE {
+            return Err(JsValue::from_str(&format!(
+                "Coinbase tag and data are {} bytes; at most {} fit in the scriptSig",
+                tag.len() + data.len(),
+                MAX_COINBASE_TAG_SIZE
+            )));
+        }
+        self.coinbase_tag = tag.as_bytes().to_vec();
+        self.coinbase_data = data;
+        Ok(())
+    }
+
+    /// Set the pool difficulty a hash needs to count as a share.
+    ///
+    /// Vardiff keeps adjusting it from there while mining.
+    #[wasm_bindgen]
+    pub fn set_share_difficulty(&mut self, difficulty: f64) {
+        self.vardiff.set_difficulty(difficulty);
+        self.share_target = self.vardiff.share_target();
+        self.stats.share_difficulty = self.vardiff.difficulty();
+    }
+
+    /// Set the share rate vardiff aims for.
+    #[wasm_bindgen]
+    pub fn set_shares_per_minute(&mut self, shares_per_minute: f64) {
+        self.vardiff = self.vardiff.clone().with_shares_per_minute(shares_per_minute);
     }
 
     /// Start mining.
@@ -281,6 +368,19 @@ impl Miner {
         }
     }
 
+    /// Check the current block against the consensus rules.
+    ///
+    /// Returns one message per violation; an empty list means the block can
+    /// be submitted.
+    #[wasm_bindgen]
+    pub fn validate_block(&self) -> Vec<String> {
+        let now = (js_sys::Date::now() / 1000.0) as u32;
+        match &self.template {
+            Some(template) => template.validate(now).iter().map(|v| v.to_string()).collect(),
+            None => vec!["No template built".to_string()],
+        }
+    }
+
     /// Reset the miner for a new block.
     #[wasm_bindgen]
     pub fn reset(&mut self) {
@@ -305,8 +405,135 @@ impl Miner {
     }
 }
 
+impl Miner {
+    /// A coinbase builder for `height` with this miner's address and tag.
+    fn coinbase_builder(&self, height: u32) -> CoinbaseBuilder {
+        CoinbaseBuilder::new(self.network, height, self.address.clone())
+            .with_extra_nonce(self.extra_nonce.to_le_bytes())
+            .with_tag(self.coinbase_tag.clone())
+            .with_script_data(self.coinbase_data.clone())
+    }
+}
+
 /// Log to the browser console.
 #[wasm_bindgen]
 pub fn console_log(message: &str) {
     web_sys::console::log_1(&JsValue::from_str(message));
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use miner_core::merkle::compute_merkle_root;
+
+    /// A miner with a coinbase-only template, built without touching JS.
+    fn miner_with_template(bits: u32) -> Miner {
+        let mut miner = Miner::new("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq", "mainnet").unwrap();
+        let builder = miner.coinbase_builder(840_000);
+        miner.template = Some(BlockTemplate::new(
+            miner.network,
+            840_000,
+            [0x11; 32],
+            bits,
+            1_700_000_000,
+            builder,
+            312_500_000,
+        ).unwrap());
+        miner
+    }
+
+    #[test]
+    fn test_extra_nonce_rolls_over_when_nonces_run_out() {
+        let mut miner = miner_with_template(0x1d00ffff);
+        let old_root = miner.template.as_ref().unwrap().header.merkle_root;
+
+        // Simulate a nearly exhausted nonce space
+        miner.current_nonce = u32::MAX - 100;
+        let info = miner.mine_next_batch(1000).unwrap();
+        assert_eq!(info.hashes_computed, 100);
+        assert!(!info.block_found);
+
+        let template = miner.template.as_ref().unwrap();
+        assert_eq!(miner.extra_nonce, 1);
+        assert_eq!(miner.current_nonce, 0);
+        assert_ne!(template.header.merkle_root, old_root);
+        assert_eq!(template.header.merkle_root, compute_merkle_root(&[template.coinbase.txid]));
+        assert!(template.coinbase.raw_tx.windows(8).any(|w| w == 1u64.to_le_bytes()));
+
+        // Mining continues from the start of the new nonce space
+        let info = miner.mine_next_batch(10).unwrap();
+        assert_eq!(info.hashes_computed, 10);
+        assert_eq!(miner.current_nonce, 10);
+        assert_eq!(miner.extra_nonce, 1);
+    }
+
+    #[test]
+    fn test_each_rollover_changes_the_merkle_root() {
+        let mut miner = miner_with_template(0x1d00ffff);
+        let mut roots = vec![miner.template.as_ref().unwrap().header.merkle_root];
+
+        for extra_nonce in 1..=3 {
+            miner.current_nonce = u32::MAX - 1;
+            miner.mine_next_batch(16).unwrap();
+            assert_eq!(miner.extra_nonce, extra_nonce);
+
+            let root = miner.template.as_ref().unwrap().header.merkle_root;
+            assert!(!roots.contains(&root));
+            roots.push(root);
+        }
+    }
+
+    #[test]
+    fn test_block_found_at_end_of_nonces_keeps_template() {
+        let mut miner = miner_with_template(0x1d00ffff);
+        // Every hash meets this target
+        miner.template.as_mut().unwrap().target = [0xFF; 32];
+        let root = miner.template.as_ref().unwrap().header.merkle_root;
+
+        miner.current_nonce = u32::MAX - 1;
+        let info = miner.mine_next_batch(1).unwrap();
+        assert!(info.block_found);
+
+        // The nonce space is used up, but the solved template must survive
+        let template = miner.template.as_ref().unwrap();
+        assert_eq!(miner.extra_nonce, 0);
+        assert_eq!(template.header.merkle_root, root);
+        assert_eq!(template.header.nonce, u32::MAX - 1);
+        assert_eq!(Some(template.header.hash()), miner.best_hash);
+    }
+
+    #[test]
+    fn test_share_difficulty() {
+        let mut miner = miner_with_template(0x1d00ffff);
+
+        // About one hash in sixteen is a share at this difficulty
+        miner.set_share_difficulty(1.0 / (1u64 << 28) as f64);
+        let info = miner.mine_next_batch(1000).unwrap();
+        assert!(info.share_found);
+        assert!(info.difficulty >= 1.0 / (1u64 << 28) as f64);
+        assert_eq!(miner.stats.best_difficulty, info.difficulty);
+
+        // Nothing reaches difficulty one in a thousand hashes
+        let best = miner.stats.best_difficulty;
+        miner.set_share_difficulty(1.0);
+        let info = miner.mine_next_batch(1000).unwrap();
+        assert!(!info.share_found);
+        assert_eq!(miner.stats.best_difficulty, best);
+    }
+
+    #[test]
+    fn test_mine_without_template() {
+        let mut miner = Miner::new("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq", "mainnet").unwrap();
+        assert_eq!(miner.mine_next_batch(10).unwrap_err(), "No template built");
+    }
+
+    #[test]
+    fn test_coinbase_tag() {
+        let mut miner = Miner::new("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq", "mainnet").unwrap();
+        assert!(miner.set_coinbase_tag("/alice/", "c0ffee").is_ok());
+
+        let coinbase = miner.coinbase_builder(840_000).build(312_500_000).unwrap();
+        assert!(coinbase.raw_tx.windows(10).any(|w| w == b"/alice/\xc0\xff\xee"));
+        assert!(!coinbase.raw_tx.windows(DEFAULT_COINBASE_TAG.len()).any(|w| w == DEFAULT_COINBASE_TAG));
+    }
+}
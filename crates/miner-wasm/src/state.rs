@@ -22,6 +22,10 @@ pub struct MiningStats {
     pub best_hash: Option<String>,
     /// Number of leading zeros in best hash.
     pub best_leading_zeros: u32,
+    /// Pool difficulty of the best hash.
+    pub best_difficulty: f64,
+    /// Current share difficulty.
+    pub share_difficulty: f64,
 }
 
 impl MiningStats {
@@ -101,6 +105,8 @@ pub struct MiningResultInfo {
     pub hash: Option<String>,
     /// Number of leading zeros in hash.
     pub leading_zeros: u32,
+    /// Pool difficulty of the hash.
+    pub difficulty: f64,
     /// Hashes computed in this batch.
     pub hashes_computed: u64,
 }
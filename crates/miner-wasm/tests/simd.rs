@@ -0,0 +1,39 @@
+//! Checks the WebAssembly SIMD backend in a real wasm runtime.
+//!
+//! Run with `wasm-pack test --node crates/miner-wasm` (see
+//! `scripts/test-wasm.sh`).
+
+#![cfg(target_arch = "wasm32")]
+
+use miner_core::hash::{mine_batch_with, HeaderMidstate};
+use miner_core::simd::{Backend, MAX_LANES};
+use wasm_bindgen_test::wasm_bindgen_test;
+
+#[wasm_bindgen_test]
+fn test_simd128_is_built_and_detected() {
+    assert!(Backend::Simd128.is_supported(), "built without +simd128");
+    assert_eq!(Backend::detect(), Backend::Simd128);
+}
+
+#[wasm_bindgen_test]
+fn test_simd128_matches_scalar() {
+    let header = [0x42; 76];
+    let midstate = HeaderMidstate::new(&header);
+    let mut out = [[0u8; 32]; MAX_LANES];
+
+    // Includes a batch that wraps around the top of the nonce space
+    for start in [0u32, 0xDEAD_BEEF, u32::MAX - 1] {
+        assert_eq!(Backend::Simd128.hash_lanes(&midstate, start, &mut out), 4);
+        for (i, hash) in out.iter().take(4).enumerate() {
+            assert_eq!(*hash, midstate.hash(start.wrapping_add(i as u32)));
+        }
+    }
+
+    // Whole batches agree too, including the scalar tail
+    let mut target = [0xFF; 32];
+    target[0] = 0x00;
+    target[1] = 0x7F;
+    let simd = mine_batch_with(Backend::Simd128, &header, &target, &[0u8; 32], 0, 1_003);
+    let scalar = mine_batch_with(Backend::Scalar, &header, &target, &[0u8; 32], 0, 1_003);
+    assert_eq!((simd.nonce, simd.hash, simd.hashes_computed), (scalar.nonce, scalar.hash, scalar.hashes_computed));
+}
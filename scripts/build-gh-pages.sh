@@ -10,17 +10,21 @@ cd "$PROJECT_ROOT"
 
 echo "Building Goblin Mining Co. for GitHub Pages..."
 
-# Build the WASM module
-echo "Building WASM module..."
+# Build the WASM modules: SIMD (per .cargo/config.toml) and a scalar
+# fallback for engines without WebAssembly SIMD
+echo "Building WASM modules..."
 wasm-pack build --target web crates/miner-wasm
+RUSTFLAGS="-C target-feature=-simd128" wasm-pack build --target web --out-dir pkg-scalar crates/miner-wasm
 
-# Copy WASM output to web/pkg
-echo "Copying WASM to web/pkg..."
-rm -rf web/pkg
-mkdir -p web/pkg
-cp crates/miner-wasm/pkg/miner_wasm.js web/pkg/
-cp crates/miner-wasm/pkg/miner_wasm_bg.wasm web/pkg/
-cp crates/miner-wasm/pkg/miner_wasm.d.ts web/pkg/ 2>/dev/null || true
+# Copy WASM output to web/pkg and web/pkg-scalar
+echo "Copying WASM to web/pkg and web/pkg-scalar..."
+for pkg in pkg pkg-scalar; do
+    rm -rf web/$pkg
+    mkdir -p web/$pkg
+    cp crates/miner-wasm/$pkg/miner_wasm.js web/$pkg/
+    cp crates/miner-wasm/$pkg/miner_wasm_bg.wasm web/$pkg/
+    cp crates/miner-wasm/$pkg/miner_wasm.d.ts web/$pkg/ 2>/dev/null || true
+done
 
 echo ""
 echo "Build complete! The 'web' folder is ready for GitHub Pages deployment."
@@ -15,14 +15,19 @@ fi
 # Navigate to project root
 cd "$(dirname "$0")/.."
 
-# Build the WASM package
+# Build the WASM packages: SIMD (per .cargo/config.toml) and a scalar
+# fallback for engines without WebAssembly SIMD
 echo "Building WASM package..."
 wasm-pack build crates/miner-wasm --target web --out-dir ../../web/pkg
+echo "Building scalar WASM package..."
+RUSTFLAGS="-C target-feature=-simd128" wasm-pack build crates/miner-wasm --target web --out-dir ../../web/pkg-scalar
 
 # Clean up unnecessary files
-rm -f web/pkg/.gitignore
-rm -f web/pkg/package.json
-rm -f web/pkg/README.md
+for pkg in web/pkg web/pkg-scalar; do
+    rm -f $pkg/.gitignore
+    rm -f $pkg/package.json
+    rm -f $pkg/README.md
+done
 
 echo "Build complete!"
 echo ""
@@ -0,0 +1,16 @@
+#!/bin/bash
+# Run the WebAssembly tests, which exercise the simd128 hashing backend
+
+set -e
+
+# Check for wasm-pack
+if ! command -v wasm-pack &> /dev/null; then
+    echo "wasm-pack not found. Installing..."
+    cargo install wasm-pack
+fi
+
+# Navigate to project root
+cd "$(dirname "$0")/.."
+
+# .cargo/config.toml enables simd128 for every wasm32 build
+wasm-pack test --node crates/miner-wasm
@@ -205,16 +205,29 @@ function updateNetworkStatsDisplay() {
     }
 }
 
+/**
+ * Whether the browser supports WebAssembly SIMD: validate a tiny module
+ * that uses an i8x16 instruction
+ */
+function wasmSimdSupported() {
+    return WebAssembly.validate(new Uint8Array([
+        0, 97, 115, 109, 1, 0, 0, 0, 1, 5, 1, 96, 0, 1, 123, 3, 2, 1, 0,
+        10, 10, 1, 8, 0, 65, 0, 253, 15, 253, 98, 11,
+    ]));
+}
+
 /**
  * Load the WASM module
  */
 async function loadWasm() {
     try {
-        // Load WASM from pkg directory (copied into web/ for deployment)
-        wasm = await import('../pkg/miner_wasm.js');
+        // Load WASM from the pkg directories (copied into web/ for deployment):
+        // the SIMD build where supported, the scalar one everywhere else
+        const simd = wasmSimdSupported();
+        wasm = await import(simd ? '../pkg/miner_wasm.js' : '../pkg-scalar/miner_wasm.js');
         await wasm.default();
 
-        log(`WASM loaded (v${wasm.version()})`);
+        log(`WASM loaded (v${wasm.version()}, ${simd ? 'SIMD' : 'scalar'} hashing)`);
         isInitialized = true;
     } catch (error) {
         // For development without WASM, use mock
@@ -243,6 +256,9 @@ function useMockMode() {
                 if (address.startsWith('1') && network === 'mainnet') return true;
                 throw new Error('Invalid address');
             }
+            next_block_bits(tipHeight, tipBits) {
+                return tipBits;
+            }
             build_template() {
                 return {
                     height: 875000,
@@ -301,6 +317,9 @@ function useMockMode() {
             async get_tip_height() {
                 return currentNetwork === 'mainnet' ? 875000 : 50000;
             }
+            async get_block_hash(height) {
+                return '0000000000000000000' + Math.random().toString(16).slice(2, 45);
+            }
             async get_block(hash) {
                 return {
                     id: hash,
@@ -741,10 +760,18 @@ async function fetchBlockTemplate() {
         const tipHash = await api.get_tip_hash();
         const tipHeight = await api.get_tip_height();
 
-        // Fetch the tip block to get the actual bits (difficulty target)
+        // The next block's bits depend on the tip and on the first block of
+        // its difficulty period (retargets, testnet4's 20-minute rule)
         const blockData = await api.get_block(tipHash);
-        const bits = blockData.bits;
+        const periodHeight = tipHeight - (tipHeight % 2016);
+        const periodData = periodHeight === tipHeight
+            ? blockData
+            : await api.get_block(await api.get_block_hash(periodHeight));
         const timestamp = Math.floor(Date.now() / 1000);
+        const bits = miner.next_block_bits(
+            tipHeight, blockData.bits, blockData.timestamp,
+            periodData.bits, periodData.timestamp, timestamp
+        );
 
         const templateInfo = miner.build_template(tipHash, tipHeight, bits, timestamp);
 
@@ -855,6 +882,12 @@ async function submitBlock() {
             return;
         }
 
+        const violations = miner.validate_block();
+        if (violations.length > 0) {
+            violations.forEach(v => log(`Invalid block: ${v}`, 'error'));
+            return;
+        }
+
         log('Submitting block...');
         const result = await api.submit_tx(blockHex);
         log(`Block submitted: ${result}`, 'success');